@@ -1,6 +1,6 @@
 use std::rc::Rc;
 
-use web_protocol::{ActionLogEntry, Item, Perspective};
+use web_protocol::{Locale, LoggedAction, Perspective, TurnIndex};
 use yew::{function_component, html, use_context, Html, Properties};
 
 #[derive(Properties, PartialEq)]
@@ -12,29 +12,39 @@ pub fn actionlog(ActionLogProps {}: &ActionLogProps) -> Html {
 
     html! {
         <div class="actionlog">
-            { for perspective.action_log.iter().cloned().map(|action| html! { <Entry {action} /> }) }
+            <div class="current-turn">
+                {format!("Now: {}", turn_text(perspective.turn_index))}
+                if let Some(last) = perspective.last_round {
+                    {format!(" (the game ends after round {})", last)}
+                }
+            </div>
+            { for perspective.action_log.chunk_by(|a, b| a.turn == b.turn).map(|turn| html! {
+                <div class="logged-turn">
+                    <div class="turn">{turn_text(turn[0].turn)}</div>
+                    { for turn.iter().cloned().map(|action| html! { <Entry {action} /> }) }
+                </div>
+            }) }
         </div>
     }
 }
 
+fn turn_text(turn: TurnIndex) -> String {
+    // games from before turns were counted log everything as turn 0
+    if turn.turn == 0 {
+        "Earlier turns".to_string()
+    } else {
+        format!("Round {}, turn {}", turn.round, turn.turn)
+    }
+}
+
 #[derive(Properties, PartialEq)]
 pub struct ActionLogEntryProps {
-    action: ActionLogEntry,
+    action: LoggedAction,
 }
 #[function_component(Entry)]
 fn entry(ActionLogEntryProps { action }: &ActionLogEntryProps) -> Html {
-    let body = match action {
-        &ActionLogEntry::Pass { actor } => format!("{actor} passed."),
-        &ActionLogEntry::AnnounceVictory { actor } => format!("{actor} announced victory."),
-        &ActionLogEntry::UseDiplomat { actor, target, item, success: true } => format!("{actor} asked {target} for a {item}. They exchanged items."),
-        &ActionLogEntry::UseDiplomat { actor, target, item, success: false } => format!("{actor} asked {target} for a {item}, but {target} did not have one."),
-        &ActionLogEntry::UseClairvoyant { actor } => format!("{actor} reordered the item stack."),
-        &ActionLogEntry::TradeOffer { offerer, target, accepted } => format!("{offerer} offered a trade to {target}. The trade was {}.", if accepted { "accepted" } else { "declined" }),
-        &ActionLogEntry::Attack { attacker, target } => format!("{attacker} attacked {target}."),
-        &ActionLogEntry::TradeTrigger { giver, receiver, item: Item::BagGoblet } => format!("{giver} passed a Bag to {receiver}."),
-        &ActionLogEntry::TradeTrigger { giver, receiver, item } => format!("{giver} passed a {item} to {receiver}."),
-        &ActionLogEntry::DonateItem { giver, receiver } => format!("{giver} donates an item to {receiver}."),
-    };
+    let perspective = use_context::<Rc<Perspective>>().unwrap();
+    let body = action.entry.narrate(Locale::English, &perspective.players);
     html! {
         <div class="entry">
             {body}
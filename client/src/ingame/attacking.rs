@@ -1,15 +1,15 @@
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
-use web_protocol::{PerspectiveAttackState, Player, AttackState, Command, Job, Item, AttackSupport, Perspective, AttackWinner, inventory_limit, Buff, BuffScore, BuffSource};
+use web_protocol::{PerspectiveAttackState, SeatId, AttackState, Command, Job, Item, AttackSupport, Perspective, AttackWinner, Buff, BuffScore, BuffSource, BuffTarget, AttackTally, BuffOption, BuffOutcome};
 use yew::prelude::*;
 use super::{CommandButton, DoneLookingBtn, SelectItem, ItemListEntry};
 
 #[derive(Properties, PartialEq)]
 pub struct AttackingProps {
-    pub myself: Player,
-    pub attacker: Player,
-    pub defender: Player,
+    pub myself: SeatId,
+    pub attacker: SeatId,
+    pub defender: SeatId,
     pub state: PerspectiveAttackState,
 }
 #[function_component(Attacking)]
@@ -23,7 +23,7 @@ pub fn attacking(props: &AttackingProps) -> Html {
 
 
     let body = match state {
-        PerspectiveAttackState::Normal(AttackState::WaitingForPriest { passed }) if passed.contains(&myself) => html! { format!("Waiting for other players to use Priest ...") },
+        PerspectiveAttackState::Normal(AttackState::WaitingForPriest { passed }) if passed.contains(&myself) || p.sealed_answer.is_some() => html! { format!("Waiting for other players to use Priest ...") },
         PerspectiveAttackState::Normal(AttackState::WaitingForPriest { .. }) => html! {
             <>
                 {"Use priest?"}
@@ -32,11 +32,11 @@ pub fn attacking(props: &AttackingProps) -> Html {
             </>
         },
         &PerspectiveAttackState::Normal(AttackState::PayingPriest { priest }) if myself == attacker => html! { <PayingPriest {priest} /> },
-        &PerspectiveAttackState::Normal(AttackState::PayingPriest { priest }) => html! { {format!("Waiting for {} to give an item to the Priest ({}) ...", attacker, priest)} },
+        &PerspectiveAttackState::Normal(AttackState::PayingPriest { priest }) => html! { {format!("Waiting for {} to give an item to the Priest ({}) ...", p.name(attacker), p.name(priest))} },
 
         &PerspectiveAttackState::FinishResolvingCredentials { target_faction, target_job } => html! {
             <>
-                <p class="attack-text">{format!("You see that {}'s faction is {:?} and their job is {:?}.", opponent, target_faction, target_job)}</p>
+                <p class="attack-text">{format!("You see that {}'s faction is {:?} and their job is {:?}.", p.name(opponent), target_faction, target_job)}</p>
                 <DoneLookingBtn />
             </>
         },
@@ -116,7 +116,7 @@ pub fn attacking(props: &AttackingProps) -> Html {
                     </>
                 }
             } else {
-                html! { <p>{format!("Waiting for {} to claim a reward ...", winner)}</p> }
+                html! { <p>{format!("Waiting for {} to claim a reward ...", p.name(winner))}</p> }
             }
         }
         &PerspectiveAttackState::Normal(AttackState::FinishResolving { winner, steal_items, three_player_faction_index }) => {
@@ -126,18 +126,18 @@ pub fn attacking(props: &AttackingProps) -> Html {
             };
 
             if steal_items {
-                html! { <p>{format!("Waiting for {} to steal items ...", winner)}</p> }
+                html! { <p>{format!("Waiting for {} to steal items ...", p.name(winner))}</p> }
             } else if let Some(i) = three_player_faction_index {
-                html! { <p>{format!("Waiting for {} to look at faction {} & job ...", winner, i + 1)}</p> }
+                html! { <p>{format!("Waiting for {} to look at faction {} & job ...", p.name(winner), i + 1)}</p> }
             } else {
-                html! { <p>{format!("Waiting for {} to look at faction & job ...", winner)}</p> }
+                html! { <p>{format!("Waiting for {} to look at faction & job ...", p.name(winner))}</p> }
             }
         }
     };
 
     html! {
         <>
-            <p class="attack-text">{format!("{} is attacking {}", props.attacker, props.defender)}</p>
+            <p class="attack-text">{format!("{} is attacking {}", p.name(attacker), p.name(defender))}</p>
             {body}
         </>
     }
@@ -145,7 +145,7 @@ pub fn attacking(props: &AttackingProps) -> Html {
 
 #[derive(Properties, PartialEq)]
 pub struct PayingPriestProps {
-    priest: Player,
+    priest: SeatId,
 }
 #[function_component(PayingPriest)]
 pub fn paying_priest(props: &PayingPriestProps) -> Html {
@@ -155,9 +155,9 @@ pub fn paying_priest(props: &PayingPriestProps) -> Html {
 
     html! {
         <>
-            <p>{format!("Select an item to give to the priest ({})", props.priest)}</p>
+            <p>{format!("Select an item to give to the priest ({})", perspective.name(props.priest))}</p>
             <SelectItem on_change={Callback::from({ let item = item.clone(); move |i| item.set(i) })}>
-                {for perspective.you.items.iter().map(|&i| html_nested! { <ItemListEntry item={i} can_select={true} /> })}
+                {for perspective.you.items.iter().map(|i| html_nested! { <ItemListEntry item={i} can_select={true} /> })}
             </SelectItem>
             <CommandButton command={item.map(move |item| Command::PayPriest { item })} text={"Submit"} />
         </>
@@ -166,7 +166,7 @@ pub fn paying_priest(props: &PayingPriestProps) -> Html {
 
 #[derive(Properties, PartialEq)]
 pub struct StealItemsProps {
-    victim: Player,
+    victim: SeatId,
     target_items: Vec<Item>,
 }
 #[function_component(StealItems)]
@@ -176,20 +176,20 @@ pub fn steal_items(props: &StealItemsProps) -> Html {
 
     let item = use_state(|| None);
     let giveback = use_state(|| None);
-    let need_give_back = perspective.you.items.len() >= inventory_limit(perspective.players.len());
+    let need_give_back = perspective.you.items.len() >= perspective.config.inventory_limit(perspective.players.len());
 
     html! {
         <>
-            <p class="attack-text">{format!("Select an item to steal from {}.", victim)}</p>
+            <p class="attack-text">{format!("Select an item to steal from {}.", perspective.name(victim))}</p>
             <SelectItem on_change={Callback::from({ let item = item.clone(); move |i| item.set(i) })}>
                 {for target_items.iter().map(|&i| html_nested! { <ItemListEntry item={i} can_select={true} /> })}
             </SelectItem>
             {if need_give_back {
                 html! {
                     <>
-                        <p class="attack-text">{format!("Select an item to give back to {}.", victim)}</p>
+                        <p class="attack-text">{format!("Select an item to give back to {}.", perspective.name(victim))}</p>
                         <SelectItem on_change={Callback::from({ let giveback = giveback.clone(); move |i| giveback.set(i) })}>
-                            {for perspective.you.items.iter().map(|&i| html_nested! { <ItemListEntry item={i} can_select={true} /> })}
+                            {for perspective.you.items.iter().map(|i| html_nested! { <ItemListEntry item={i} can_select={true} /> })}
                         </SelectItem>
                     </>
                 }
@@ -204,9 +204,9 @@ pub fn steal_items(props: &StealItemsProps) -> Html {
 
 #[derive(Properties, PartialEq)]
 pub struct AttackOverviewProps {
-    attacker: Player,
-    defender: Player,
-    votes: HashMap<Player, AttackSupport>,
+    attacker: SeatId,
+    defender: SeatId,
+    votes: HashMap<SeatId, AttackSupport>,
     #[prop_or_default]
     buffs: Vec<Buff>,
     #[prop_or_default]
@@ -226,18 +226,16 @@ pub fn attack_overview(props: &AttackOverviewProps) -> Html {
 
     let supporter_list: Vec<_> = attack_supporters.by_ref().take(votes.len()).collect();
 
-    let (attv, defv): (Vec<_>, Vec<_>) = buffs.iter().map(|b| b.raw_score).chain(votes.values().map(|v| v.vote_value())).partition(|&v| v > 0);
-    let attacking_votes = 2 + attv.into_iter().sum::<BuffScore>();
-    let defending_votes = 2 - defv.into_iter().sum::<BuffScore>();
+    let tally = AttackTally::new(votes, buffs);
 
     html! {
         <>
             <ul>
-                <li>{format!("{} is the attacker", attacker)}</li>
-                {for supporter_list.into_iter().map(|p| html! {
-                    <li>{format!("{}: {:?}", p, votes.get(&p).unwrap())} {if hypnotize_btn { html! { <CommandButton text={"Hypnotize"} command={Some(Command::Hypnotize { target: Some(p) })} /> } } else { html! {} }}</li>
+                <li>{format!("{} is the attacker", p.name(attacker))}</li>
+                {for supporter_list.into_iter().map(|s| html! {
+                    <li>{format!("{}: {:?}", p.name(s), votes.get(&s).unwrap())} {if hypnotize_btn { html! { <CommandButton text={"Hypnotize"} command={Some(Command::Hypnotize { target: Some(s) })} /> } } else { html! {} }}</li>
                 })}
-                <li>{format!("{} is the defender", defender)}</li>
+                <li>{format!("{} is the defender", p.name(defender))}</li>
             </ul>
             {if buffs.is_empty() {
                 html! {}
@@ -247,13 +245,13 @@ pub fn attack_overview(props: &AttackOverviewProps) -> Html {
                         <p>{"Active buffs:"}</p>
                         <ul>
                             {for buffs.iter().map(|buff| html! {
-                                <li>{format!("{} uses {:?} ({})", buff.user, buff.source, buff_score(buff.raw_score))}</li>
+                                <li>{format!("{} uses {:?} ({})", p.name(buff.user), buff.source, buff_score(buff.raw_score))}</li>
                             })}
                         </ul>
                     </>
                 }
             }}
-            <p>{format!("Current tally: {} vs {}", buff_score(attacking_votes), buff_score(defending_votes))}</p>
+            <p>{format!("Current tally: {}", tally_text(tally))}</p>
         </>
     }
 }
@@ -262,13 +260,36 @@ fn buff_score(x: BuffScore) -> String {
     format!("{}", (x as f32) / 2.)
 }
 
+fn tally_text(tally: AttackTally) -> String {
+    let result = match tally.winner() {
+        Some(AttackWinner::Attacker) => "attacker wins",
+        Some(AttackWinner::Defender) => "defender wins",
+        None => "nobody wins",
+    };
+    format!("{} vs {} ({})", buff_score(tally.attack), buff_score(tally.defense), result)
+}
+
+fn outcome_text(p: &Perspective, option: &BuffOption) -> String {
+    let buff = match (&option.buff, option.target) {
+        (BuffSource::Job(job), Some(target)) => format!("{:?} on {}", job, p.name(target)),
+        (buff, _) => format!("{:?}", buff),
+    };
+    let outcome = match &option.outcome {
+        BuffOutcome::Tally(tally) => tally_text(*tally),
+        BuffOutcome::Ended => "the fight ends without a winner".to_string(),
+        BuffOutcome::Decided(AttackWinner::Attacker) => "the attacker wins right away".to_string(),
+        BuffOutcome::Decided(AttackWinner::Defender) => "the defender wins right away".to_string(),
+    };
+    format!("{}: {}", buff, outcome)
+}
+
 #[derive(Properties, PartialEq)]
 pub struct ItemsAndJobsProps {
-    attacker: Player,
-    defender: Player,
-    votes: HashMap<Player, AttackSupport>,
+    attacker: SeatId,
+    defender: SeatId,
+    votes: HashMap<SeatId, AttackSupport>,
     buffs: Vec<Buff>,
-    passed: HashSet<Player>,
+    passed: HashSet<SeatId>,
 }
 #[function_component(ItemsAndJobs)]
 pub fn items_and_jobs(props: &ItemsAndJobsProps) -> Html {
@@ -276,7 +297,7 @@ pub fn items_and_jobs(props: &ItemsAndJobsProps) -> Html {
     let p = use_context::<Rc<Perspective>>().unwrap();
 
     let myself = p.players[p.your_player_index].player;
-    let my_turn = !passed.contains(&myself);
+    let my_turn = !passed.contains(&myself) && p.sealed_answer.is_none();
 
     let item = use_state(|| None);
 
@@ -289,12 +310,24 @@ pub fn items_and_jobs(props: &ItemsAndJobsProps) -> Html {
                 html! {
                     <>
                         <p>{"Note: This interface is WIP and lets you issue invalid commands. You should at least get an error message in these cases though."}</p>
+                        {if let Some(outlook) = p.attack_outlook.as_ref().filter(|o| !o.options.is_empty()) {
+                            html! {
+                                <>
+                                    <p>{"What your buffs would do:"}</p>
+                                    <ul>
+                                        {for outlook.options.iter().map(|o| html! { <li>{outcome_text(&p, o)}</li> })}
+                                    </ul>
+                                </>
+                            }
+                        } else {
+                            html! {}
+                        }}
                         <SelectItem on_change={Callback::from({ let item = item.clone(); move |i| item.set(i) })}>
-                            {for p.you.items.iter().map(|&i| html_nested! { <ItemListEntry item={i} can_select={true} /> })}
+                            {for p.you.items.iter().map(|i| html_nested! { <ItemListEntry item={i} can_select={true} /> })}
                         </SelectItem>
                         <CommandButton text={"Use item"} command={item.map(move |item| Command::ItemOrJob { buff: Some(BuffSource::Item(item)), target: None })} />
 
-                        {if p.you.job == Job::PoisonMixer {
+                        {if BuffSource::Job(p.you.job).rule().is_some_and(|r| r.target == BuffTarget::Fighter) {
                             html! {
                                 <>
                                     <CommandButton text={"Use job to make attacker win"} command={Some(Command::ItemOrJob { buff: Some(BuffSource::Job(p.you.job)), target: Some(attacker) })} />
@@ -308,6 +341,8 @@ pub fn items_and_jobs(props: &ItemsAndJobsProps) -> Html {
                         <CommandButton text={"Pass"} command={Some(Command::ItemOrJob { buff: None, target: None })} />
                     </>
                 }
+            } else if p.sealed_answer.is_some() {
+                html! { <p>{"Your answer counts once everybody answered."}</p> }
             } else {
                 html! { <p>{"You have passed."}</p> }
             }}
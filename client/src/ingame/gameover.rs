@@ -0,0 +1,92 @@
+use std::rc::Rc;
+
+use web_protocol::{FactionKind, FactionStanding, GameSummary, Perspective, VictoryEvaluation, VictoryFlavor, WinningFaction};
+use yew::{function_component, html, html_nested, use_context, Html, Properties};
+
+#[derive(Properties, PartialEq)]
+pub struct GameOverProps {
+    pub winner: WinningFaction,
+    pub evaluation: Option<VictoryEvaluation>,
+    pub standings: Vec<FactionStanding>,
+}
+#[function_component(GameOver)]
+pub fn game_over(GameOverProps { winner, evaluation, standings }: &GameOverProps) -> Html {
+    let p = use_context::<Rc<Perspective>>().unwrap();
+    let headline = match *winner {
+        WinningFaction::Normal(faction) => format!("The {:?} is victorious!", faction),
+        WinningFaction::Traitor(traitor) => format!("The sole victor is {}!", p.name(traitor)),
+        WinningFaction::Draw => "The game ends in a draw.".to_owned(),
+        WinningFaction::Tiebreak(faction) => format!("The {:?} wins the tiebreak!", faction),
+    };
+    html! {
+        <div class="victory-text">
+            <p>{headline}</p>
+            if let Some(e) = evaluation {
+                <p>{format!("{} announced victory for the {:?}{}.", p.name(e.announcer), e.faction, if e.flavor == VictoryFlavor::Loge { " as the Loge" } else { "" })}</p>
+                <p>{format!(
+                    "Counting {}, the team needed {} items{} and had {}.",
+                    list(&e.required_items),
+                    e.needed_items,
+                    if e.minority { " (fewer, since they are in the minority)" } else { "" },
+                    e.total_items(),
+                )}</p>
+                <ul>
+                    {for e.contributions.iter().map(|c| html_nested! {
+                        <li>{format!(
+                            "{}: {}{}",
+                            p.name(c.player),
+                            if c.items.is_empty() { "nothing".to_owned() } else { list(&c.items) },
+                            if c.same_faction { "" } else { ", but on the other side" },
+                        )}</li>
+                    })}
+                </ul>
+                if e.loge_coat == Some(false) {
+                    <p>{"The Coat of Armor of the Loge was missing."}</p>
+                }
+            }
+            if !standings.is_empty() {
+                <p>{"The round limit ended the game. The victory items each side held:"}</p>
+                <ul>
+                    {for standings.iter().map(|s| html_nested! {
+                        <li>{format!("{:?}: {} of the {} needed", s.faction, s.held_items, s.needed_items)}</li>
+                    })}
+                </ul>
+            }
+            if let Some(summary) = &p.summary {
+                <Summary summary={summary.clone()} />
+            }
+        </div>
+    }
+}
+
+fn list<T: ToString>(xs: &[T]) -> String {
+    xs.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+#[derive(Properties, PartialEq)]
+struct SummaryProps {
+    summary: GameSummary,
+}
+#[function_component(Summary)]
+fn summary(SummaryProps { summary }: &SummaryProps) -> Html {
+    let p = use_context::<Rc<Perspective>>().unwrap();
+    html! {
+        <div class="summary">
+            <table>
+                <tr><th>{"Player"}</th><th>{"Faction"}</th><th>{"Jobs"}</th><th>{"Items"}</th></tr>
+                {for summary.players.iter().map(|s| {
+                    let faction = match s.faction {
+                        FactionKind::Normal(f) => format!("{:?}", f),
+                        FactionKind::ThreePlayer(cards) => format!("{:?}, {:?}, {:?}", cards[0], cards[1], cards[2]),
+                    };
+                    let jobs = s.jobs.iter().map(|j| format!("{:?}", j)).collect::<Vec<_>>().join(" → ");
+                    html_nested! {
+                        <tr><td>{p.name(s.player)}</td><td>{faction}</td><td>{jobs}</td><td>{list(&s.items)}</td></tr>
+                    }
+                })}
+            </table>
+            <p>{format!("Left on the item stack: {}", list(&summary.item_stack))}</p>
+            <p>{format!("Left on the job stack: {}", summary.job_stack.iter().map(|j| format!("{:?}", j)).collect::<Vec<_>>().join(", "))}</p>
+        </div>
+    }
+}
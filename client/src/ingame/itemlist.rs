@@ -38,7 +38,7 @@ pub fn playerlist(ItemListProps { selection, blocklist }: &ItemListProps) -> Htm
         <>
             {"Your items:"}
             <div class="itemlist">
-                {for perspective.you.items.iter().enumerate().map(|(idx, &i)| {
+                {for perspective.you.items.iter().enumerate().map(|(idx, i)| {
                     let is_selected = selection.as_ref().map_or(false, |x| x.index() == Some(idx));
                     let selected = if is_selected { Some("selected") } else { None };
                     let can_select = selected.is_some() && !blocklist.contains(&i);
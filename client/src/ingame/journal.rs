@@ -0,0 +1,47 @@
+use std::rc::Rc;
+
+use web_protocol::{Item, JournalEntry, Perspective, Reveal};
+use yew::{function_component, html, use_context, Html, Properties};
+
+fn list(items: &[Item]) -> String {
+    items.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+#[function_component(Journal)]
+pub fn journal() -> Html {
+    let perspective = use_context::<Rc<Perspective>>().unwrap();
+    if perspective.journal.is_empty() {
+        return html! {};
+    }
+
+    html! {
+        <div class="journal">
+            <h3>{"What you found out"}</h3>
+            { for perspective.journal.iter().cloned().map(|entry| html! { <Entry {entry} /> }) }
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct JournalEntryProps {
+    entry: JournalEntry,
+}
+#[function_component(Entry)]
+fn entry(JournalEntryProps { entry }: &JournalEntryProps) -> Html {
+    let perspective = use_context::<Rc<Perspective>>().unwrap();
+    let name = |p| perspective.name(p);
+    let card = |index: Option<usize>| index.map_or(String::new(), |i| format!(" (card {})", i + 1));
+    let body = match &entry.reveal {
+        Reveal::Priviledge { player, items } => format!("{} held {} when you passed them the Priviledge.", name(*player), list(items)),
+        &Reveal::Monocle { player, faction, three_player_faction_index } => format!("{} belongs to the {:?}{}.", name(player), faction, card(three_player_faction_index)),
+        Reveal::Diplomat { player, items } => format!("{} held {} when you asked them for an item.", name(*player), list(items)),
+        &Reveal::Credentials { player, faction, three_player_faction_index, job } => format!("{} belongs to the {:?}{} and was the {:?}.", name(player), faction, card(three_player_faction_index), job),
+        Reveal::AttackItems { player, items } => format!("{} held {} when you defeated them.", name(*player), list(items)),
+        Reveal::ItemStack { items } => format!("The item stack was {} (top item last).", list(items)),
+    };
+    html! {
+        <div class="entry">
+            {format!("Round {}, turn {}: {}", entry.turn.round, entry.turn.turn, body)}
+        </div>
+    }
+}
@@ -6,7 +6,7 @@ use wasm_bindgen::JsCast;
 use web_sys::{EventSource, HtmlInputElement, MessageEvent};
 use yew::prelude::*;
 use yew_router::hooks::use_location;
-use web_protocol::{GameCommand, GameInfo, Perspective, PerspectiveTurnState, WinningFaction};
+use web_protocol::{GameCommand, GameInfo, Locale, Perspective, PerspectiveTurnState};
 
 pub struct Ingame {
     game: String,
@@ -120,6 +120,17 @@ fn dev_mode(DevModeProps { game_id, game_info }: &DevModeProps) -> Html {
 }
 
 
+#[function_component(ResignButton)]
+fn resign_button() -> Html {
+    let cmd = use_context::<Commander>().unwrap();
+    let onclick = Callback::from(move |_| {
+        if gloo_dialogs::confirm("Leave this game for good? Everything will be answered for you from now on.") {
+            cmd.cmd(GameCommand::LeaveGame);
+        }
+    });
+    html! { <button class="button" {onclick}>{"Leave game"}</button> }
+}
+
 #[derive(Clone, PartialEq)]
 struct Commander {
     game: String,
@@ -132,6 +143,16 @@ impl Commander {
             super::post_json(&path, &cmd).await;
         });
     }
+    /// Like `cmd`, but passes what the server rejected the command with to `on_error`
+    fn cmd_or(&self, cmd: GameCommand, on_error: Callback<String>) {
+        log!("Sending command", format!("{:?}", cmd));
+        let path = format!("/api/game/{}", self.game);
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Err(e) = super::try_post_json(&path, &cmd).await {
+                on_error.emit(e);
+            }
+        });
+    }
 }
 
 mod utils;
@@ -148,6 +169,9 @@ mod myjob;
 mod myfaction;
 mod actionlog;
 mod clairvoyant;
+mod gameover;
+mod journal;
+mod notebook;
 
 #[derive(Properties, PartialEq)]
 struct GameUiProps {
@@ -162,40 +186,41 @@ fn game_ui(props: &GameUiProps) -> Html {
             let mut hide_all = false;
             let mut hide_items = false;
             let body = match &p.turn {
+                PerspectiveTurnState::GameOver { winner, evaluation, standings } => html! { <gameover::GameOver winner={*winner} evaluation={evaluation.clone()} standings={standings.clone()} /> },
+                _ if me.resigned => html! { <p>{"You have left the game."}</p> },
                 PerspectiveTurnState::DonatingItem { donor } if donor == &me.player => {
                     hide_all = true;
                     html! { <donation::ItemDonation /> }
                 }
-                PerspectiveTurnState::DonatingItem { donor } => html! { {format!("Waiting for {} to donate an item ...", donor)} },
+                PerspectiveTurnState::DonatingItem { donor } => html! { {format!("Waiting for {} to donate an item ...", p.name(*donor))} },
                 PerspectiveTurnState::TurnStart { player } if player == &me.player => {
                     hide_all = true;
                     html! { <turnstart::MyTurnStart my_job={p.you.job} job_used={p.you.job_is_visible} is_turn_end={false} /> }
                 },
-                PerspectiveTurnState::TurnStart { player } => html! { {format!("Waiting for {} ...", player)} },
+                PerspectiveTurnState::TurnStart { player } => html! { {format!("Waiting for {} ...", p.name(*player))} },
                 PerspectiveTurnState::TurnEndPhase { player } if player == &me.player => {
                     hide_all = true;
                     html! { <turnstart::MyTurnStart my_job={p.you.job} job_used={p.you.job_is_visible} is_turn_end={true} /> }
                 },
-                PerspectiveTurnState::TurnEndPhase { player } => html! { {format!("Waiting for {} to end their turn ...", player)} },
-                PerspectiveTurnState::GameOver { winner: WinningFaction::Normal(winner) } => html! { <div class="victory-text">{format!("The {:?} is victorious!", winner)}</div> },
-                PerspectiveTurnState::GameOver { winner: WinningFaction::Traitor(traitor) } => html! { <div class="victory-text">{format!("The sole victor is {traitor}!")}</div> },
+                PerspectiveTurnState::TurnEndPhase { player } => html! { {format!("Waiting for {} to end their turn ...", p.name(*player))} },
                 &PerspectiveTurnState::TradePending { offerer, target, item } if target == me.player => {
                     hide_items = true;
-                    html! { <trading::TradeOffer you={p.you.clone()} {offerer} item={item.unwrap()} stack_empty={p.item_stack == 0} /> }
+                    html! { <trading::TradeOffer you={p.you} {offerer} item={item.unwrap()} stack_empty={p.item_stack == 0} /> }
                 }
-                PerspectiveTurnState::TradePending { offerer, target, .. } => html! { <p class="trade-text">{format!("{} is offering an item to {} ...", offerer, target)}</p> },
+                PerspectiveTurnState::TradePending { offerer, target, .. } => html! { <p class="trade-text">{format!("{} is offering an item to {} ...", p.name(*offerer), p.name(*target))}</p> },
                 &PerspectiveTurnState::ResolvingTradeTrigger { giver, receiver, ref trigger } => html! { <trade_trigger::TradeTrigger myself={me.player} {giver} {receiver} trigger={trigger.clone()} /> },
 
                 &PerspectiveTurnState::Attacking { attacker, defender, ref state } => html! { <attacking::Attacking {attacker} {defender} myself={me.player} state={state.clone()} /> },
 
-                &PerspectiveTurnState::DoingClairvoyant { player, .. } if player != me.player => html! { <p>{format!("Waiting for the Clairvoyant ({}) to do their work ...", player)}</p> },
+                &PerspectiveTurnState::DoingClairvoyant { player, .. } if player != me.player => html! { <p>{format!("Waiting for the Clairvoyant ({}) to do their work ...", p.name(player))}</p> },
                 PerspectiveTurnState::DoingClairvoyant { player: _, item_stack } => html! { <clairvoyant::Clairvoyant item_stack={item_stack.clone().unwrap()} /> },
-                &PerspectiveTurnState::UnsuccessfulDiplomat { diplomat, target, .. } if diplomat != me.player => html! { <p>{format!("Waiting for the Diplomat ({}) to confirm that {} does not have the requested item ...", diplomat, target)}</p> },
-                PerspectiveTurnState::UnsuccessfulDiplomat { target, inventory, .. } => html! { <><p>{format!("Since {} does not have the requested item, you may see their inventory: {}", target, inventory.iter().flatten().map(|x| x.to_string()).collect::<Vec<_>>().join(", "))}</p><DoneLookingBtn /></> },
+                &PerspectiveTurnState::UnsuccessfulDiplomat { diplomat, target, .. } if diplomat != me.player => html! { <p>{format!("Waiting for the Diplomat ({}) to confirm that {} does not have the requested item ...", p.name(diplomat), p.name(target))}</p> },
+                PerspectiveTurnState::UnsuccessfulDiplomat { target, inventory, .. } => html! { <><p>{format!("Since {} does not have the requested item, you may see their inventory: {}", p.name(*target), inventory.iter().flatten().map(|x| x.to_string()).collect::<Vec<_>>().join(", "))}</p><DoneLookingBtn /></> },
             };
             html! {
                 <div class="hud">
                     <ContextProvider<Rc<Perspective>> context={Rc::new(p.clone())}>
+                        <p class="narration" aria-live="polite">{p.narrate(Locale::English)}</p>
                         if !hide_all {
                             <playerlist::PlayerList />
                             <myfaction::MyFaction />
@@ -205,12 +230,18 @@ fn game_ui(props: &GameUiProps) -> Html {
                             }
                         }
                         {body}
+                        if !me.resigned && !matches!(p.turn, PerspectiveTurnState::GameOver { .. }) {
+                            <ResignButton />
+                        }
+                        <journal::Journal />
+                        <notebook::Notebook />
                         <actionlog::ActionLog />
                     </ContextProvider<Rc<Perspective>>>
                 </div>
             }
         }
-        GameInfo::Spectating(_) => html! { {"Spectating is not implemented yet"} },
+        // there is no board for spectators yet, only what is going on
+        GameInfo::Spectating(s) => html! { <p>{s.narrate(Locale::English)}</p> },
     }
 }
 
@@ -0,0 +1,49 @@
+use std::rc::Rc;
+
+use web_protocol::{Perspective, PlayerOdds};
+use yew::{function_component, html, use_context, use_state, Callback, Html};
+
+fn percent(p: f64) -> String {
+    format!("{:.0}%", p * 100.)
+}
+
+/// Only lists what is at least somewhat likely, most likely first
+fn likely<T: std::fmt::Display>(odds: &[(T, f64)]) -> String {
+    let mut odds: Vec<_> = odds.iter().collect();
+    odds.sort_by(|a, b| b.1.total_cmp(&a.1));
+    odds.iter().take(4).map(|(x, p)| format!("{} ({})", x, percent(*p))).collect::<Vec<_>>().join(", ")
+}
+
+#[function_component(Notebook)]
+pub fn notebook() -> Html {
+    let perspective = use_context::<Rc<Perspective>>().unwrap();
+    let open = use_state(|| false);
+    let toggle = { let open = open.clone(); Callback::from(move |_| open.set(!*open)) };
+
+    html! {
+        <div class="notebook">
+            <button onclick={toggle}>{if *open { "Close notebook" } else { "Notebook" }}</button>
+            if *open {
+                <table>
+                    <tr><th>{"Player"}</th><th>{"Order"}</th><th>{"Job"}</th><th>{"Items"}</th></tr>
+                    { for perspective.notebook.players.iter().map(|odds| row(&perspective, odds)) }
+                </table>
+            }
+        </div>
+    }
+}
+
+fn row(perspective: &Perspective, odds: &PlayerOdds) -> Html {
+    let order = match odds.order_cards {
+        Some(cards) => format!("{} (cards: {})", percent(odds.order), cards.map(percent).join(", ")),
+        None => percent(odds.order),
+    };
+    html! {
+        <tr>
+            <td>{perspective.name(odds.player)}</td>
+            <td>{order}</td>
+            <td>{likely(&odds.jobs)}</td>
+            <td>{likely(&odds.items)}</td>
+        </tr>
+    }
+}
@@ -1,12 +1,12 @@
 use std::rc::Rc;
 
-use web_protocol::{Perspective, Player};
+use web_protocol::{Perspective, SeatId};
 use yew::{classes, function_component, html, use_context, Callback, Html, Properties, UseStateHandle};
 
 #[derive(Properties, PartialEq)]
 pub struct PlayerListProps {
     #[prop_or_default]
-    pub selected: Option<UseStateHandle<Vec<Player>>>,
+    pub selected: Option<UseStateHandle<Vec<SeatId>>>,
     #[prop_or_default]
     pub block_select: bool,
 }
@@ -23,8 +23,10 @@ pub fn playerlist(PlayerListProps { selected, block_select }: &PlayerListProps)
             {for perspective.players.iter().enumerate().map(|(i, p)| {
                 let is_you = i == perspective.your_player_index;
                 let you = if is_you { Some("you") } else { None };
+                let pending = if perspective.pending_actors.contains(&p.player) { Some("pending") } else { None };
+                let resigned = if p.resigned { Some("resigned") } else { None };
 
-                let mut class = classes!("entry", you);
+                let mut class = classes!("entry", you, pending, resigned);
                 let mut onclick = None;
                 if let Some(players) = &selected {
                     if players.contains(&p.player) {
@@ -52,7 +54,7 @@ pub fn playerlist(PlayerListProps { selected, block_select }: &PlayerListProps)
 
                 html! {
                     <div class={class} onclick={onclick}>
-                        <div class="name">{p.player.to_string()}</div>
+                        <div class="name">{&p.name}</div>
                         <div class="job">{p.job.map(|j| format!("{:?}", j)).unwrap_or("?".to_owned())}</div>
                         <div class="item_count">{p.item_count}</div>
                     </div>
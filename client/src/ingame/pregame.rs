@@ -1,23 +1,45 @@
+use std::fmt;
+
 use super::{Commander, SimpleDropdown};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
 use yew::prelude::*;
 
-use web_protocol::{Player, GameCommand};
+use web_protocol::{Character, GameCommand, GameConfig, Item, Job, RoundLimit, Seat, SeatId};
 
 #[derive(Properties, PartialEq)]
 pub struct WaitingForPlayersProps {
-    pub players: Vec<Player>,
-    pub you: Option<Player>,
+    pub players: Vec<Seat>,
+    pub you: Option<SeatId>,
 }
 
 #[function_component(WaitingForPlayers)]
 pub fn waiting_for_players(WaitingForPlayersProps { players, you }: &WaitingForPlayersProps) -> Html {
     let cmd = use_context::<Commander>().unwrap();
     let cmd2 = cmd.clone();
+    let loge_victory = use_state(|| true);
+    let toggle_loge_victory = {
+        let loge_victory = loge_victory.clone();
+        Callback::from(move |_| loge_victory.set(!*loge_victory))
+    };
+    let sealed_answers = use_state(|| false);
+    let toggle_sealed_answers = {
+        let sealed_answers = sealed_answers.clone();
+        Callback::from(move |_| sealed_answers.set(!*sealed_answers))
+    };
+    let rounds = use_state(|| None);
+    let rounds_after_stack = use_state(|| None);
+    let tiebreak = use_state(|| false);
+    let toggle_tiebreak = {
+        let tiebreak = tiebreak.clone();
+        Callback::from(move |_| tiebreak.set(!*tiebreak))
+    };
+    let rules = use_state(GameConfig::default);
+    let start_error = use_state(|| None);
     html! {
         <div class="content">
             {"Players:"}
             <ul>
-                {for players.iter().map(|p| html! { <li key={p.to_string()}>{if Some(p) == you.as_ref() { format!("{} (you)", p) } else { p.to_string() }}</li> })}
+                {for players.iter().map(|p| html! { <li key={p.id.0}>{if Some(p.id) == *you { format!("{} (you)", p.name) } else { p.name.clone() }}</li> })}
             </ul>
             {match you {
                 None => html! { <PlayerSelection players={players.clone()} /> },
@@ -25,7 +47,42 @@ pub fn waiting_for_players(WaitingForPlayersProps { players, you }: &WaitingForP
                     <>
                         <button class="button" onclick={Callback::from(move |_| cmd.cmd(GameCommand::LeaveGame))}>{"Leave"}</button>
                         if players.len() >= 3 {
-                            <button class="button" onclick={Callback::from(move |_| cmd2.cmd(GameCommand::StartGame))}>{"Start Game"}</button>
+                            <label class="checkbox">
+                                <input type="checkbox" checked={*loge_victory} onchange={toggle_loge_victory} />
+                                {" Allow solo victories"}
+                            </label>
+                            <label class="checkbox">
+                                <input type="checkbox" checked={*sealed_answers} onchange={toggle_sealed_answers} />
+                                {" Answer the priest and fights in secret"}
+                            </label>
+                            <label>
+                                {"End the game after round "}
+                                <RoundInput value={rounds.clone()} placeholder="never" />
+                            </label>
+                            <label>
+                                {"or this many rounds after the item stack ran out "}
+                                <RoundInput value={rounds_after_stack.clone()} placeholder="never" />
+                            </label>
+                            if rounds.is_some() || rounds_after_stack.is_some() {
+                                <label class="checkbox">
+                                    <input type="checkbox" checked={*tiebreak} onchange={toggle_tiebreak} />
+                                    {" Break ties by victory items instead of ending in a draw"}
+                                </label>
+                            }
+                            <RuleSettings config={rules.clone()} players={players.len()} />
+                            <button class="button" onclick={{
+                                let start_error = start_error.clone();
+                                Callback::from(move |_| {
+                                    let round_limit = RoundLimit { rounds: *rounds, rounds_after_stack: *rounds_after_stack, tiebreak: *tiebreak };
+                                    let config = GameConfig { loge_victory: *loge_victory, sealed_answers: *sealed_answers, round_limit, ..(*rules).clone() };
+                                    start_error.set(None);
+                                    let start_error = start_error.clone();
+                                    cmd2.cmd_or(GameCommand::StartGame(config), Callback::from(move |e| start_error.set(Some(e))))
+                                })
+                            }}>{"Start Game"}</button>
+                            if let Some(e) = &*start_error {
+                                <p class="help is-danger">{e}</p>
+                            }
                         }
                     </>
                 },
@@ -34,26 +91,194 @@ pub fn waiting_for_players(WaitingForPlayersProps { players, you }: &WaitingForP
     }
 }
 
+/// A callback that changes `config` with `f`
+fn edit<T: 'static>(config: &UseStateHandle<GameConfig>, f: impl Fn(&mut GameConfig, T) + 'static) -> Callback<T> {
+    let config = config.clone();
+    Callback::from(move |value| {
+        let mut changed = (*config).clone();
+        f(&mut changed, value);
+        config.set(changed);
+    })
+}
+
+/// Where an item is in the game, see `GameConfig::start_items` and `GameConfig::stack_items`
+#[derive(Clone, Copy, PartialEq)]
+enum ItemPlacement {
+    Start,
+    Stack,
+    Out,
+}
+impl ItemPlacement {
+    const ALL: [ItemPlacement; 3] = [ItemPlacement::Start, ItemPlacement::Stack, ItemPlacement::Out];
+
+    fn of(config: &GameConfig, item: Item) -> ItemPlacement {
+        if config.start_items.contains(&item) {
+            ItemPlacement::Start
+        } else if config.stack_items.contains(&item) {
+            ItemPlacement::Stack
+        } else {
+            ItemPlacement::Out
+        }
+    }
+    fn set(self, config: &mut GameConfig, item: Item) {
+        config.start_items.retain(|&i| i != item);
+        config.stack_items.retain(|&i| i != item);
+        match self {
+            ItemPlacement::Start => config.start_items.push(item),
+            ItemPlacement::Stack => config.stack_items.push(item),
+            ItemPlacement::Out => (),
+        }
+    }
+}
+impl fmt::Display for ItemPlacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemPlacement::Start => write!(f, "dealt at the start"),
+            ItemPlacement::Stack => write!(f, "only on the stack"),
+            ItemPlacement::Out => write!(f, "left out"),
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct RuleSettingsProps {
+    config: UseStateHandle<GameConfig>,
+    players: usize,
+}
+/// The jobs and items in the game and how many victory items it takes. Starts out as the game as printed.
+#[function_component(RuleSettings)]
+fn rule_settings(RuleSettingsProps { config, players }: &RuleSettingsProps) -> Html {
+    let players = *players;
+    html! {
+        <details class="rule-settings">
+            <summary>{"Jobs, items and victory"}</summary>
+            <p>{"Jobs in the game:"}</p>
+            {for Job::all().map(|job| html! {
+                <label class="checkbox">
+                    <input type="checkbox" checked={config.jobs.contains(&job)} onchange={edit(config, move |c, _: Event| {
+                        if c.jobs.contains(&job) {
+                            c.jobs.retain(|&j| j != job);
+                        } else {
+                            c.jobs.push(job);
+                        }
+                    })} />
+                    {format!(" {} ", job)}
+                </label>
+            })}
+            <p>{"Items besides Keys, Goblets and the bags:"}</p>
+            <ul>
+                {for Item::all().filter(|i| !matches!(i, Item::Key | Item::Goblet | Item::BagKey | Item::BagGoblet)).map(|item| {
+                    let placement = ItemPlacement::of(config, item);
+                    html! {
+                        <li>
+                            {format!("{}: ", item)}
+                            <div class="select">
+                                <select onchange={edit(config, move |c, e: Event| {
+                                    let i: usize = e.target_unchecked_into::<HtmlSelectElement>().value().parse().unwrap();
+                                    ItemPlacement::ALL[i].set(c, item);
+                                })}>
+                                    {for ItemPlacement::ALL.iter().enumerate().map(|(i, p)| html! { <option value={i.to_string()} selected={*p == placement}>{p.to_string()}</option> })}
+                                </select>
+                            </div>
+                        </li>
+                    }
+                })}
+            </ul>
+            <label>{"Keys "}<CountInput value={config.keys} on_change={edit(config, |c, n| c.keys = n)} /></label>
+            <label>{" Goblets "}<CountInput value={config.goblets} on_change={edit(config, |c, n| c.goblets = n)} /></label>
+            <p>
+                <label>
+                    {format!("Items a player may keep with {} players ", players)}
+                    <CountInput value={config.inventory_limit(players)} on_change={edit(config, move |c, n| { c.inventory_limits.insert(players, n); })} />
+                </label>
+            </p>
+            <p>
+                <label>{"Victory items needed "}<CountInput value={config.victory_items} on_change={edit(config, |c, n| c.victory_items = n)} /></label>
+                <label>{" or by the smaller faction "}<CountInput value={config.minority_victory_items} on_change={edit(config, |c, n| c.minority_victory_items = n)} /></label>
+            </p>
+        </details>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct CountInputProps {
+    value: usize,
+    on_change: Callback<usize>,
+}
+/// A number of things, which only changes once it is a valid one
+#[function_component(CountInput)]
+fn count_input(CountInputProps { value, on_change }: &CountInputProps) -> Html {
+    let oninput = {
+        let on_change = on_change.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            if let Ok(n) = input.value().parse() {
+                on_change.emit(n);
+            }
+        })
+    };
+    html! {
+        <input class="input round-input" type="number" min="0" value={value.to_string()} {oninput} />
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct RoundInputProps {
+    value: UseStateHandle<Option<u32>>,
+    placeholder: &'static str,
+}
+/// A number of rounds, or nothing if left empty
+#[function_component(RoundInput)]
+fn round_input(RoundInputProps { value, placeholder }: &RoundInputProps) -> Html {
+    let oninput = {
+        let value = value.clone();
+        Callback::from(move |e: InputEvent| { let input: HtmlInputElement = e.target_unchecked_into(); value.set(input.value().parse().ok()); })
+    };
+    html! {
+        <input class="input round-input" type="number" min="1" placeholder={*placeholder} {oninput} />
+    }
+}
+
 #[derive(Properties, PartialEq)]
 pub struct PlayerSelectionProps {
-    pub players: Vec<Player>,
+    pub players: Vec<Seat>,
+}
+
+/// Picking a character is optional, they only decorate the seat
+#[derive(Clone, Copy, PartialEq)]
+struct CharacterChoice(Option<Character>);
+impl fmt::Display for CharacterChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(c) => write!(f, "{}", c),
+            None => write!(f, "No character"),
+        }
+    }
 }
 
 #[function_component(PlayerSelection)]
 pub fn player_selection(props: &PlayerSelectionProps) -> Html {
-    let avail_players = Player::all().filter(|p| !props.players.contains(&p));
-    let selected_join_player = use_state(|| avail_players.clone().next().unwrap());
-    let selected_join_player2 = selected_join_player.clone();
+    let taken: Vec<_> = props.players.iter().filter_map(|p| p.character).collect();
+    let characters = std::iter::once(None).chain(Character::all().filter(|c| !taken.contains(c)).map(Some)).map(CharacterChoice);
+    let name = use_state(String::new);
+    let character = use_state(|| CharacterChoice(None));
 
     let cmd = use_context::<Commander>().unwrap();
+    let oninput = {
+        let name = name.clone();
+        Callback::from(move |e: InputEvent| { let input: HtmlInputElement = e.target_unchecked_into(); name.set(input.value()); })
+    };
+    let join = {
+        let name = name.clone();
+        let character = character.clone();
+        Callback::from(move |_| cmd.cmd(GameCommand::JoinGame { name: (*name).clone(), character: character.0 }))
+    };
 
     html! {
         <>
-            <SimpleDropdown<Player> options={avail_players.collect::<Vec<_>>()} on_change={Callback::from(move |x| {
-                selected_join_player2.set(x);
-            })} />
-            <button class="button" onclick={Callback::from(move |_| cmd.cmd(GameCommand::JoinGame(*selected_join_player)))}>{"Join"}</button>
+            <input class="input" placeholder="Your name" value={(*name).clone()} {oninput} />
+            <SimpleDropdown<CharacterChoice> options={characters.collect::<Vec<_>>()} on_change={Callback::from(move |x| character.set(x))} />
+            <button class="button" disabled={name.trim().is_empty()} onclick={join}>{"Join"}</button>
         </>
     }
 }
-
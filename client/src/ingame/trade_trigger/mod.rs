@@ -1,6 +1,8 @@
 mod sextant;
 
-use web_protocol::{Command, PerspectiveTradeTriggerState, Player};
+use std::rc::Rc;
+
+use web_protocol::{Command, Perspective, PerspectiveTradeTriggerState, SeatId};
 use yew::prelude::*;
 use crate::ingame::CommandButton;
 
@@ -8,18 +10,20 @@ use super::DoneLookingBtn;
 
 #[derive(Properties, PartialEq)]
 pub struct TradeTriggerProps {
-    pub myself: Player,
-    pub giver: Player,
-    pub receiver: Player,
+    pub myself: SeatId,
+    pub giver: SeatId,
+    pub receiver: SeatId,
     pub trigger: PerspectiveTradeTriggerState,
 }
 
 #[function_component(TradeTrigger)]
 pub fn trade_trigger(props: &TradeTriggerProps) -> Html {
     let &TradeTriggerProps { myself, giver, receiver, ref trigger } = props;
+    let p = use_context::<Rc<Perspective>>().unwrap();
+    let (giver_name, receiver_name) = (p.name(giver), p.name(receiver));
 
     match trigger {
-        PerspectiveTradeTriggerState::Priviledge { items: None } => html! { <p>{format!("Waiting for {} to look at {}'s items ...", giver, receiver)}</p> },
+        PerspectiveTradeTriggerState::Priviledge { items: None } => html! { <p>{format!("Waiting for {} to look at {}'s items ...", giver_name, receiver_name)}</p> },
         PerspectiveTradeTriggerState::Priviledge { items: Some(items) } => html! { <><p>{format!("You see the following items: {}", items.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", "))}</p><DoneLookingBtn /></> },
         PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: None } if giver == myself => html! {
             html! {
@@ -31,10 +35,10 @@ pub fn trade_trigger(props: &TradeTriggerProps) -> Html {
                 </>
             }
         },
-        PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: None } => html! { <p>{format!("Waiting for {} to look at {}'s faction ...", giver, receiver)}</p> },
-        PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: Some(i) } => html! { <p>{format!("Waiting for {} to look at {}'s faction {} ...", giver, receiver, i + 1)}</p> },
-        PerspectiveTradeTriggerState::Monocle { faction: Some(faction), three_player_faction_index: _ } => html! { <><p>{format!("You see that {} is a member of the {:?}.", receiver, faction)}</p><DoneLookingBtn /></> },
-        PerspectiveTradeTriggerState::Coat { available_jobs: None } => html! { <p>{format!("Waiting for {} to pick a new job ...", giver)}</p> },
+        PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: None } => html! { <p>{format!("Waiting for {} to look at {}'s faction ...", giver_name, receiver_name)}</p> },
+        PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: Some(i) } => html! { <p>{format!("Waiting for {} to look at {}'s faction {} ...", giver_name, receiver_name, i + 1)}</p> },
+        PerspectiveTradeTriggerState::Monocle { faction: Some(faction), three_player_faction_index: _ } => html! { <><p>{format!("You see that {} is a member of the {:?}.", receiver_name, faction)}</p><DoneLookingBtn /></> },
+        PerspectiveTradeTriggerState::Coat { available_jobs: None } => html! { <p>{format!("Waiting for {} to pick a new job ...", giver_name)}</p> },
         PerspectiveTradeTriggerState::Coat { available_jobs: Some(jobs) } => html! { <coat::ResolveCoat jobs={jobs.clone()} /> },
         &PerspectiveTradeTriggerState::Sextant { ref item_selections, is_forward } => html! { <sextant::ResolveSextant responsible_player={giver} item_selections={item_selections.clone()} {is_forward}  /> },
     }
@@ -50,8 +54,6 @@ mod coat;
 
 // foliant should be indicated to the others
 
-// turn number
-
 // wappen der loge sieg verkünden
 
 
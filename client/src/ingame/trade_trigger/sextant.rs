@@ -1,15 +1,15 @@
 use std::collections::HashMap;
 use std::rc::Rc;
 
-use web_protocol::{Perspective, Player, Item, Command};
+use web_protocol::{Perspective, SeatId, Item, Command};
 use yew::prelude::*;
 
 use crate::ingame::CommandButton;
 
 #[derive(Properties, PartialEq)]
 pub struct ResolveSextantProps {
-    pub responsible_player: Player,
-    pub item_selections: HashMap<Player, Item>,
+    pub responsible_player: SeatId,
+    pub item_selections: HashMap<SeatId, Item>,
     pub is_forward: Option<bool>,
 }
 
@@ -28,7 +28,7 @@ pub fn resolve_sextant(props: &ResolveSextantProps) -> Html {
                 <CommandButton command={Some(Command::SetSextantDirection { forward: true })} text={"Right"} />
             </div>
         },
-        None => html! { <p class="sextant-text">{format!("Waiting for {} to determine the direction.", responsible_player)}</p> },
+        None => html! { <p class="sextant-text">{format!("Waiting for {} to determine the direction.", p.name(*responsible_player))}</p> },
         &Some(is_forward) => {
             let num_players = p.players.len();
             let mut i = p.your_player_index + num_players;
@@ -41,12 +41,12 @@ pub fn resolve_sextant(props: &ResolveSextantProps) -> Html {
 
             let next_player = p.players[i].player;
             match item_selections.get(&me.player) {
-                Some(i) => html! { <p class="sextant-text">{format!("You are passing a {:?} to {}.", i, next_player)}</p> },
+                Some(i) => html! { <p class="sextant-text">{format!("You are passing a {:?} to {}.", i, p.name(next_player))}</p> },
                 None => html! {
                     <>
-                        <p class="sextant-text">{format!("Select an item to pass on to {}.", next_player)}</p>
+                        <p class="sextant-text">{format!("Select an item to pass on to {}.", p.name(next_player))}</p>
                         <div class="itemlist">
-                            {for p.you.items.iter().map(|&i| {
+                            {for p.you.items.iter().map(|i| {
                                 let is_selected = *item == Some(i);
                                 let selected = if *item == Some(i) { Some("selected") } else { None };
 
@@ -1,5 +1,7 @@
+use std::rc::Rc;
+
 use yew::prelude::*;
-use web_protocol::{Player, Item, PlayerState, Command};
+use web_protocol::{SeatId, Item, PlayerState, Command, Perspective};
 
 use crate::ingame::itemlist::{ItemList, ItemWithIndex};
 use crate::ingame::CommandButton;
@@ -7,12 +9,13 @@ use crate::ingame::CommandButton;
 #[derive(Properties, PartialEq)]
 pub struct TradeOfferProps {
     pub you: PlayerState,
-    pub offerer: Player,
+    pub offerer: SeatId,
     pub item: Item,
     pub stack_empty: bool,
 }
 #[function_component(TradeOffer)]
 pub fn trade_offer(props: &TradeOfferProps) -> Html {
+    let perspective = use_context::<Rc<Perspective>>().unwrap();
     let selection = ItemWithIndex::use_new();
     let reject = match props.item {
         Item::BlackPearl | Item::BrokenMirror => None,
@@ -32,7 +35,7 @@ pub fn trade_offer(props: &TradeOfferProps) -> Html {
         <div class="item-offer">
             <ItemList {selection} {blocklist} />
 
-            <div class="text">{format!("{} is offering you a {}", props.offerer, props.item)}</div>
+            <div class="text">{format!("{} is offering you a {}", perspective.name(props.offerer), props.item)}</div>
             <CommandButton class="is-green" text={"Accept"} command={item.map(|item| Command::AcceptTrade { item })} />
             <CommandButton class="is-red" text={"Decline"} command={reject} />
         </div>
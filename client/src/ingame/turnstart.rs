@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 use std::rc::Rc;
 
-use web_protocol::{Command, Item, Job, Perspective, Player, VictoryFlavor};
+use web_protocol::{Command, Item, Job, Perspective, SeatId, VictoryFlavor};
 use yew::prelude::*;
 
 use crate::ingame::itemlist::{ItemList, ItemWithIndex};
@@ -34,7 +34,7 @@ pub fn my_turn_start(MyTurnStartProps { is_turn_end, my_job, job_used }: &MyTurn
     enum HasPlayer { No, One, Many }
     let perspective = use_context::<Rc<Perspective>>().unwrap();
     let movekind = use_state(|| WipMoveKind::None);
-    let players = use_state(|| Vec::<Player>::new());
+    let players = use_state(|| Vec::<SeatId>::new());
     let item = ItemWithIndex::use_new();
     let diplomat_item = use_state(|| DIPLOMAT_ITEM_LIST[0]);
     let action_btn = |kind: WipMoveKind, text: &'static str, has_player: HasPlayer, has_item: bool| -> Html {
@@ -77,7 +77,7 @@ pub fn my_turn_start(MyTurnStartProps { is_turn_end, my_job, job_used }: &MyTurn
         buttons.push(action_btn(WipMoveKind::UseDiplomat, "Use Diplomat", HasPlayer::One, true));
     }
 
-    let is_victory_item = |&&x: &&Item| x == Item::Key || x == Item::Goblet || ((perspective.item_stack == 0) && (x == Item::BagKey || x == Item::BagGoblet));
+    let is_victory_item = |&x: &Item| x == Item::Key || x == Item::Goblet || ((perspective.item_stack == 0) && (x == Item::BagKey || x == Item::BagGoblet));
     if perspective.you.items.contains(&Item::CoatOfArmorOfTheLoge) && perspective.you.items.iter().filter(is_victory_item).count() >= 3 {
         buttons.push(action_btn(WipMoveKind::LogeVictory, "Announce Sole Victory (Loge)", HasPlayer::No, false));
     }
@@ -110,18 +110,18 @@ pub fn my_turn_start(MyTurnStartProps { is_turn_end, my_job, job_used }: &MyTurn
                     } else {
                         text += ", ";
                     }
-                    text += &p.to_string();
+                    text += perspective.name(*p);
                 }
             }
             Cow::from(format!("You are going to announce the victory of the {:?} {}.", perspective.you.effective_faction(), text))
         }
-        WipMoveKind::OfferTrade => Cow::from(format!("You offer to trade a {} to {}.", item.item().map(|x| x.to_string()).unwrap_or("?".to_owned()), players.get(0).map(|p| p.to_string()).unwrap_or("?".to_owned()))),
-        WipMoveKind::Attack => Cow::from(format!("You attack {}.", players.get(0).map(|p| p.to_string()).unwrap_or("?".to_owned()))),
+        WipMoveKind::OfferTrade => Cow::from(format!("You offer to trade a {} to {}.", item.item().map(|x| x.to_string()).unwrap_or("?".to_owned()), players.get(0).map_or("?", |&p| perspective.name(p)))),
+        WipMoveKind::Attack => Cow::from(format!("You attack {}.", players.get(0).map_or("?", |&p| perspective.name(p)))),
         WipMoveKind::UseClairvoyant => Cow::from("You are going to use your job ability (Clairvoyant)."),
         WipMoveKind::UseDiplomat => Cow::from(format!(
             "You are going to use your job ability (Diplomat). You are demanding a {} from {} in exchange for a {}.",
             diplomat_item.to_string(),
-            players.get(0).map(|p| p.to_string()).unwrap_or("?".to_owned()),
+            players.get(0).map_or("?", |&p| perspective.name(p)),
             item.item().map(|x| x.to_string()).unwrap_or("?".to_owned())
         )),
     };
@@ -11,7 +11,7 @@ use wasm_bindgen_futures::JsFuture;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
-use web_protocol::MyState;
+use web_protocol::{AutoAnswers, MyState};
 
 mod ingame;
 
@@ -47,6 +47,12 @@ async fn fetch_json<T: serde::de::DeserializeOwned>(path: &str) -> T {
     serde_json::from_str(&text).unwrap()
 }
 async fn post_json<T: serde::Serialize>(path: &str, body: &T) {
+    if let Err(text) = try_post_json(path, body).await {
+        gloo_dialogs::alert(&text);
+    }
+}
+/// Like `post_json`, but leaves showing what the server rejected the request with to the caller
+async fn try_post_json<T: serde::Serialize>(path: &str, body: &T) -> Result<(), String> {
     let body = serde_json::to_string(body).unwrap();
     let opts = web_sys::RequestInit::new();
     opts.set_method("POST");
@@ -57,15 +63,48 @@ async fn post_json<T: serde::Serialize>(path: &str, body: &T) {
     let resp: web_sys::Response = resp.dyn_into().unwrap();
     if !resp.ok() {
         let text = JsFuture::from(resp.text().unwrap()).await.unwrap();
-        let text = text.as_string().unwrap();
-        gloo_dialogs::alert(&text);
+        return Err(text.as_string().unwrap());
     }
+    Ok(())
 }
 
 fn view_game_item(game: String) -> Html {
     html! { <li><Link to={AppRoute::Game { id: game.clone() }}>{game}</Link></li> }
 }
-fn view_content(r: AppRoute, my_games: Vec<String>) -> Html {
+#[derive(Properties, PartialEq)]
+struct AutoAnswerSettingsProps {
+    answers: AutoAnswers,
+}
+
+#[function_component(AutoAnswerSettings)]
+fn auto_answer_settings(props: &AutoAnswerSettingsProps) -> Html {
+    let answers = use_state(|| props.answers);
+    let toggle = |set: fn(&mut AutoAnswers)| {
+        let answers = answers.clone();
+        Callback::from(move |_| {
+            let mut new = *answers;
+            set(&mut new);
+            answers.set(new);
+            wasm_bindgen_futures::spawn_local(async move { post_json("/api/me/auto_answers", &new).await });
+        })
+    };
+    html! {
+        <div class="content">
+            <p>{"When I have no choice, answer for me after a random delay:"}</p>
+            <label class="checkbox">
+                <input type="checkbox" checked={answers.priest} onchange={toggle(|a| a.priest = !a.priest)} />
+                {" Not using the Priest"}
+            </label>
+            <br />
+            <label class="checkbox">
+                <input type="checkbox" checked={answers.items_or_jobs} onchange={toggle(|a| a.items_or_jobs = !a.items_or_jobs)} />
+                {" Passing in fights"}
+            </label>
+        </div>
+    }
+}
+
+fn view_content(r: AppRoute, my_games: Vec<String>, auto_answers: AutoAnswers) -> Html {
     match r {
         AppRoute::Home => html! {
             <div>
@@ -76,6 +115,8 @@ fn view_content(r: AppRoute, my_games: Vec<String>) -> Html {
                         <li><Link to={AppRoute::Game { id: uuid::Uuid::new_v4().to_string() }}>{"+ New Game"}</Link></li>
                     </ul>
                 </div>
+                <h3 class="title">{"Settings"}</h3>
+                <AutoAnswerSettings answers={auto_answers} />
             </div>
         },
         AppRoute::Game { id: g } => html! {
@@ -120,9 +161,9 @@ impl Component for App {
             Some(MyState::LoggedOut) => html! { <button class="button is-black is-outlined" onclick={ctx.link().callback(|_| Msg::Login)}>{"Login"}</button> },
             Some(MyState::LoggedIn { .. }) => html! { <button class="button is-black is-outlined" onclick={ctx.link().callback(|_| Msg::Logout)}>{"Logout"}</button> },
         };
-        let (my_games, logged_in) = match &self.my_state {
-            Some(MyState::LoggedIn { my_games }) => (my_games.clone(), true),
-            _ => (vec![], false),
+        let (my_games, auto_answers, logged_in) = match &self.my_state {
+            Some(MyState::LoggedIn { my_games, auto_answers }) => (my_games.clone(), *auto_answers, true),
+            _ => (vec![], AutoAnswers::default(), false),
         };
         html! {
             <BrowserRouter>
@@ -146,7 +187,7 @@ impl Component for App {
                 <div class="container is-centered">
                     {if logged_in { html! {
                         <Switch<AppRoute>
-                            render={move |r| view_content(r, my_games.clone())}
+                            render={move |r| view_content(r, my_games.clone(), auto_answers)}
                         />
                     } } else { html! {
                         {"Please log in."}
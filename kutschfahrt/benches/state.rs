@@ -0,0 +1,40 @@
+//! How fast the things are that bots do over and over while searching.
+
+use std::collections::HashMap;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use kutschfahrt::bot::{play_bots, Agent, IsmctsAgent, RandomAgent};
+use kutschfahrt::State;
+use web_protocol::*;
+
+/// A six player game some way in, so that inventories and the log have filled up a bit
+fn midgame() -> (State, SeatId) {
+    let players = (0..6).map(|i| Seat { id: SeatId(i), name: format!("Player {}", i), character: None }).collect();
+    let mut state = State::new(players, GameConfig::default(), 3);
+    let mut agents: HashMap<SeatId, Box<dyn Agent>> = (0..6).map(|i| (SeatId(i), Box::new(RandomAgent::new(i as u64)) as Box<dyn Agent>)).collect();
+    play_bots(&mut state, &mut agents, 40).unwrap();
+    let actor = (0..6).map(SeatId).find(|&p| !state.legal_commands(p).is_empty()).expect("the game is still going");
+    (state, actor)
+}
+
+fn bench_state(c: &mut Criterion) {
+    let (state, actor) = midgame();
+    let command = state.legal_commands(actor)[0].clone();
+
+    c.bench_function("clone", |b| b.iter(|| black_box(&state).clone()));
+    c.bench_function("apply_command", |b| b.iter(|| {
+        let mut s = state.clone();
+        s.apply_command(actor, black_box(command.clone())).unwrap();
+        s
+    }));
+    c.bench_function("legal_commands", |b| b.iter(|| black_box(&state).legal_commands(actor)));
+
+    let view = state.perspective(actor);
+    let mut group = c.benchmark_group("bots");
+    group.sample_size(10);
+    group.bench_function("ismcts_100", |b| b.iter(|| IsmctsAgent::new(0, 100).choose(black_box(&view))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_state);
+criterion_main!(benches);
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use rand::prelude::*;
+use web_protocol::*;
+
+use crate::{State, GameState, GameStatePlayers, GameRng};
+
+/// Removes one copy of each of `items` from `pool` (as far as they are in there).
+fn take<T: PartialEq + Copy>(pool: &mut Vec<T>, items: &[T]) {
+    for i in items {
+        if let Some(idx) = pool.iter().position(|x| x == i) {
+            pool.swap_remove(idx);
+        }
+    }
+}
+
+/// Builds a complete game state that looks exactly like `view` to its owner.
+///
+/// Everything the owner can't see (other inventories, factions and jobs, the order of the stacks)
+/// is dealt at random from what's left. Things that are never shown to anybody, like the state a trade
+/// trigger returns to, are filled in with a plausible guess.
+pub fn determinize(view: &Perspective, rng: &mut impl Rng) -> State {
+    let me = view.players[view.your_player_index].player;
+    let n = view.players.len();
+
+    let mut known_items: IndexMap<SeatId, Vec<Item>> = IndexMap::new();
+    let mut known_jobs: IndexMap<SeatId, Job> = view.players.iter().filter_map(|p| Some((p.player, p.job?))).collect();
+    let mut known_factions: IndexMap<SeatId, Faction> = IndexMap::new();
+    // three player games only show single faction cards
+    let mut known_cards: IndexMap<SeatId, (usize, Faction)> = IndexMap::new();
+    let mut known_item_stack = None;
+    let mut known_job_stack = None;
+    known_items.insert(me, view.you.items.to_vec());
+    known_jobs.insert(me, view.you.job);
+
+    let attack_loser = |attacker, defender| if attacker == me { defender } else { attacker };
+    match &view.turn {
+        PerspectiveTurnState::DoingClairvoyant { item_stack: Some(stack), .. } => known_item_stack = Some(stack.clone()),
+        PerspectiveTurnState::UnsuccessfulDiplomat { target, inventory: Some(items), .. } => { known_items.insert(*target, items.clone()); }
+        PerspectiveTurnState::ResolvingTradeTrigger { receiver, trigger, .. } => match trigger {
+            PerspectiveTradeTriggerState::Priviledge { items: Some(items) } => { known_items.insert(*receiver, items.clone()); }
+            &PerspectiveTradeTriggerState::Monocle { faction: Some(faction), three_player_faction_index: None } => { known_factions.insert(*receiver, faction); }
+            &PerspectiveTradeTriggerState::Monocle { faction: Some(faction), three_player_faction_index: Some(i) } => { known_cards.insert(*receiver, (i, faction)); }
+            PerspectiveTradeTriggerState::Coat { available_jobs: Some(jobs) } => known_job_stack = Some(jobs.clone()),
+            _ => (),
+        },
+        &PerspectiveTurnState::Attacking { attacker, defender, ref state } => match state {
+            PerspectiveAttackState::FinishResolvingItems { target_items } => { known_items.insert(attack_loser(attacker, defender), target_items.clone()); }
+            &PerspectiveAttackState::FinishResolvingCredentials { target_faction, target_job } => {
+                known_jobs.insert(attack_loser(attacker, defender), target_job);
+                if n > 3 {
+                    known_factions.insert(attack_loser(attacker, defender), target_faction);
+                } else {
+                    // see `determinize_turn`
+                    known_cards.insert(attack_loser(attacker, defender), (0, target_faction));
+                }
+            }
+            _ => (),
+        },
+        _ => (),
+    }
+
+    // deal the items
+    let mut items: Vec<Item> = Item::all().flat_map(|i| vec![i; view.config.copies(i)]).collect();
+    for known in known_items.values() {
+        take(&mut items, known);
+    }
+    if let Some(stack) = &known_item_stack {
+        take(&mut items, stack);
+    }
+    items.shuffle(rng);
+    let mut inventories = IndexMap::new();
+    for p in &view.players {
+        let inventory = known_items.get(&p.player).cloned()
+            .unwrap_or_else(|| items.split_off(items.len().saturating_sub(p.item_count)));
+        inventories.insert(p.player, inventory);
+    }
+    let item_stack = known_item_stack.unwrap_or(items);
+
+    // deal the jobs
+    let mut jobs = view.config.jobs.clone();
+    take(&mut jobs, &known_jobs.values().copied().collect::<Vec<_>>());
+    if let Some(stack) = &known_job_stack {
+        take(&mut jobs, stack);
+    }
+    jobs.shuffle(rng);
+    let player_jobs: IndexMap<SeatId, Job> = view.players.iter()
+        .map(|p| (p.player, known_jobs.get(&p.player).copied().unwrap_or_else(|| jobs.pop().expect("enough jobs for everyone"))))
+        .collect();
+    let job_stack = known_job_stack.unwrap_or(jobs);
+
+    // deal the factions, same distribution as in `State::new`
+    let per_faction = if n == 3 { 5 } else { n - n / 2 };
+    let mut cards = vec![Faction::Order; per_faction];
+    cards.extend(vec![Faction::Brotherhood; per_faction]);
+    match &view.you.faction {
+        FactionKind::Normal(mine) => take(&mut cards, &[*mine]),
+        FactionKind::ThreePlayer(mine) => take(&mut cards, mine),
+    }
+    take(&mut cards, &known_factions.values().copied().collect::<Vec<_>>());
+    take(&mut cards, &known_cards.values().map(|&(_, f)| f).collect::<Vec<_>>());
+    cards.shuffle(rng);
+
+    let players = view.players.iter().map(|p| {
+        let faction = if p.player == me {
+            view.you.faction
+        } else if let Some(&f) = known_factions.get(&p.player) {
+            FactionKind::Normal(f)
+        } else if n == 3 {
+            let mut triple = [cards.pop(), cards.pop(), cards.pop()].map(|c| c.expect("enough factions for everyone"));
+            if let Some(&(i, f)) = known_cards.get(&p.player) {
+                cards.push(triple[i]);
+                triple[i] = f;
+            }
+            FactionKind::ThreePlayer(triple)
+        } else {
+            FactionKind::Normal(cards.pop().expect("enough factions for everyone"))
+        };
+        let state = PlayerState {
+            faction,
+            job: player_jobs[&p.player],
+            job_is_visible: p.job.is_some(),
+            items: inventories[&p.player].iter().copied().collect(),
+        };
+        (p.player, state)
+    }).collect();
+
+    let mut game = GameState {
+        p: GameStatePlayers::new(
+            players,
+            view.players.iter().map(|p| Seat { id: p.player, name: p.name.clone(), character: p.character }).collect(),
+        ),
+        item_stack,
+        job_stack,
+        action_log: view.action_log.clone(),
+        config: Arc::new(view.config.clone()),
+        rng: GameRng::new(rng.gen()),
+        victory: match &view.turn {
+            PerspectiveTurnState::GameOver { evaluation, .. } => evaluation.clone(),
+            _ => None,
+        },
+        former_jobs: Vec::new(),
+        turn_index: view.turn_index,
+        turn_player: turn_player(view),
+        journal: view.journal.iter().map(|e| (me, e.clone())).collect(),
+        sealed: view.sealed_answer.iter().map(|c| (me, c.clone())).collect(),
+        resigned: view.players.iter().filter(|p| p.resigned).map(|p| p.player).collect(),
+        last_round: view.last_round,
+    };
+    if let Some(summary) = &view.summary {
+        // once the game is over there is nothing left to guess
+        game.item_stack = summary.item_stack.clone();
+        game.job_stack = summary.job_stack.clone();
+        for p in &summary.players {
+            let (&job, former) = p.jobs.split_last().expect("everybody has a job");
+            let state = game.p.player_mut(p.player);
+            state.faction = p.faction;
+            state.job = job;
+            state.items = p.items.iter().copied().collect();
+            game.former_jobs.extend(former.iter().map(|&j| (p.player, j)));
+        }
+    }
+    let turn = determinize_turn(view, &game, me);
+    State { game, turn, setup: None, history: Vec::new(), last_command_undoable: false }
+}
+
+/// Whose turn `view.turn_index` counts, so that new rounds start where they would in the real game.
+/// Everything but resigning that is logged in a turn starts with the turn player acting.
+fn turn_player(view: &Perspective) -> Option<SeatId> {
+    if let PerspectiveTurnState::TurnStart { player } = view.turn {
+        return Some(player);
+    }
+    view.action_log.iter().rev()
+        .take_while(|a| a.turn == view.turn_index)
+        .filter(|a| !matches!(a.entry, ActionLogEntry::Resign { .. }))
+        .last()
+        .map(|a| a.entry.actor())
+}
+
+fn determinize_turn(view: &Perspective, game: &GameState, me: SeatId) -> TurnState {
+    use PerspectiveTurnState as P;
+    match view.turn.clone() {
+        P::TurnStart { player } => TurnState::WaitingForQuickblink(player),
+        P::TurnEndPhase { player } => TurnState::WaitingForEndTurn(player),
+        P::DoingClairvoyant { player, .. } => TurnState::DoingClairvoyant { clairvoyant: player, next: player },
+        P::UnsuccessfulDiplomat { diplomat, target, .. } => TurnState::UnsuccessfulDiplomat { diplomat, target },
+        P::GameOver { winner, .. } => TurnState::GameOver { winner },
+        P::TradePending { offerer, target, item } => {
+            let item = item.unwrap_or_else(|| game.p.player(offerer).items.iter().next().expect("offerers have something to offer"));
+            TurnState::TradePending { offerer, target, item }
+        }
+        P::ResolvingTradeTrigger { giver, receiver, trigger } => {
+            let trigger = match trigger {
+                PerspectiveTradeTriggerState::Priviledge { .. } => TradeTriggerState::Priviledge,
+                PerspectiveTradeTriggerState::Monocle { three_player_faction_index, .. } => TradeTriggerState::Monocle { three_player_faction_index },
+                PerspectiveTradeTriggerState::Coat { .. } => TradeTriggerState::Coat,
+                PerspectiveTradeTriggerState::Sextant { item_selections, is_forward } => TradeTriggerState::Sextant { item_selections, is_forward },
+            };
+            TurnState::ResolvingTradeTrigger { giver, receiver, trigger, next_state: FollowupState::end_phase(giver) }
+        }
+        P::Attacking { attacker, defender, state } => {
+            let winner = if me == attacker { AttackWinner::Attacker } else { AttackWinner::Defender };
+            let state = match state {
+                PerspectiveAttackState::Normal(state) => state,
+                PerspectiveAttackState::FinishResolvingNeedFactionIndex =>
+                    AttackState::FinishResolving { winner, steal_items: false, three_player_faction_index: None },
+                PerspectiveAttackState::FinishResolvingCredentials { .. } => {
+                    let three_player_faction_index = if view.players.len() == 3 { Some(0) } else { None };
+                    AttackState::FinishResolving { winner, steal_items: false, three_player_faction_index }
+                }
+                PerspectiveAttackState::FinishResolvingItems { .. } =>
+                    AttackState::FinishResolving { winner, steal_items: true, three_player_faction_index: None },
+            };
+            TurnState::Attacking { attacker, defender, state }
+        }
+        P::DonatingItem { donor } => TurnState::DonatingItem { donor, followup: FollowupState::end_phase(donor) },
+    }
+}
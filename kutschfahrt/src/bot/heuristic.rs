@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+
+use rand::prelude::*;
+use web_protocol::*;
+
+use super::{Agent, legal_commands};
+
+/// A simple rule-based player.
+///
+/// It keeps track of who it thinks is on its side (from its notebook and how people
+/// behaved in attacks), attacks suspected enemies, gets rid of the Black Pearl, announces victory
+/// as soon as it holds enough victory items on its own, and uses its job when that obviously helps.
+pub struct HeuristicAgent {
+    rng: StdRng,
+    /// positive means probably an enemy, negative probably a friend
+    suspicion: HashMap<SeatId, i32>,
+    /// how much of the action log we have already looked at
+    log_seen: usize,
+    /// the last attack whose votes we took into account
+    votes_seen: Option<(SeatId, SeatId, usize)>,
+}
+
+impl HeuristicAgent {
+    pub fn new(seed: u64) -> HeuristicAgent {
+        HeuristicAgent {
+            rng: StdRng::seed_from_u64(seed),
+            suspicion: HashMap::new(),
+            log_seen: 0,
+            votes_seen: None,
+        }
+    }
+
+    fn suspect(&mut self, p: SeatId, amount: i32) {
+        *self.suspicion.entry(p).or_insert(0) += amount;
+    }
+
+    /// `Some(true)` for (probable) enemies, `Some(false)` for (probable) friends
+    fn is_enemy(&self, view: &Perspective, p: SeatId) -> Option<bool> {
+        if let Some(f) = view.notebook.players.iter().find(|o| o.player == p).and_then(|o| o.faction()) {
+            return Some(f != view.you.effective_faction());
+        }
+        match self.suspicion.get(&p).copied().unwrap_or(0) {
+            s if s >= 2 => Some(true),
+            s if s <= -2 => Some(false),
+            _ => None,
+        }
+    }
+
+    fn observe(&mut self, view: &Perspective) {
+        let me = view.players[view.your_player_index].player;
+
+        for logged in &view.action_log[self.log_seen.min(view.action_log.len())..] {
+            match logged.entry {
+                ActionLogEntry::Attack { attacker, target } if target == me => self.suspect(attacker, 3),
+                ActionLogEntry::DonateItem { giver, receiver } if receiver == me => self.suspect(giver, -1),
+                _ => (),
+            }
+        }
+        self.log_seen = view.action_log.len();
+
+        if let PerspectiveTurnState::Attacking { attacker, defender, state: PerspectiveAttackState::Normal(AttackState::ItemsOrJobs { ref votes, .. }) } = view.turn {
+            // whoever sided against us (or a friend) in an attack is suspicious
+            let attack = Some((attacker, defender, view.action_log.len()));
+            if self.votes_seen != attack {
+                self.votes_seen = attack;
+                let defender_side = match (attacker == me, defender == me) {
+                    (true, _) => Some(false),
+                    (_, true) => Some(true),
+                    _ => match (self.is_enemy(view, attacker), self.is_enemy(view, defender)) {
+                        (Some(true), Some(false)) => Some(true),
+                        (Some(false), Some(true)) => Some(false),
+                        _ => None,
+                    },
+                };
+                if let Some(we_defend) = defender_side {
+                    for (&p, &vote) in votes {
+                        match (vote, we_defend) {
+                            (AttackSupport::Attack, true) | (AttackSupport::Defend, false) => self.suspect(p, 2),
+                            (AttackSupport::Defend, true) | (AttackSupport::Attack, false) => self.suspect(p, -2),
+                            (AttackSupport::Abstain, _) => (),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn victory_items(view: &Perspective) -> [Item; 2] {
+        match view.you.effective_faction() {
+            Faction::Order => [Item::Key, Item::BagKey],
+            Faction::Brotherhood => [Item::Goblet, Item::BagGoblet],
+        }
+    }
+
+    /// How much we want to keep an item
+    fn value(view: &Perspective, item: Item) -> i32 {
+        let [victory, bag] = Self::victory_items(view);
+        match item {
+            i if i == victory => 10,
+            i if i == bag => 8,
+            Item::BlackPearl => -10,
+            Item::Dagger | Item::Gloves | Item::PoisonRing | Item::CastingKnives | Item::Whip => 3,
+            Item::CoatOfArmorOfTheLoge => 2,
+            _ => 1,
+        }
+    }
+
+    fn least_valuable(view: &Perspective, items: impl Iterator<Item = Item>) -> Option<Item> {
+        items.min_by_key(|&i| Self::value(view, i))
+    }
+
+    fn victory_item_count(view: &Perspective) -> usize {
+        let [victory, bag] = Self::victory_items(view);
+        // bags only count once the stack is empty
+        view.you.items.iter().filter(|&i| i == victory || (i == bag && view.item_stack == 0)).count()
+    }
+
+    /// How many victory items announcing on our own takes. Like `State::evaluate_victory` counts it,
+    /// but we only take the smaller minority number once we know for sure that we are the minority.
+    fn needed_victory_items(view: &Perspective) -> usize {
+        let faction = view.you.effective_faction();
+        let known = |mine: bool| view.notebook.players.iter().filter(|o| o.faction().is_some_and(|f| (f == faction) == mine)).count();
+        let n = view.players.len();
+        if known(false) * 2 > n {
+            view.config.minority_victory_items
+        } else if known(true) * 2 >= n {
+            view.config.victory_items
+        } else {
+            view.config.victory_items.max(view.config.minority_victory_items)
+        }
+    }
+
+    fn turn_start(&mut self, view: &Perspective, me: SeatId) -> Vec<Command> {
+        let mut wishes = Vec::new();
+        let others: Vec<SeatId> = view.players.iter().map(|p| p.player).filter(|&p| p != me).collect();
+
+        if Self::victory_item_count(view) >= Self::needed_victory_items(view) {
+            wishes.push(Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: Vec::new() } });
+        }
+
+        let mut enemies: Vec<SeatId> = others.iter().copied().filter(|&p| self.is_enemy(view, p) == Some(true)).collect();
+        enemies.sort_by_key(|p| -self.suspicion.get(p).copied().unwrap_or(0));
+        let not_friends: Vec<SeatId> = others.iter().copied().filter(|&p| self.is_enemy(view, p) != Some(false)).collect();
+
+        if view.you.items.contains(&Item::BlackPearl) {
+            let target = enemies.first().or_else(|| not_friends.choose(&mut self.rng)).or_else(|| others.choose(&mut self.rng));
+            if let Some(&target) = target {
+                wishes.push(Command::OfferTrade { target, item: Item::BlackPearl });
+            }
+        }
+
+        let [victory, _] = Self::victory_items(view);
+        if view.you.can_use_job(Job::Diplomat) {
+            if let (Some(&target), Some(return_item)) = (enemies.first(), Self::least_valuable(view, view.you.items.iter())) {
+                wishes.push(Command::UseDiplomat { target, item: victory, return_item });
+            }
+        }
+        if view.you.can_use_job(Job::Clairvoyant) && view.item_stack >= 2 && self.rng.gen_ratio(1, 3) {
+            wishes.push(Command::UseClairvoyant);
+        }
+
+        if let Some(&target) = enemies.first() {
+            wishes.push(Command::InitiateAttack { player: target });
+        }
+
+        // pass on something useless to somebody who might be a friend
+        let friends: Vec<SeatId> = others.iter().copied().filter(|&p| self.is_enemy(view, p) == Some(false)).collect();
+        if let (Some(&target), Some(item)) = (friends.choose(&mut self.rng), Self::least_valuable(view, view.you.items.iter())) {
+            if Self::value(view, item) < 3 {
+                wishes.push(Command::OfferTrade { target, item });
+            }
+        }
+
+        // poke around a bit every now and then to learn something
+        if self.rng.gen_ratio(1, 4) {
+            if let Some(&target) = not_friends.choose(&mut self.rng) {
+                wishes.push(Command::InitiateAttack { player: target });
+            }
+        }
+        wishes.push(Command::Pass);
+        wishes
+    }
+
+    fn attack(&mut self, view: &Perspective, me: SeatId, attacker: SeatId, defender: SeatId, state: &PerspectiveAttackState, legal: &[Command]) -> Vec<Command> {
+        // which side we're on: Some(true) for the attacker
+        let side = if me == attacker {
+            Some(true)
+        } else if me == defender {
+            Some(false)
+        } else {
+            match (self.is_enemy(view, attacker), self.is_enemy(view, defender)) {
+                (Some(false), Some(true)) | (Some(false), None) | (None, Some(true)) => Some(true),
+                (Some(true), Some(false)) | (Some(true), None) | (None, Some(false)) => Some(false),
+                _ => None,
+            }
+        };
+        let loser = if me == attacker { defender } else { attacker };
+
+        match state {
+            PerspectiveAttackState::Normal(AttackState::WaitingForPriest { .. }) => {
+                vec![Command::UsePriest { priest: side == Some(false) }, Command::UsePriest { priest: false }]
+            }
+            PerspectiveAttackState::Normal(AttackState::PayingPriest { .. }) => {
+                Self::least_valuable(view, view.you.items.iter()).map(|item| Command::PayPriest { item }).into_iter().collect()
+            }
+            PerspectiveAttackState::Normal(AttackState::DeclaringSupport(_)) => {
+                let support = match side {
+                    Some(true) => AttackSupport::Attack,
+                    Some(false) => AttackSupport::Defend,
+                    None => AttackSupport::Abstain,
+                };
+                vec![Command::DeclareSupport { support }]
+            }
+            PerspectiveAttackState::Normal(AttackState::WaitingForHypnotizer(votes)) => {
+                // turn around whoever supports the other side
+                let wrong_vote = if side == Some(true) { AttackSupport::Defend } else { AttackSupport::Attack };
+                votes.iter().filter(|&(_, &v)| v == wrong_vote).map(|(&target, _)| Command::Hypnotize { target: Some(target) })
+                    .chain(std::iter::once(Command::Hypnotize { target: None }))
+                    .collect()
+            }
+            PerspectiveAttackState::Normal(AttackState::ItemsOrJobs { .. }) => {
+                let mut wishes = Vec::new();
+                if side.is_some() {
+                    // only buffs that push towards our side are legal for us
+                    wishes.extend(legal.iter().filter(|c| match c {
+                        Command::ItemOrJob { buff: Some(buff), target: None } => buff.rule().is_some_and(|r| r.effect != BuffEffect::EndFight),
+                        _ => false,
+                    }).cloned());
+                    if let Some(poison_target) = match side {
+                        Some(true) => Some(defender),
+                        Some(false) => Some(attacker),
+                        None => None,
+                    } {
+                        wishes.extend(legal.iter().filter(|c| matches!(c, Command::ItemOrJob { target, .. } if *target == Some(poison_target))).cloned());
+                    }
+                }
+                wishes.push(Command::ItemOrJob { buff: None, target: None });
+                wishes
+            }
+            PerspectiveAttackState::Normal(AttackState::Resolving { .. }) => {
+                // find out who they are unless we're sure already
+                let steal = self.is_enemy(view, loser) == Some(true) || self.rng.gen_ratio(1, 3);
+                vec![Command::ClaimReward { steal_items: steal }]
+            }
+            PerspectiveAttackState::FinishResolvingItems { target_items } => {
+                let best = target_items.iter().copied().max_by_key(|&i| Self::value(view, i));
+                let mut wishes = Vec::new();
+                if let Some(item) = best {
+                    wishes.push(Command::StealItem { item, give_back: None });
+                    let mut mine = view.you.items;
+                    mine.push(item);
+                    if let Some(give_back) = Self::least_valuable(view, mine.iter()) {
+                        wishes.push(Command::StealItem { item, give_back: Some(give_back) });
+                    }
+                }
+                wishes
+            }
+            PerspectiveAttackState::FinishResolvingNeedFactionIndex => {
+                vec![Command::ThreePlayerSelectFactionIndex { index: self.rng.gen_range(0..3) }]
+            }
+            _ => vec![Command::DoneLookingAtThings],
+        }
+    }
+}
+
+impl Agent for HeuristicAgent {
+    fn choose(&mut self, view: &Perspective) -> Command {
+        self.observe(view);
+        let legal = legal_commands(view);
+        if legal.len() == 1 {
+            return legal[0].clone();
+        }
+
+        let me = view.players[view.your_player_index].player;
+        let my_items = view.you.items.iter();
+        let wishes = match &view.turn {
+            &PerspectiveTurnState::TurnStart { .. } => self.turn_start(view, me),
+            PerspectiveTurnState::TurnEndPhase { .. } => vec![Command::Pass],
+            PerspectiveTurnState::DoingClairvoyant { item_stack: Some(stack), .. } => {
+                // what we want sits on top, so whoever draws next helps our cause
+                let mut stack = stack.clone();
+                stack.sort_by_key(|&i| -Self::value(view, i));
+                stack.truncate(2);
+                vec![Command::ClairvoyantSetItems { top_items: stack }]
+            }
+            &PerspectiveTurnState::TradePending { offerer, item: Some(item), .. } => {
+                let mut wishes = Vec::new();
+                if self.is_enemy(view, offerer) == Some(true) || item == Item::BlackPearl && self.rng.gen_ratio(1, 2) {
+                    wishes.push(Command::RejectTrade);
+                }
+                let bag_offered = matches!(item, Item::BagKey | Item::BagGoblet);
+                let mut candidates: Vec<Item> = my_items.filter(|&i| !(bag_offered && matches!(i, Item::BagKey | Item::BagGoblet))).collect();
+                candidates.sort_by_key(|&i| Self::value(view, i));
+                wishes.extend(candidates.into_iter().map(|item| Command::AcceptTrade { item }));
+                wishes
+            }
+            &PerspectiveTurnState::Attacking { attacker, defender, ref state } => self.attack(view, me, attacker, defender, state, &legal),
+            PerspectiveTurnState::ResolvingTradeTrigger { trigger, .. } => match trigger {
+                PerspectiveTradeTriggerState::Coat { available_jobs: Some(jobs) } => {
+                    // fighting jobs are the most generally useful
+                    let preferred = [Job::Duelist, Job::Thug, Job::GrandMaster, Job::Bodyguard];
+                    let mut jobs = jobs.clone();
+                    jobs.sort_by_key(|j| preferred.iter().position(|p| p == j).unwrap_or(preferred.len()));
+                    jobs.into_iter().map(|job| Command::PickNewJob { job }).collect()
+                }
+                PerspectiveTradeTriggerState::Sextant { is_forward: None, .. } => vec![Command::SetSextantDirection { forward: self.rng.gen() }],
+                PerspectiveTradeTriggerState::Sextant { .. } => {
+                    Self::least_valuable(view, my_items).map(|item| Command::SelectSextantItem { item }).into_iter().collect()
+                }
+                _ => vec![Command::DoneLookingAtThings],
+            },
+            PerspectiveTurnState::DonatingItem { .. } => {
+                let mut targets: Vec<SeatId> = view.players.iter().map(|p| p.player).filter(|&p| p != me).collect();
+                targets.sort_by_key(|&p| self.suspicion.get(&p).copied().unwrap_or(0));
+                let item = Self::least_valuable(view, my_items);
+                targets.into_iter().filter_map(|target| Some(Command::DonateItem { target, item: item? })).collect()
+            }
+            _ => vec![Command::DoneLookingAtThings],
+        };
+
+        wishes.into_iter().find(|c| legal.contains(c))
+            .or_else(|| legal.choose(&mut self.rng).cloned())
+            .expect("agents are only asked when they have something to do")
+    }
+}
@@ -0,0 +1,137 @@
+use rand::prelude::*;
+use web_protocol::*;
+
+use crate::State;
+use super::{Agent, determinize, random_command};
+
+/// How long a rollout may go on before we call it a draw
+const ROLLOUT_CAP: usize = 200;
+const EXPLORATION: f64 = 0.7;
+
+/// Information set Monte Carlo tree search.
+///
+/// Every iteration deals a fresh guess of the hidden information (see `determinize`), walks down
+/// the tree along the commands that are legal in that guess and finishes the game with random moves.
+/// The tree is shared between all guesses, so it ends up judging commands by how well they do
+/// across everything the player might be facing.
+pub struct IsmctsAgent {
+    rng: StdRng,
+    iterations: usize,
+}
+
+impl IsmctsAgent {
+    pub fn new(seed: u64, iterations: usize) -> IsmctsAgent {
+        IsmctsAgent { rng: StdRng::seed_from_u64(seed), iterations }
+    }
+}
+
+struct Node {
+    /// the move leading here
+    actor: SeatId,
+    command: Command,
+    visits: u32,
+    /// summed up results for `actor`
+    reward: f64,
+    /// how often this node's move was legal when its parent was visited
+    availability: u32,
+    children: Vec<usize>,
+}
+
+/// Whoever is asked first, the same way `play_bots` would ask them
+fn next_actor(s: &State) -> Option<(SeatId, Vec<Command>)> {
+    s.pending_actors().first().map(|&p| (p, s.legal_commands(p)))
+}
+
+/// The commands worth searching: announcing with more than one teammate is left out,
+/// since there are far too many ways to do it and the tree would never get past them.
+fn search_commands(cmds: Vec<Command>) -> Vec<Command> {
+    cmds.into_iter()
+        .filter(|c| !matches!(c, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates } } if teammates.len() > 1))
+        .collect()
+}
+
+/// 1 for a win, 0 for a loss and 0.5 if nobody won
+fn result(s: &State, p: SeatId) -> f64 {
+    match &s.turn {
+        TurnState::GameOver { winner: WinningFaction::Normal(f) | WinningFaction::Tiebreak(f) } => if s.game.p.player(p).effective_faction() == *f { 1.0 } else { 0.0 },
+        &TurnState::GameOver { winner: WinningFaction::Traitor(t) } => if t == p { 1.0 } else { 0.0 },
+        _ => 0.5,
+    }
+}
+
+impl IsmctsAgent {
+    fn iterate(&mut self, view: &Perspective, nodes: &mut Vec<Node>) {
+        let me = view.players[view.your_player_index].player;
+        let mut s = determinize(view, &mut self.rng);
+        let mut path = Vec::new();
+        let mut current = 0;
+
+        // selection and expansion, starting with our own move even if others could act as well
+        let mut next = Some((me, s.legal_commands(me)));
+        while let Some((actor, cmds)) = next {
+            let mut available = Vec::new();
+            let mut untried = Vec::new();
+            for c in search_commands(cmds) {
+                match nodes[current].children.iter().find(|&&i| nodes[i].actor == actor && nodes[i].command == c) {
+                    Some(&i) => {
+                        nodes[i].availability += 1;
+                        available.push(i);
+                    }
+                    None => untried.push(c),
+                }
+            }
+            let chosen = if let Some(command) = untried.choose(&mut self.rng).cloned() {
+                nodes.push(Node { actor, command, visits: 0, reward: 0.0, availability: 1, children: Vec::new() });
+                let i = nodes.len() - 1;
+                nodes[current].children.push(i);
+                i
+            } else {
+                let ucb = |n: &Node| n.reward / n.visits as f64 + EXPLORATION * ((n.availability as f64).ln() / n.visits as f64).sqrt();
+                available.into_iter().max_by(|&a, &b| ucb(&nodes[a]).partial_cmp(&ucb(&nodes[b])).unwrap())
+                    .expect("a player with legal commands has some child to go to")
+            };
+            // the command came straight from `legal_commands`, so none of `apply_command`'s precautions are needed
+            s.apply_command_unchecked(actor, nodes[chosen].command.clone()).expect("legal commands are accepted");
+            path.push(chosen);
+            current = chosen;
+            if nodes[chosen].visits == 0 {
+                break;
+            }
+            next = next_actor(&s);
+        }
+
+        // rollout
+        for _ in 0..ROLLOUT_CAP {
+            let (actor, cmds) = match next_actor(&s) {
+                Some(next) => next,
+                None => break,
+            };
+            let c = random_command(cmds, &mut self.rng).expect("next_actor only returns players with commands");
+            s.apply_command_unchecked(actor, c).expect("legal commands are accepted");
+        }
+
+        // backpropagation
+        for i in path {
+            nodes[i].visits += 1;
+            nodes[i].reward += result(&s, nodes[i].actor);
+        }
+    }
+}
+
+impl Agent for IsmctsAgent {
+    fn choose(&mut self, view: &Perspective) -> Command {
+        let me = view.players[view.your_player_index].player;
+        let mut nodes = vec![Node { actor: me, command: Command::Pass, visits: 0, reward: 0.0, availability: 0, children: Vec::new() }];
+        for _ in 0..self.iterations {
+            self.iterate(view, &mut nodes);
+        }
+
+        let legal = super::legal_commands(view);
+        nodes[0].children.iter().map(|&i| &nodes[i])
+            .filter(|n| legal.contains(&n.command))
+            .max_by_key(|n| n.visits)
+            .map(|n| n.command.clone())
+            .or_else(|| random_command(legal, &mut self.rng))
+            .expect("agents are only asked when they have something to do")
+    }
+}
@@ -0,0 +1,102 @@
+//! Computer players.
+//!
+//! Agents only ever get to see a player's `Perspective`, just like a human at the table.
+//! Whatever they need to know about the rules (e.g. which commands they may issue)
+//! they get from a `determinize`d state that is consistent with that perspective.
+
+use std::collections::HashMap;
+
+use rand::prelude::*;
+use web_protocol::*;
+
+use crate::{State, CommandError};
+
+mod determinize;
+mod heuristic;
+mod ismcts;
+
+pub use determinize::determinize;
+pub use heuristic::HeuristicAgent;
+pub use ismcts::IsmctsAgent;
+
+pub trait Agent {
+    /// Picks the next command for the player whose view this is.
+    /// Only called when that player actually has something to do.
+    fn choose(&mut self, view: &Perspective) -> Command;
+}
+
+/// Every command the owner of `view` may issue right now.
+pub fn legal_commands(view: &Perspective) -> Vec<Command> {
+    let me = view.players[view.your_player_index].player;
+    let s = determinize(view, &mut StdRng::seed_from_u64(0));
+    s.legal_commands(me).into_iter()
+        // whether this succeeds depends on the target's hidden inventory
+        .filter(|c| !matches!(c, Command::UseDiplomat { item, return_item, .. } if is_bag(*item) && is_bag(*return_item)))
+        .collect()
+}
+
+fn is_bag(item: Item) -> bool {
+    matches!(item, Item::BagKey | Item::BagGoblet)
+}
+
+/// Picks any legal command, with every kind of command being equally likely.
+/// Victory announcements end the game right away, so they only come up now and then.
+pub struct RandomAgent {
+    rng: StdRng,
+}
+impl RandomAgent {
+    pub fn new(seed: u64) -> RandomAgent {
+        RandomAgent { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+impl Agent for RandomAgent {
+    fn choose(&mut self, view: &Perspective) -> Command {
+        random_command(legal_commands(view), &mut self.rng).expect("agents are only asked when they have something to do")
+    }
+}
+
+fn random_command(legal: Vec<Command>, rng: &mut impl Rng) -> Option<Command> {
+    let mut kinds: Vec<Vec<Command>> = Vec::new();
+    let mut announcements = Vec::new();
+    for c in legal {
+        if let Command::AnnounceVictory { .. } = c {
+            announcements.push(c);
+            continue;
+        }
+        match kinds.iter_mut().find(|k| std::mem::discriminant(&k[0]) == std::mem::discriminant(&c)) {
+            Some(k) => k.push(c),
+            None => kinds.push(vec![c]),
+        }
+    }
+    if kinds.is_empty() || rng.gen_ratio(1, 40) {
+        if let Some(c) = announcements.choose(rng) {
+            return Some(c.clone());
+        }
+    }
+    kinds.choose(rng).and_then(|k| k.choose(rng)).cloned()
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("The agent for {player:?} chose {command:?}, which was rejected: {error}")]
+pub struct BotError {
+    pub player: SeatId,
+    pub command: Command,
+    pub error: CommandError,
+}
+
+/// Lets the agents act for their seats until only players without an agent can act,
+/// the game is over, or `max_commands` commands were issued. Returns the number of commands issued.
+pub fn play_bots(state: &mut State, agents: &mut HashMap<SeatId, Box<dyn Agent>>, max_commands: usize) -> Result<usize, BotError> {
+    let mut issued = 0;
+    while issued < max_commands {
+        let next = state.pending_actors().into_iter().find(|p| agents.contains_key(p));
+        let player = match next {
+            Some(p) => p,
+            None => break,
+        };
+        let command = agents.get_mut(&player).unwrap().choose(&state.perspective(player));
+        state.apply_command(player, command.clone()).map_err(|error| BotError { player, command, error })?;
+        issued += 1;
+    }
+    Ok(issued)
+}
@@ -0,0 +1,59 @@
+use super::*;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("{players} players need at least {players} jobs, but only {available} are in the game")]
+    NotEnoughJobs { players: usize, available: usize },
+    #[error("{players} players need at least {needed} start items besides the bags, but only {available} are in the game")]
+    NotEnoughStartItems { players: usize, needed: usize, available: usize },
+    #[error("{0:?} is in the game more than once")]
+    DuplicateJob(Job),
+    #[error("{0:?} can't be listed as a start or stack item")]
+    InvalidItem(Item),
+    #[error("With {players} players and an inventory limit of {limit}, there might be nobody left to donate to")]
+    InventoryLimitTooLow { players: usize, limit: usize },
+    #[error("There can't be more than {} copies of {0:?}", Inventory::MAX_COPIES)]
+    TooManyCopies(Item),
+    #[error("The round limit has to allow at least one round")]
+    NoRounds,
+}
+
+/// Checks that a game with `config` can be dealt for `players` players and can't get stuck.
+pub fn check_config(config: &GameConfig, players: usize) -> Result<(), ConfigError> {
+    for (i, &job) in config.jobs.iter().enumerate() {
+        if config.jobs[..i].contains(&job) {
+            return Err(ConfigError::DuplicateJob(job));
+        }
+    }
+    if config.jobs.len() < players {
+        return Err(ConfigError::NotEnoughJobs { players, available: config.jobs.len() });
+    }
+
+    let listed: Vec<Item> = config.start_items.iter().chain(&config.stack_items).copied().collect();
+    for (i, &item) in listed.iter().enumerate() {
+        let special = matches!(item, Item::Key | Item::Goblet | Item::BagKey | Item::BagGoblet);
+        if special || listed[..i].contains(&item) {
+            return Err(ConfigError::InvalidItem(item));
+        }
+    }
+    if let Some(item) = Item::all().find(|&i| config.copies(i) > Inventory::MAX_COPIES) {
+        return Err(ConfigError::TooManyCopies(item));
+    }
+    let needed = players.saturating_sub(2);
+    let available = config.keys + config.goblets + config.start_items.len();
+    if available < needed {
+        return Err(ConfigError::NotEnoughStartItems { players, needed, available });
+    }
+
+    // whoever has to donate holds more than the limit, so if all items fit below the limit
+    // everywhere else there is always somebody to donate to
+    let limit = config.inventory_limit(players);
+    let total = Item::all().map(|i| config.copies(i)).sum::<usize>();
+    if limit * players < total {
+        return Err(ConfigError::InventoryLimitTooLow { players, limit });
+    }
+    if config.round_limit.rounds == Some(0) {
+        return Err(ConfigError::NoRounds);
+    }
+    Ok(())
+}
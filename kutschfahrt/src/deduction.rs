@@ -0,0 +1,222 @@
+//! What a player can tell about the hidden parts of the game from their `Perspective`.
+//!
+//! Factions never change, so they are exact: the cards you neither hold nor got to see
+//! are a random draw from the part of the deck you haven't seen.
+//! Jobs only move through the Coat and the Tome, whose trade triggers are public, so jobs you saw are
+//! followed until one of those makes them uncertain again.
+//! Items move around in secret all the time. An inventory you saw only counts as long as its owner
+//! did nothing with items since, and items passed on with a public trade trigger only count until
+//! the receiver does something else. Whatever is left is assumed to be spread evenly over the
+//! remaining inventories and the stack, which is a fair guess but no exact count.
+
+use super::*;
+
+/// What the owner of `view` can tell about everybody
+pub fn deduce(view: &Perspective) -> Notebook {
+    let (faction_worlds, factions) = deduce_factions(view);
+    let (job_worlds, jobs) = deduce_jobs(view);
+    let items = deduce_items(view);
+    let players = view.players.iter().zip(factions).zip(jobs).zip(items)
+        .map(|(((p, (order, order_cards)), jobs), items)| PlayerOdds { player: p.player, order, order_cards, jobs, items })
+        .collect();
+    Notebook { faction_worlds, job_worlds, players }
+}
+
+fn index(view: &Perspective, p: SeatId) -> usize {
+    view.players.iter().position(|q| q.player == p).expect("Invalid player")
+}
+
+/// The number of ways to draw `k` out of `n` different cards, in order
+fn falling(n: usize, k: usize) -> f64 {
+    if k > n {
+        0.0
+    } else {
+        (n - k + 1..=n).map(|x| x as f64).product()
+    }
+}
+
+/// The number of ways to pick `k` out of `n`
+fn choose(n: usize, k: usize) -> f64 {
+    falling(n, k) / falling(k, k)
+}
+
+fn faction_cards(f: FactionKind) -> [Option<Faction>; 3] {
+    match f {
+        FactionKind::Normal(f) => [Some(f), None, None],
+        FactionKind::ThreePlayer(cards) => cards.map(Some),
+    }
+}
+
+/// The factions of everybody, or rather each player's chance of being on the side of the Order,
+/// along with that of each of their cards in three player games
+type FactionOdds = Vec<(f64, Option<[f64; 3]>)>;
+
+fn deduce_factions(view: &Perspective) -> (u64, FactionOdds) {
+    let n = view.players.len();
+    // same deck as in `State::new`
+    let (cards, per_faction) = if n == 3 { (3, 5) } else { (1, n - n / 2) };
+
+    let mut known = vec![[None; 3]; n];
+    known[view.your_player_index] = faction_cards(view.you.faction);
+    for e in &view.journal {
+        if let Reveal::Monocle { player, faction, three_player_faction_index } | Reveal::Credentials { player, faction, three_player_faction_index, .. } = e.reveal {
+            known[index(view, player)][three_player_faction_index.unwrap_or(0)] = Some(faction);
+        }
+    }
+    if let Some(summary) = &view.summary {
+        for p in &summary.players {
+            known[index(view, p.player)] = faction_cards(p.faction);
+        }
+    }
+
+    // the deal is random, so the cards we don't know are a random draw from the ones we haven't seen
+    let seen = |f| known.iter().flat_map(|c| &c[..cards]).filter(|&&c| c == Some(f)).count();
+    let (order, brotherhood) = (per_faction - seen(Faction::Order), per_faction - seen(Faction::Brotherhood));
+    let unknown = known.iter().flat_map(|c| &c[..cards]).filter(|c| c.is_none()).count();
+    let worlds = (0..=unknown).filter(|&k| k <= order && unknown - k <= brotherhood).map(|k| choose(unknown, k)).sum::<f64>();
+    let card_odds = order as f64 / (order + brotherhood) as f64;
+
+    let odds = known.iter().map(|c| {
+        let known_order = c[..cards].iter().filter(|&&f| f == Some(Faction::Order)).count();
+        let free = c[..cards].iter().filter(|f| f.is_none()).count();
+        // add up the chances of drawing enough Order cards among the free ones
+        let needed = (cards / 2 + 1).saturating_sub(known_order);
+        let chance = (needed..=free)
+            .map(|j| choose(order, j) * choose(brotherhood, free - j) / choose(order + brotherhood, free))
+            .sum();
+        let card = |f: Option<Faction>| f.map_or(card_odds, |f| if f == Faction::Order { 1.0 } else { 0.0 });
+        (chance, Some([card(c[0]), card(c[1]), card(c[2])]).filter(|_| n == 3))
+    }).collect();
+    (worlds as u64, odds)
+}
+
+/// The chance of everybody having each job
+fn deduce_jobs(view: &Perspective) -> (u64, Vec<Vec<(Job, f64)>>) {
+    let n = view.players.len();
+    let mut known = vec![None; n];
+
+    // follow the jobs we saw through the log
+    let mut reveals = view.journal.iter().filter_map(|e| match e.reveal {
+        Reveal::Credentials { player, job, .. } => Some((e.log_position, player, job)),
+        _ => None,
+    }).peekable();
+    for logged in &view.action_log {
+        while let Some((_, player, job)) = reveals.next_if(|&(position, ..)| position <= logged.seq) {
+            known[index(view, player)] = Some(job);
+        }
+        if let ActionLogEntry::TradeTrigger { giver, receiver, item } = logged.entry {
+            match item {
+                // whoever passes on the coat picks a new job
+                Item::Coat => known[index(view, giver)] = None,
+                Item::Tome => known.swap(index(view, giver), index(view, receiver)),
+                _ => (),
+            }
+        }
+    }
+    for (_, player, job) in reveals {
+        known[index(view, player)] = Some(job);
+    }
+    for (i, p) in view.players.iter().enumerate() {
+        if p.job.is_some() {
+            known[i] = p.job;
+        }
+    }
+    known[view.your_player_index] = Some(view.you.job);
+    if let Some(summary) = &view.summary {
+        for p in &summary.players {
+            known[index(view, p.player)] = p.jobs.last().copied();
+        }
+    }
+
+    let left: Vec<Job> = view.config.jobs.iter().copied().filter(|j| !known.contains(&Some(*j))).collect();
+    let unknown = known.iter().filter(|j| j.is_none()).count();
+    let odds = known.into_iter().map(|job| match job {
+        Some(job) => vec![(job, 1.0)],
+        None => left.iter().map(|&j| (j, 1.0 / left.len() as f64)).collect(),
+    }).collect();
+    (falling(left.len(), unknown) as u64, odds)
+}
+
+/// Whether `entry` might have changed what `p` holds
+fn moves_items(entry: &ActionLogEntry, p: SeatId) -> bool {
+    match *entry {
+        ActionLogEntry::Pass { .. } | ActionLogEntry::AnnounceVictory { .. } | ActionLogEntry::UseClairvoyant { .. } | ActionLogEntry::Resign { .. } => false,
+        ActionLogEntry::UseDiplomat { actor, target, success, .. } => success && (actor == p || target == p),
+        ActionLogEntry::TradeOffer { offerer, target, accepted } => accepted && (offerer == p || target == p),
+        // the loser may have to hand over an item, and the attacker may have paid a priest, who isn't logged
+        ActionLogEntry::Attack { .. } => true,
+        // everybody passes on an item afterwards
+        ActionLogEntry::TradeTrigger { item: Item::Sextant, .. } => true,
+        ActionLogEntry::TradeTrigger { giver, receiver, .. } | ActionLogEntry::DonateItem { giver, receiver } => giver == p || receiver == p,
+    }
+}
+
+/// The chance of everybody holding at least one of each item
+fn deduce_items(view: &Perspective) -> Vec<Vec<(Item, f64)>> {
+    let n = view.players.len();
+    // whole inventories we know, and single items we know somebody holds
+    let mut exact: Vec<Option<Vec<Item>>> = vec![None; n];
+    let mut held: Vec<Vec<Item>> = vec![Vec::new(); n];
+
+    let mut reveals = view.journal.iter().filter_map(|e| match &e.reveal {
+        Reveal::Priviledge { player, items } | Reveal::Diplomat { player, items } => Some((e.log_position, *player, items)),
+        // the winner of a fight steals from the inventory right after seeing it
+        Reveal::AttackItems { .. } => None,
+        Reveal::Monocle { .. } | Reveal::Credentials { .. } | Reveal::ItemStack { .. } => None,
+    }).peekable();
+    for logged in &view.action_log {
+        while let Some((_, player, items)) = reveals.next_if(|&(position, ..)| position <= logged.seq) {
+            exact[index(view, player)] = Some(items.clone());
+        }
+        for (i, p) in view.players.iter().enumerate() {
+            if moves_items(&logged.entry, p.player) {
+                exact[i] = None;
+                held[i].clear();
+            }
+        }
+        if let ActionLogEntry::TradeTrigger { receiver, item, .. } = logged.entry {
+            // bags are all shown as the same one
+            if !matches!(item, Item::BagKey | Item::BagGoblet | Item::Sextant) {
+                held[index(view, receiver)].push(item);
+            }
+        }
+    }
+    for (_, player, items) in reveals {
+        exact[index(view, player)] = Some(items.clone());
+    }
+    exact[view.your_player_index] = Some(view.you.items.to_vec());
+    if let Some(summary) = &view.summary {
+        for p in &summary.players {
+            exact[index(view, p.player)] = Some(p.items.clone());
+        }
+    }
+
+    // whatever we don't know the place of is spread over the unknown parts of the inventories and the stack
+    let mut pool: Vec<usize> = Item::all().map(|i| view.config.copies(i)).collect();
+    let mut slots = Vec::with_capacity(n);
+    for (i, p) in view.players.iter().enumerate() {
+        let known = match &exact[i] {
+            Some(items) if items.len() == p.item_count => items,
+            _ => {
+                exact[i] = None;
+                held[i].truncate(p.item_count);
+                &held[i]
+            }
+        };
+        for &item in known {
+            pool[item as usize] = pool[item as usize].saturating_sub(1);
+        }
+        slots.push(p.item_count - known.len());
+    }
+    let total = slots.iter().sum::<usize>() + view.item_stack;
+
+    (0..n).map(|i| Item::all().filter_map(|item| {
+        let chance = match &exact[i] {
+            Some(items) => if items.contains(&item) { 1.0 } else { 0.0 },
+            None if held[i].contains(&item) => 1.0,
+            // one minus the chance that none of the player's unknown items is one of these
+            None => 1.0 - (0..slots[i]).map(|j| (total - pool[item as usize]).saturating_sub(j) as f64 / (total - j) as f64).product::<f64>(),
+        };
+        Some((item, chance)).filter(|_| chance > 0.0)
+    }).collect()).collect()
+}
@@ -0,0 +1,218 @@
+//! Plays lots of random games and checks that the engine survives them.
+//!
+//! Every game is determined by its seeds, so a failure can be reproduced and shrunk
+//! to the shortest command sequence that still shows it.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use super::*;
+use crate::tests::seats;
+
+/// Games per player count in the default test run, enough to get every rule variant once
+const SMOKE_GAMES: u64 = 12;
+/// Games per player count in the full run, which takes minutes: `cargo test -- --ignored`
+const GAMES_PER_PLAYER_COUNT: u64 = 250;
+const MOVE_CAP: usize = 300;
+
+#[derive(Debug)]
+enum Failure {
+    Panic(String),
+    Invariant(InvariantViolation),
+    Perspective(SeatId, String),
+}
+
+fn catch<T>(f: impl FnOnce() -> T) -> Result<T, Failure> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|e| {
+        let msg = e.downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| e.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Failure::Panic(msg)
+    })
+}
+
+/// Whether `p` is the one player who may see more than a spectator right now
+fn sees_more_than_spectators(s: &State, p: SeatId) -> bool {
+    match &s.turn {
+        &TurnState::DoingClairvoyant { clairvoyant, .. } => p == clairvoyant,
+        &TurnState::UnsuccessfulDiplomat { diplomat, .. } => p == diplomat,
+        &TurnState::TradePending { target, .. } => p == target,
+        TurnState::ResolvingTradeTrigger { giver, trigger, .. } => match trigger {
+            TradeTriggerState::Sextant { item_selections, .. } => item_selections.contains_key(&p),
+            _ => p == *giver,
+        },
+        &TurnState::Attacking { attacker, defender, state: AttackState::FinishResolving { winner, .. } } => match winner {
+            AttackWinner::Attacker => p == attacker,
+            AttackWinner::Defender => p == defender,
+        },
+        _ => false,
+    }
+}
+
+fn check_perspectives(s: &State) -> Result<(), Failure> {
+    let spectator = catch(|| s.spectate())?;
+    for (i, (p, state)) in s.game.p.iter().enumerate() {
+        let fail = |msg: &str| Err(Failure::Perspective(p, msg.to_string()));
+        let view = catch(|| s.perspective(p))?;
+        // every state can be told, in every language
+        catch(|| (view.narrate(Locale::English), view.narrate(Locale::German)))?;
+        if view.you != *state {
+            return fail("wrong own player state");
+        }
+        if view.your_player_index != i {
+            return fail("wrong player index");
+        }
+        if view.players != spectator.players || view.item_stack != spectator.item_stack || view.action_log != spectator.action_log || view.turn_index != spectator.turn_index || view.summary != spectator.summary {
+            return fail("public information differs from the spectator view");
+        }
+        if view.turn != spectator.turn && !sees_more_than_spectators(s, p) {
+            return fail("sees something they should not");
+        }
+        let mut pending = spectator.pending_actors.clone();
+        // only you know that you already answered in secret
+        if view.sealed_answer.is_some() {
+            pending.retain(|&q| q != p);
+        }
+        if view.pending_actors != pending {
+            return fail("disagrees with the spectator about who can act");
+        }
+        if view.pending_actors.contains(&p) == s.legal_commands(p).is_empty() {
+            return fail("pending, but has no legal commands or the other way around");
+        }
+    }
+    Ok(())
+}
+
+/// Applies a single command and checks everything we can check afterwards.
+/// Returns whether the command was accepted.
+fn step(s: &mut State, actor: SeatId, c: Command) -> Result<bool, Failure> {
+    if catch(|| s.apply_command(actor, c))?.is_err() {
+        return Ok(false);
+    }
+    s.check_invariants().map_err(Failure::Invariant)?;
+    check_perspectives(s)?;
+    Ok(true)
+}
+
+/// Replays `commands` from `setup`, silently skipping the ones that are rejected.
+fn run(setup: &GameSetup, commands: &[(SeatId, Command)]) -> Result<(), Failure> {
+    let mut s = catch(|| State::new(setup.players.clone(), setup.config.clone(), setup.seed))?;
+    s.check_invariants().map_err(Failure::Invariant)?;
+    check_perspectives(&s)?;
+    for (actor, c) in commands {
+        step(&mut s, *actor, c.clone())?;
+    }
+    Ok(())
+}
+
+/// Removes commands for as long as `fails` keeps failing, so only the commands needed to show the problem remain.
+fn shrink(commands: &[(SeatId, Command)], fails: impl Fn(&[(SeatId, Command)]) -> bool) -> Vec<(SeatId, Command)> {
+    let mut commands = commands.to_vec();
+    let mut chunk = commands.len().max(1);
+    while chunk > 0 {
+        let mut start = 0;
+        while start < commands.len() {
+            let mut candidate = commands.clone();
+            candidate.drain(start..cmp::min(start + chunk, commands.len()));
+            if fails(&candidate) {
+                commands = candidate;
+            } else {
+                start += chunk;
+            }
+        }
+        chunk /= 2;
+    }
+    commands
+}
+
+/// Picks a random kind of command first and then one of that kind.
+/// Victory announcements end the game right away, so they only come up now and then.
+fn random_move(s: &State, rng: &mut impl Rng) -> Option<(SeatId, Command)> {
+    let mut moves: Vec<Vec<(SeatId, Command)>> = Vec::new();
+    let mut announcements = Vec::new();
+    for p in s.game.p.ids() {
+        for c in s.legal_commands(p) {
+            if let Command::AnnounceVictory { .. } = c {
+                announcements.push((p, c));
+                continue;
+            }
+            match moves.iter_mut().find(|m| std::mem::discriminant(&m[0].1) == std::mem::discriminant(&c)) {
+                Some(m) => m.push((p, c)),
+                None => moves.push(vec![(p, c)]),
+            }
+        }
+    }
+    // now and then somebody leaves
+    if !moves.is_empty() && rng.gen_ratio(1, 300) {
+        let playing: Vec<_> = s.game.p.ids().filter(|&p| !s.has_resigned(p)).collect();
+        return playing.choose(rng).map(|&p| (p, Command::Resign));
+    }
+    if moves.is_empty() || rng.gen_ratio(1, 40) {
+        if let Some(m) = announcements.choose(rng) {
+            return Some(m.clone());
+        }
+    }
+    moves.choose(rng).and_then(|m| m.choose(rng)).cloned()
+}
+
+/// Plays one random game. On failure, returns the shrunk command sequence along with what went wrong.
+fn fuzz_game(setup: &GameSetup, move_seed: u64) -> Result<(), (Vec<(SeatId, Command)>, Failure)> {
+    let mut rng = StdRng::seed_from_u64(move_seed);
+    let mut s = State::new(setup.players.clone(), setup.config.clone(), setup.seed);
+    let mut commands = Vec::new();
+    for _ in 0..MOVE_CAP {
+        let (actor, c) = match catch(|| random_move(&s, &mut rng)) {
+            Ok(Some(m)) => m,
+            Ok(None) => break,
+            Err(e) => return Err((commands, e)),
+        };
+        commands.push((actor, c.clone()));
+        match step(&mut s, actor, c.clone()) {
+            Ok(true) => (),
+            Ok(false) => return Err((commands, Failure::Panic(format!("legal command {:?} by {:?} was rejected", c, actor)))),
+            Err(e) => {
+                let shrunk = shrink(&commands, |cmds| run(setup, cmds).is_err());
+                return Err((shrunk, e));
+            }
+        }
+    }
+    if commands.len() < MOVE_CAP && !matches!(s.turn, TurnState::GameOver { .. }) {
+        return Err((commands, Failure::Panic(format!("nobody can do anything in {:?}", s.turn))));
+    }
+    Ok(())
+}
+
+fn random_games(games: u64) {
+    for n in 3..=GameConfig::default().jobs.len() {
+        for i in 0..games {
+            // some games are cut short, so that the ends of those get tested as well
+            let round_limit = match i % 3 {
+                2 => RoundLimit { rounds: Some(6), rounds_after_stack: Some(1), tiebreak: i % 2 == 0 },
+                _ => RoundLimit::default(),
+            };
+            let config = GameConfig { sealed_answers: i % 2 == 1, round_limit, ..GameConfig::default() };
+            let setup = GameSetup { players: seats(n), config, seed: i };
+            if let Err((commands, failure)) = fuzz_game(&setup, 1000 + i) {
+                panic!("{:?} failed with {:?}\nafter {:#?}", setup, failure, commands);
+            }
+        }
+    }
+}
+
+#[test]
+fn random_games_smoke() {
+    random_games(SMOKE_GAMES);
+}
+
+#[test]
+#[ignore]
+fn random_games_full() {
+    random_games(GAMES_PER_PLAYER_COUNT);
+}
+
+#[test]
+fn shrink_finds_minimal_sequence() {
+    let commands: Vec<_> = (0..10).map(|i| (SeatId(i), Command::InitiateAttack { player: SeatId(i) })).collect();
+    let needed = [commands[2].clone(), commands[7].clone()];
+    let shrunk = shrink(&commands, |cmds| needed.iter().all(|c| cmds.contains(c)));
+    assert_eq!(shrunk, needed);
+}
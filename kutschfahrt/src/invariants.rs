@@ -0,0 +1,134 @@
+use super::*;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    #[error("There are {found} copies of {item:?} in the game instead of {expected}")]
+    ItemCount { item: Item, expected: usize, found: usize },
+    #[error("There are {found} copies of {job:?} in the game instead of {expected}")]
+    JobCount { job: Job, expected: usize, found: usize },
+    #[error("{0:?} has no items")]
+    NoItems(SeatId),
+    #[error("{0:?} has more items than allowed")]
+    InventoryLimit(SeatId),
+    #[error("The turn refers to {0:?}, who is not playing")]
+    UnseatedPlayer(SeatId),
+}
+
+impl GameState {
+    fn item_count(&self, item: Item) -> usize {
+        let held: usize = self.p.states().map(|p| p.items.count(item)).sum();
+        held + self.item_stack.iter().filter(|&&i| i == item).count()
+    }
+    fn job_count(&self, job: Job) -> usize {
+        let held = self.p.states().filter(|p| p.job == job).count();
+        held + self.job_stack.iter().filter(|&&j| j == job).count()
+    }
+}
+
+fn turn_players(turn: &TurnState) -> Vec<SeatId> {
+    match turn {
+        &TurnState::WaitingForQuickblink(p) | &TurnState::WaitingForEndTurn(p) => vec![p],
+        &TurnState::DoingClairvoyant { clairvoyant, next } => vec![clairvoyant, next],
+        &TurnState::UnsuccessfulDiplomat { diplomat, target } => vec![diplomat, target],
+        TurnState::GameOver { .. } => Vec::new(),
+        &TurnState::TradePending { offerer, target, .. } => vec![offerer, target],
+        TurnState::ResolvingTradeTrigger { giver, receiver, trigger, next_state } => {
+            let mut ret = vec![*giver, *receiver];
+            if let TradeTriggerState::Sextant { item_selections, .. } = trigger {
+                ret.extend(item_selections.keys().copied());
+            }
+            ret.extend(followup_players(next_state));
+            ret
+        }
+        TurnState::Attacking { attacker, defender, state } => {
+            let mut ret = vec![*attacker, *defender];
+            match state {
+                AttackState::WaitingForPriest { passed } => ret.extend(passed.iter().copied()),
+                &AttackState::PayingPriest { priest } => ret.push(priest),
+                AttackState::DeclaringSupport(votes) | AttackState::WaitingForHypnotizer(votes) => ret.extend(votes.keys().copied()),
+                AttackState::ItemsOrJobs { votes, passed, buffs } => {
+                    ret.extend(votes.keys().copied());
+                    ret.extend(passed.iter().copied());
+                    ret.extend(buffs.iter().map(|b| b.user));
+                }
+                AttackState::Resolving { .. } | AttackState::FinishResolving { .. } => (),
+            }
+            ret
+        }
+        TurnState::DonatingItem { donor, followup } => {
+            let mut ret = vec![*donor];
+            ret.extend(followup_players(followup));
+            ret
+        }
+    }
+}
+fn followup_players(followup: &FollowupState) -> Vec<SeatId> {
+    match followup {
+        FollowupState::State(turn) => turn_players(turn),
+        FollowupState::TradeTriggers { giver, receiver, next_state, .. } => {
+            let mut ret = vec![*giver, *receiver];
+            ret.extend(turn_players(next_state));
+            ret
+        }
+    }
+}
+
+impl State {
+    /// Checks the rules that hold in every position of a game dealt by `State::new`:
+    /// all items and jobs of the config are in play as often as it says, everybody holds at least one item
+    /// and no more than they may keep, and the turn only involves seated players.
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        self.check_table()?;
+        let config = &self.game.config;
+        for item in Item::all() {
+            let (expected, found) = (config.copies(item), self.game.item_count(item));
+            if found != expected {
+                return Err(InvariantViolation::ItemCount { item, expected, found });
+            }
+        }
+        for job in Job::all() {
+            let (expected, found) = (config.jobs.contains(&job) as usize, self.game.job_count(job));
+            if found != expected {
+                return Err(InvariantViolation::JobCount { job, expected, found });
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `check_invariants`, but only demands that no items or jobs appeared or vanished since `before`.
+    /// This also works for hand-made positions that don't use the full set of items.
+    pub(crate) fn check_transition(&self, before: &State) -> Result<(), InvariantViolation> {
+        self.check_table()?;
+        for item in Item::all() {
+            let (expected, found) = (before.game.item_count(item), self.game.item_count(item));
+            if found != expected {
+                return Err(InvariantViolation::ItemCount { item, expected, found });
+            }
+        }
+        for job in Job::all() {
+            let (expected, found) = (before.game.job_count(job), self.game.job_count(job));
+            if found != expected {
+                return Err(InvariantViolation::JobCount { job, expected, found });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_table(&self) -> Result<(), InvariantViolation> {
+        let players = &self.game.p;
+        let limit = self.game.inventory_limit();
+        for (player, state) in players.iter() {
+            let count = state.items.len();
+            if count == 0 {
+                return Err(InvariantViolation::NoItems(player));
+            }
+            if count > limit && !matches!(self.turn, TurnState::DonatingItem { donor, .. } if donor == player) {
+                return Err(InvariantViolation::InventoryLimit(player));
+            }
+        }
+        match turn_players(&self.turn).into_iter().find(|&p| !players.contains(p)) {
+            Some(p) => Err(InvariantViolation::UnseatedPlayer(p)),
+            None => Ok(()),
+        }
+    }
+}
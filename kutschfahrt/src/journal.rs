@@ -0,0 +1,48 @@
+use super::*;
+
+impl State {
+    /// What the current position shows a single player and nobody else
+    fn reveal(&self) -> Option<(SeatId, Reveal)> {
+        let s = &self.game;
+        match self.turn {
+            TurnState::DoingClairvoyant { clairvoyant, .. } => Some((clairvoyant, Reveal::ItemStack { items: s.item_stack.clone() })),
+            TurnState::UnsuccessfulDiplomat { diplomat, target } =>
+                Some((diplomat, Reveal::Diplomat { player: target, items: s.p.player(target).items.to_vec() })),
+            TurnState::ResolvingTradeTrigger { giver, receiver, ref trigger, .. } => match *trigger {
+                TradeTriggerState::Priviledge => Some((giver, Reveal::Priviledge { player: receiver, items: s.p.player(receiver).items.to_vec() })),
+                // three player games only show something once a card was picked
+                TradeTriggerState::Monocle { three_player_faction_index } => s.p.player(receiver).faction_by_index(three_player_faction_index)
+                    .map(|faction| (giver, Reveal::Monocle { player: receiver, faction, three_player_faction_index })),
+                _ => None,
+            },
+            TurnState::Attacking { attacker, defender, state: AttackState::FinishResolving { winner, steal_items, three_player_faction_index } } => {
+                let (winner, loser) = match winner {
+                    AttackWinner::Attacker => (attacker, defender),
+                    AttackWinner::Defender => (defender, attacker),
+                };
+                let victim = s.p.player(loser);
+                if steal_items {
+                    Some((winner, Reveal::AttackItems { player: loser, items: victim.items.to_vec() }))
+                } else {
+                    victim.faction_by_index(three_player_faction_index)
+                        .map(|faction| (winner, Reveal::Credentials { player: loser, faction, three_player_faction_index, job: victim.job }))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Writes down whatever the position shows a single player. Every position that shows something
+    /// is left by the next command, so this is called once after each command.
+    pub(crate) fn record_reveal(&mut self) {
+        if let Some((p, reveal)) = self.reveal() {
+            let log_position = self.game.action_log.len() as u64;
+            self.game.journal.push((p, JournalEntry { turn: self.game.turn_index, log_position, reveal }));
+        }
+    }
+
+    /// Everything `p` got to see that the others didn't, oldest first
+    pub fn journal(&self, p: SeatId) -> Vec<JournalEntry> {
+        self.game.journal.iter().filter(|&&(q, _)| q == p).map(|(_, e)| e.clone()).collect()
+    }
+}
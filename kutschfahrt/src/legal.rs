@@ -0,0 +1,328 @@
+use super::*;
+
+fn unique<T: PartialEq + Copy>(xs: &[T]) -> Vec<T> {
+    let mut ret = Vec::new();
+    for &x in xs {
+        if !ret.contains(&x) {
+            ret.push(x);
+        }
+    }
+    ret
+}
+
+impl State {
+    /// Everybody who can act right now, in seating order.
+    ///
+    /// These are exactly the players `legal_commands` has something for. Most of the time that is
+    /// a single player, but a few things (the priest, items and jobs in an attack, the sextant)
+    /// wait for several players at once. Players who answered in secret are done already.
+    pub fn pending_actors(&self) -> Vec<SeatId> {
+        let s = &self.game;
+        let mut actors = match &self.turn {
+            TurnState::GameOver { .. } => Vec::new(),
+            &TurnState::UnsuccessfulDiplomat { diplomat: p, .. }
+            | &TurnState::WaitingForQuickblink(p)
+            | &TurnState::WaitingForEndTurn(p)
+            | &TurnState::TradePending { target: p, .. }
+            | &TurnState::DonatingItem { donor: p, .. }
+            | &TurnState::DoingClairvoyant { clairvoyant: p, .. } => vec![p],
+            &TurnState::Attacking { attacker, defender, ref state } => match state {
+                AttackState::WaitingForPriest { passed } | AttackState::ItemsOrJobs { passed, .. } => s.p.ids().filter(|p| !passed.contains(p)).collect(),
+                AttackState::PayingPriest { .. } | AttackState::WaitingForHypnotizer(_) => vec![attacker],
+                AttackState::DeclaringSupport(votes) => s.p.attack_supporters(attacker, defender).nth(votes.len()).into_iter().collect(),
+                &AttackState::Resolving { winner } | &AttackState::FinishResolving { winner, .. } => match winner {
+                    AttackWinner::Attacker => vec![attacker],
+                    AttackWinner::Defender => vec![defender],
+                },
+            },
+            &TurnState::ResolvingTradeTrigger { giver, ref trigger, .. } => match trigger {
+                TradeTriggerState::Sextant { item_selections, is_forward: Some(_) } => s.p.ids().filter(|p| !item_selections.contains_key(p)).collect(),
+                _ => vec![giver],
+            },
+        };
+        actors.retain(|&p| self.sealed_answer(p).is_none());
+        actors
+    }
+
+    /// The command `actor` has to give if there is nothing else they could do right now.
+    pub fn only_answer(&self, actor: SeatId) -> Option<Command> {
+        let mut cmds = self.legal_commands(actor);
+        match cmds.len() {
+            1 => cmds.pop(),
+            _ => None,
+        }
+    }
+
+    /// Lists every command that `apply_command` would accept from `actor` right now, apart from resigning.
+    ///
+    /// Victory teammates are listed in seating order (`apply_command` accepts them in any order),
+    /// and duplicate items or jobs only show up once.
+    pub fn legal_commands(&self, actor: SeatId) -> Vec<Command> {
+        let s = &self.game;
+        let mut cmds = Vec::new();
+        if !s.p.contains(actor) || self.sealed_answer(actor).is_some() {
+            return cmds;
+        }
+
+        let me = s.p.player(actor);
+        let my_items: Vec<_> = me.items.kinds().collect();
+        let others: Vec<_> = s.p.ids().filter(|&p| p != actor).collect();
+
+        match &self.turn {
+            TurnState::GameOver { .. } => (),
+            &TurnState::UnsuccessfulDiplomat { diplomat, .. } => {
+                if actor == diplomat {
+                    cmds.push(Command::DoneLookingAtThings);
+                }
+            }
+            &TurnState::WaitingForQuickblink(p) | &TurnState::WaitingForEndTurn(p) => {
+                if actor != p {
+                    return cmds;
+                }
+
+                cmds.push(Command::Pass);
+                if me.can_use_job(Job::Clairvoyant) {
+                    cmds.push(Command::UseClairvoyant);
+                }
+                if me.can_use_job(Job::Diplomat) {
+                    for &target in &others {
+                        let target_items = s.p.player(target).items;
+                        for item in Item::all() {
+                            let resolved = resolve_diplomat_item(&target_items, item, s.item_stack.is_empty());
+                            for &return_item in &my_items {
+                                // an unsuccessful request never gets to the actual trade
+                                if resolved.is_none_or(|r| !is_bag_swap(&s.item_stack, r, return_item)) {
+                                    cmds.push(Command::UseDiplomat { target, item, return_item });
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if matches!(self.turn, TurnState::WaitingForEndTurn(_)) {
+                    return cmds;
+                }
+
+                for mask in 0..(1usize << others.len()) {
+                    let teammates = others.iter().enumerate().filter(|&(i, _)| mask & (1 << i) != 0).map(|(_, &t)| t).collect();
+                    let flavor = VictoryFlavor::Normal { teammates };
+                    if announcement_winner(s, actor, &flavor).is_ok() {
+                        cmds.push(Command::AnnounceVictory { flavor });
+                    }
+                }
+                if announcement_winner(s, actor, &VictoryFlavor::Loge).is_ok() {
+                    cmds.push(Command::AnnounceVictory { flavor: VictoryFlavor::Loge });
+                }
+                for &target in &others {
+                    for &item in &my_items {
+                        cmds.push(Command::OfferTrade { target, item });
+                    }
+                }
+                for &player in &others {
+                    cmds.push(Command::InitiateAttack { player });
+                }
+            }
+            &TurnState::Attacking { attacker, defender, ref state } => match state {
+                AttackState::WaitingForPriest { passed } => {
+                    if !passed.contains(&actor) {
+                        cmds.push(Command::UsePriest { priest: false });
+                        if actor != attacker && me.can_use_job(Job::Priest) {
+                            cmds.push(Command::UsePriest { priest: true });
+                        }
+                    }
+                }
+                &AttackState::PayingPriest { .. } => {
+                    if actor == attacker {
+                        cmds.extend(my_items.iter().map(|&item| Command::PayPriest { item }));
+                    }
+                }
+                AttackState::DeclaringSupport(votes) => {
+                    if s.p.attack_supporters(attacker, defender).nth(votes.len()) == Some(actor) {
+                        for support in [AttackSupport::Attack, AttackSupport::Defend, AttackSupport::Abstain] {
+                            cmds.push(Command::DeclareSupport { support });
+                        }
+                    }
+                }
+                AttackState::WaitingForHypnotizer(_) => {
+                    if actor == attacker {
+                        cmds.push(Command::Hypnotize { target: None });
+                        if me.can_use_job(Job::Hypnotist) {
+                            cmds.extend(s.p.attack_supporters(attacker, defender).map(|t| Command::Hypnotize { target: Some(t) }));
+                        }
+                    }
+                }
+                AttackState::ItemsOrJobs { votes, passed, buffs } => {
+                    if passed.contains(&actor) {
+                        return cmds;
+                    }
+                    cmds.push(Command::ItemOrJob { buff: None, target: None });
+
+                    let role = if actor == attacker {
+                        AttackRole::Attacker
+                    } else if actor == defender {
+                        AttackRole::Defender
+                    } else {
+                        match votes.get(&actor) {
+                            Some(&vote) => AttackRole::AttackSupport(vote),
+                            None => return cmds,
+                        }
+                    };
+
+                    let job_buff = Some(BuffSource::Job(me.job)).filter(|_| me.can_use_job(me.job));
+                    for buff in job_buff.into_iter().chain(my_items.iter().map(|&i| BuffSource::Item(i))) {
+                        let Some(rule) = buff.rule() else { continue };
+                        if rule.score(role).is_none() {
+                            continue;
+                        }
+                        match rule.effect {
+                            BuffEffect::EndFight => cmds.push(Command::ItemOrJob { buff: Some(buff), target: None }),
+                            BuffEffect::DecideFight => {
+                                for target in [attacker, defender] {
+                                    cmds.push(Command::ItemOrJob { buff: Some(buff.clone()), target: Some(target) });
+                                }
+                            }
+                            BuffEffect::Score | BuffEffect::SendSupportersHome => {
+                                if buffs.iter().all(|b| b.source != buff) {
+                                    cmds.push(Command::ItemOrJob { buff: Some(buff), target: None });
+                                }
+                            }
+                        }
+                    }
+                }
+                &AttackState::Resolving { winner } => {
+                    let winner_player = match winner {
+                        AttackWinner::Attacker => attacker,
+                        AttackWinner::Defender => defender,
+                    };
+                    if actor == winner_player {
+                        cmds.push(Command::ClaimReward { steal_items: false });
+                        cmds.push(Command::ClaimReward { steal_items: true });
+                    }
+                }
+                &AttackState::FinishResolving { winner, steal_items, three_player_faction_index } => {
+                    let (winner_player, loser_player) = match winner {
+                        AttackWinner::Attacker => (attacker, defender),
+                        AttackWinner::Defender => (defender, attacker),
+                    };
+                    if actor != winner_player {
+                        return cmds;
+                    }
+                    let loser = s.p.player(loser_player);
+
+                    if steal_items {
+                        for item in loser.items.kinds() {
+                            if loser.items.len() == 1 {
+                                let mut give_backs = my_items.clone();
+                                give_backs.push(item);
+                                for give_back in unique(&give_backs) {
+                                    cmds.push(Command::StealItem { item, give_back: Some(give_back) });
+                                }
+                            } else {
+                                cmds.push(Command::StealItem { item, give_back: None });
+                            }
+                        }
+                    } else {
+                        cmds.push(Command::DoneLookingAtThings);
+                        if three_player_faction_index.is_none() && matches!(loser.faction, FactionKind::ThreePlayer(_)) {
+                            cmds.extend((0..3).map(|index| Command::ThreePlayerSelectFactionIndex { index }));
+                        }
+                    }
+                }
+            }
+            &TurnState::TradePending { target, item, .. } => {
+                if actor == target {
+                    for &item2 in &my_items {
+                        if !is_bag_swap(&s.item_stack, item, item2) {
+                            cmds.push(Command::AcceptTrade { item: item2 });
+                        }
+                    }
+                    if ![Item::BlackPearl, Item::BrokenMirror].contains(&item) {
+                        cmds.push(Command::RejectTrade);
+                    }
+                }
+            }
+            &TurnState::ResolvingTradeTrigger { giver, receiver, ref trigger, .. } => match trigger {
+                TradeTriggerState::Priviledge | TradeTriggerState::Monocle { .. } => {
+                    if actor == giver {
+                        cmds.push(Command::DoneLookingAtThings);
+                        if matches!(trigger, TradeTriggerState::Monocle { three_player_faction_index: None })
+                            && matches!(s.p.player(receiver).faction, FactionKind::ThreePlayer(_)) {
+                            cmds.extend((0..3).map(|index| Command::ThreePlayerSelectFactionIndex { index }));
+                        }
+                    }
+                }
+                TradeTriggerState::Coat => {
+                    if actor == giver {
+                        cmds.extend(unique(&s.job_stack).into_iter().map(|job| Command::PickNewJob { job }));
+                    }
+                }
+                TradeTriggerState::Sextant { is_forward: None, .. } => {
+                    if actor == giver {
+                        cmds.push(Command::SetSextantDirection { forward: true });
+                        cmds.push(Command::SetSextantDirection { forward: false });
+                    }
+                }
+                TradeTriggerState::Sextant { item_selections, is_forward: Some(_) } => {
+                    if !item_selections.contains_key(&actor) {
+                        cmds.extend(my_items.iter().map(|&item| Command::SelectSextantItem { item }));
+                    }
+                }
+            }
+            &TurnState::DonatingItem { donor, .. } => {
+                if actor == donor {
+                    let limit = s.inventory_limit();
+                    for &target in others.iter().filter(|&&t| s.p.player(t).items.len() < limit) {
+                        for &item in &my_items {
+                            cmds.push(Command::DonateItem { target, item });
+                        }
+                    }
+                }
+            }
+            &TurnState::DoingClairvoyant { clairvoyant, .. } => {
+                if actor == clairvoyant {
+                    let stack = &s.item_stack;
+                    match stack.len() {
+                        0 => cmds.push(Command::ClairvoyantSetItems { top_items: Vec::new() }),
+                        1 => cmds.push(Command::ClairvoyantSetItems { top_items: stack.clone() }),
+                        _ => {
+                            for i in 0..stack.len() {
+                                for j in 0..stack.len() {
+                                    let c = Command::ClairvoyantSetItems { top_items: vec![stack[i], stack[j]] };
+                                    if i != j && !cmds.contains(&c) {
+                                        cmds.push(c);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        cmds
+    }
+
+    /// How the current fight stands while items and jobs are being played, and what each buff
+    /// `actor` may still use would do to it. `None` outside of that phase or once `actor` has passed.
+    pub fn attack_outlook(&self, actor: SeatId) -> Option<AttackOutlook> {
+        let (votes, buffs) = match &self.turn {
+            TurnState::Attacking { state: AttackState::ItemsOrJobs { votes, passed, buffs }, .. } if !passed.contains(&actor) => (votes, buffs),
+            _ => return None,
+        };
+        let tally = AttackTally::new(votes, buffs);
+        let options = self.legal_commands(actor).into_iter().filter_map(|c| {
+            let (buff, target) = match &c {
+                Command::ItemOrJob { buff: Some(buff), target } => (buff.clone(), *target),
+                _ => return None,
+            };
+            let mut s = self.clone();
+            s.apply_command_unchecked(actor, c).expect("legal commands are accepted");
+            let outcome = match s.turn {
+                TurnState::Attacking { state: AttackState::ItemsOrJobs { votes, buffs, .. }, .. } => BuffOutcome::Tally(AttackTally::new(&votes, &buffs)),
+                TurnState::Attacking { state: AttackState::Resolving { winner }, .. } => BuffOutcome::Decided(winner),
+                _ => BuffOutcome::Ended,
+            };
+            Some(BuffOption { buff, target, outcome })
+        }).collect();
+        Some(AttackOutlook { tally, options })
+    }
+}
@@ -1,33 +1,213 @@
 #![allow(dead_code)]
 
-use std::cell::RefCell;
 use std::collections::{HashSet, HashMap};
 use std::{iter, cmp};
-use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
 
-use indexmap::IndexMap;
 use serde::{Serialize, Deserialize};
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
 
 use web_protocol::*;
 
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GameState {
     p: GameStatePlayers,
     item_stack: Vec<Item>,
     job_stack: Vec<Job>,
-    action_log: Vec<ActionLogEntry>,
+    #[serde(deserialize_with = "deserialize_action_log")]
+    action_log: Vec<LoggedAction>,
+    // games stored before rule variants existed are played by the regular rules.
+    // The config never changes during a game, so clones share it
+    #[serde(default)]
+    config: Arc<GameConfig>,
+    // games stored before the rng was part of the state just continue with a fresh one
+    #[serde(default = "GameRng::from_entropy")]
+    rng: GameRng,
+    /// why the game was won, once somebody announced victory
+    #[serde(default)]
+    victory: Option<VictoryEvaluation>,
+    /// jobs that players swapped for a new one, in the order it happened
+    #[serde(default)]
+    former_jobs: Vec<(SeatId, Job)>,
+    // games stored before turns were counted start counting with the next turn
+    #[serde(default)]
+    turn_index: TurnIndex,
+    /// whose turn `turn_index` counts
+    #[serde(default)]
+    turn_player: Option<SeatId>,
+    /// what each player got to see privately, see `State::journal`
+    #[serde(default)]
+    journal: Vec<(SeatId, JournalEntry)>,
+    /// answers given in secret that don't count yet, see `State::sealed_answer`
+    #[serde(default)]
+    sealed: Vec<(SeatId, Command)>,
+    /// who left the game, see `State::has_resigned`
+    #[serde(default)]
+    resigned: Vec<SeatId>,
+    /// the round after which the round limit ends the game, see `State::end_round`
+    #[serde(default)]
+    last_round: Option<u32>,
 }
-#[derive(Debug, Serialize, Deserialize)]
+/// Every player needs a job of their own, so there can't be more players than jobs.
+const MAX_PLAYERS: usize = Job::Clairvoyant as usize + 1;
+
+/// The players in seating order.
+///
+/// Bots clone whole game states all the time, so this is kept in fixed-size arrays
+/// that clone without allocating. Only the first `len` entries are in use.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "StoredPlayers", into = "StoredPlayers")]
 pub struct GameStatePlayers {
-    #[serde(with = "indexmap::serde_seq")]
-    players: IndexMap<Player, RefCell<PlayerState>>,
+    len: usize,
+    players: [(SeatId, PlayerState); MAX_PLAYERS],
+    /// who sits where, in any order
+    seats: Arc<[Seat]>,
 }
-#[derive(Debug, Serialize, Deserialize)]
+/// Games stored before there were seats don't know any names,
+/// so everyone goes by the name of the character they played.
+#[derive(Serialize, Deserialize)]
+struct StoredPlayers {
+    players: Vec<(SeatId, PlayerState)>,
+    #[serde(default)]
+    seats: Vec<Seat>,
+}
+impl TryFrom<StoredPlayers> for GameStatePlayers {
+    type Error = String;
+    fn try_from(stored: StoredPlayers) -> Result<GameStatePlayers, String> {
+        if stored.players.len() > MAX_PLAYERS {
+            return Err(format!("{} players, but there can't be more than {}", stored.players.len(), MAX_PLAYERS));
+        }
+        Ok(if stored.seats.is_empty() {
+            GameStatePlayers::with_legacy_seats(stored.players)
+        } else {
+            GameStatePlayers::new(stored.players, stored.seats)
+        })
+    }
+}
+impl From<GameStatePlayers> for StoredPlayers {
+    fn from(p: GameStatePlayers) -> StoredPlayers {
+        StoredPlayers { players: p.players[..p.len].to_vec(), seats: p.seats.to_vec() }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct State {
     game: GameState,
     pub turn: TurnState,
+    /// `None` for games that were not dealt by `State::new`
+    #[serde(default)]
+    setup: Option<Arc<GameSetup>>,
+    /// every command that was accepted since the game was dealt, in order
+    #[serde(default)]
+    history: Vec<(SeatId, Command)>,
+    /// whether the last entry of `history` revealed nothing and may be taken back
+    #[serde(default)]
+    last_command_undoable: bool,
+}
+
+/// Everything `State::new` needs to deal a game, so that it can be dealt again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSetup {
+    #[serde(deserialize_with = "deserialize_seats")]
+    pub players: Vec<Seat>,
+    #[serde(default)]
+    pub config: GameConfig,
+    pub seed: u64,
+}
+
+/// Games stored before log entries were wrapped in `LoggedAction` only know the entries themselves
+fn deserialize_action_log<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<LoggedAction>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StoredAction {
+        Logged(LoggedAction),
+        Legacy(ActionLogEntry),
+    }
+    let log = Vec::<StoredAction>::deserialize(deserializer)?;
+    Ok(log.into_iter().enumerate().map(|(i, a)| match a {
+        StoredAction::Logged(logged) => logged,
+        StoredAction::Legacy(entry) => LoggedAction { seq: i as u64, turn: TurnIndex::default(), actor: entry.actor(), timestamp: None, entry },
+    }).collect())
+}
+
+/// Games stored before there were seats list their characters instead
+fn deserialize_seats<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<Seat>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StoredSeat {
+        Seat(Seat),
+        Legacy(SeatId),
+    }
+    let seats = Vec::<StoredSeat>::deserialize(deserializer)?;
+    Ok(seats.into_iter().map(|s| match s {
+        StoredSeat::Seat(seat) => seat,
+        StoredSeat::Legacy(id) => Seat::legacy(id),
+    }).collect())
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("Command {index} could not be replayed: {error}")]
+pub struct ReplayError {
+    pub index: usize,
+    pub error: CommandError,
+}
+
+/// The source of all randomness in a game.
+///
+/// Serializes as its seed and stream position, so a stored game continues with exactly
+/// the same random choices it would have made without the round trip.
+#[derive(Debug, Clone)]
+pub struct GameRng(ChaCha8Rng);
+#[derive(Serialize, Deserialize)]
+struct GameRngState {
+    seed: [u8; 32],
+    stream: u64,
+    // the position is a u128, which not every JSON library can store
+    word_pos_low: u64,
+    word_pos_high: u64,
+}
+impl GameRng {
+    pub fn new(seed: u64) -> GameRng {
+        GameRng(ChaCha8Rng::seed_from_u64(seed))
+    }
+    fn from_entropy() -> GameRng {
+        GameRng(ChaCha8Rng::from_entropy())
+    }
+}
+impl PartialEq for GameRng {
+    fn eq(&self, other: &GameRng) -> bool {
+        (self.0.get_seed(), self.0.get_stream(), self.0.get_word_pos()) == (other.0.get_seed(), other.0.get_stream(), other.0.get_word_pos())
+    }
+}
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+impl Serialize for GameRng {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let word_pos = self.0.get_word_pos();
+        GameRngState { seed: self.0.get_seed(), stream: self.0.get_stream(), word_pos_low: word_pos as u64, word_pos_high: (word_pos >> 64) as u64 }.serialize(serializer)
+    }
+}
+impl<'de> Deserialize<'de> for GameRng {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let state = GameRngState::deserialize(deserializer)?;
+        let mut rng = ChaCha8Rng::from_seed(state.seed);
+        rng.set_stream(state.stream);
+        rng.set_word_pos(((state.word_pos_high as u128) << 64) | state.word_pos_low as u128);
+        Ok(GameRng(rng))
+    }
 }
 
 #[derive(thiserror::Error, Debug, PartialEq, Eq)]
@@ -64,6 +244,12 @@ pub enum CommandError {
     WrongNumberOfClairvoyantItems,
     #[error("Solo victory requires at least 3 (mixed) victory items (and coat of arms of the loge)")]
     InvalidLogeVictory,
+    #[error("Solo victories are not allowed in this game")]
+    LogeVictoryDisabled,
+    #[error("Your last command can't be taken back")]
+    CannotUndo,
+    #[error("You have left the game")]
+    Resigned,
 }
 
 impl From<JobUseError> for CommandError {
@@ -72,32 +258,205 @@ impl From<JobUseError> for CommandError {
     }
 }
 
+impl GameState {
+    fn inventory_limit(&self) -> usize {
+        self.config.inventory_limit(self.p.len())
+    }
+    fn log(&mut self, actor: SeatId, entry: ActionLogEntry) {
+        let seq = self.action_log.len() as u64;
+        self.action_log.push(LoggedAction { seq, turn: self.turn_index, actor, timestamp: None, entry });
+    }
+    /// Whether `p`'s turn would be the first of a new round
+    fn starts_round(&self, p: SeatId) -> bool {
+        self.turn_player != Some(p) && self.turn_player.is_none_or(|q| self.p.index_of(p) <= self.p.index_of(q))
+    }
+    /// Counts `p`'s turn, unless it is already under way
+    fn start_turn(&mut self, p: SeatId) {
+        if self.turn_player == Some(p) {
+            return;
+        }
+        if self.starts_round(p) {
+            self.turn_index.round += 1;
+        }
+        self.turn_index.turn += 1;
+        self.turn_player = Some(p);
+    }
+}
 impl GameStatePlayers {
-    fn next_player(&self, p: Player) -> Player {
-        let index = self.players.get_index_of(&p).expect("Invalid player");
-        let next_index = (index + 1) % self.players.len();
-        *self.players.get_index(next_index).unwrap().0
+    /// Only ever filled into the unused entries
+    const EMPTY: (SeatId, PlayerState) = (SeatId(u32::MAX), PlayerState {
+        faction: FactionKind::Normal(Faction::Order),
+        job: Job::Thug,
+        job_is_visible: false,
+        items: Inventory::new(),
+    });
+
+    fn new(players: Vec<(SeatId, PlayerState)>, seats: Vec<Seat>) -> GameStatePlayers {
+        assert!(players.len() <= MAX_PLAYERS, "too many players");
+        let mut p = GameStatePlayers { len: players.len(), players: [GameStatePlayers::EMPTY; MAX_PLAYERS], seats: seats.into() };
+        p.players[..players.len()].copy_from_slice(&players);
+        p
+    }
+    fn with_legacy_seats(players: Vec<(SeatId, PlayerState)>) -> GameStatePlayers {
+        let seats = players.iter().map(|&(id, _)| Seat::legacy(id)).collect();
+        GameStatePlayers::new(players, seats)
+    }
+    fn len(&self) -> usize {
+        self.len
+    }
+    /// Everybody in seating order
+    fn iter(&self) -> impl DoubleEndedIterator<Item=(SeatId, &PlayerState)> + Clone {
+        self.players[..self.len].iter().map(|(k, v)| (*k, v))
     }
-    fn player<'a>(&'a self, p: Player) -> impl Deref<Target=PlayerState> + 'a {
-        self.players.get(&p).unwrap().borrow()
+    fn ids(&self) -> impl DoubleEndedIterator<Item=SeatId> + Clone + '_ {
+        self.players[..self.len].iter().map(|&(k, _)| k)
     }
-    fn player_mut<'a>(&'a self, p: Player) -> impl DerefMut<Target=PlayerState> + 'a {
-        self.players.get(&p).unwrap().borrow_mut()
+    fn states(&self) -> impl Iterator<Item=&PlayerState> {
+        self.players[..self.len].iter().map(|(_, v)| v)
     }
-    fn player_pair_mut<'a>(&'a self, a: Player, b: Player) -> (impl DerefMut<Target=PlayerState> + 'a, impl DerefMut<Target=PlayerState> + 'a) {
-        (self.players.get(&a).unwrap().borrow_mut(), self.players.get(&b).unwrap().borrow_mut())
+    fn index_of(&self, p: SeatId) -> Option<usize> {
+        self.ids().position(|k| k == p)
     }
-    fn attack_supporters<'a>(&'a self, attacker: Player, defender: Player) -> impl Iterator<Item=Player> + 'a {
-        let keys = self.players.keys();
+    fn contains(&self, p: SeatId) -> bool {
+        self.index_of(p).is_some()
+    }
+    fn seat(&self, p: SeatId) -> &Seat {
+        self.seats.iter().find(|s| s.id == p).expect("Invalid player")
+    }
+    /// What everybody gets to see about the players
+    fn public(&self) -> Vec<PerspectivePlayer> {
+        self.iter().map(|(k, v)| {
+            let seat = self.seat(k);
+            PerspectivePlayer {
+                player: k,
+                name: seat.name.clone(),
+                character: seat.character,
+                job: if v.job_is_visible { Some(v.job) } else { None },
+                item_count: v.items.len(),
+                // only `GameState::public_players` knows
+                resigned: false,
+            }
+        }).collect()
+    }
+    fn next_player(&self, p: SeatId) -> SeatId {
+        let index = self.index_of(p).expect("Invalid player");
+        self.players[(index + 1) % self.len].0
+    }
+    fn player(&self, p: SeatId) -> &PlayerState {
+        &self.players[self.index_of(p).expect("Invalid player")].1
+    }
+    fn player_mut(&mut self, p: SeatId) -> &mut PlayerState {
+        let index = self.index_of(p).expect("Invalid player");
+        &mut self.players[index].1
+    }
+    /// Two different players at once, e.g. both sides of a trade
+    fn player_pair_mut(&mut self, a: SeatId, b: SeatId) -> (&mut PlayerState, &mut PlayerState) {
+        let (i, j) = (self.index_of(a).expect("Invalid player"), self.index_of(b).expect("Invalid player"));
+        assert_ne!(i, j, "a player can't be on both sides");
+        let (low, high) = self.players.split_at_mut(cmp::max(i, j));
+        if i < j {
+            (&mut low[i].1, &mut high[0].1)
+        } else {
+            (&mut high[0].1, &mut low[j].1)
+        }
+    }
+    fn attack_supporters(&self, attacker: SeatId, defender: SeatId) -> impl Iterator<Item=SeatId> + '_ {
+        let keys = self.ids();
         let keys_twice = keys.clone().chain(keys);
-        keys_twice.copied()
+        keys_twice
             .skip_while(move |&x| x != attacker)
-            .take(self.players.len())
+            .take(self.len)
             .filter(move |&x| x != attacker && x != defender)
     }
 }
 impl State {
-    pub fn apply_command(&mut self, actor: Player, c: Command) -> Result<(), CommandError> {
+    /// Applies `c` on behalf of `actor`. A rejected command leaves the state untouched.
+    pub fn apply_command(&mut self, actor: SeatId, c: Command) -> Result<(), CommandError> {
+        // the individual rules are free to bail out halfway through,
+        // so they work on a scratch copy that only replaces the real state once everything went through
+        if self.has_resigned(actor) {
+            return Err(CommandError::Resigned);
+        }
+        let mut next = self.clone();
+        match c {
+            Command::Resign => next.resign(actor)?,
+            _ => next.apply_or_seal(actor, c.clone())?,
+        }
+        // the game doesn't wait for players who left
+        next.answer_for_resigned();
+        #[cfg(debug_assertions)]
+        if let Err(e) = next.check_transition(self) {
+            panic!("{:?} by {:?} broke the rules: {}", c, actor, e);
+        }
+        next.last_command_undoable = !reveals_information(self, &next, &c);
+        next.history.push((actor, c));
+        *self = next;
+        Ok(())
+    }
+
+    /// Takes back `actor`'s last command, as long as nobody acted after them
+    /// and the command did not show anyone something they could not see before.
+    /// Players who resigned can't take anything back, resigning included.
+    pub fn undo(&mut self, actor: SeatId) -> Result<(), CommandError> {
+        match (&self.setup, self.history.last()) {
+            (Some(setup), Some(&(last_actor, _))) if last_actor == actor && self.last_command_undoable && !self.has_resigned(actor) => {
+                let commands = &self.history[..self.history.len() - 1];
+                let mut state = State::replay(setup, commands).expect("the history of a game always replays");
+                // replaying only knows what happened, not when
+                for (replayed, logged) in state.game.action_log.iter_mut().zip(&self.game.action_log) {
+                    replayed.timestamp = logged.timestamp;
+                }
+                *self = state;
+                Ok(())
+            }
+            _ => Err(CommandError::CannotUndo),
+        }
+    }
+
+    /// Deals the game described by `setup` and applies `commands` one after another.
+    ///
+    /// Since all randomness is drawn from the seeded rng, this rebuilds exactly the position
+    /// the original game was in after those commands.
+    pub fn replay(setup: &GameSetup, commands: &[(SeatId, Command)]) -> Result<State, ReplayError> {
+        let mut state = State::new(setup.players.clone(), setup.config.clone(), setup.seed);
+        for (index, (actor, c)) in commands.iter().enumerate() {
+            state.apply_command(*actor, c.clone()).map_err(|error| ReplayError { index, error })?;
+        }
+        Ok(state)
+    }
+
+    pub fn setup(&self) -> Option<&GameSetup> {
+        self.setup.as_deref()
+    }
+
+    pub fn history(&self) -> &[(SeatId, Command)] {
+        &self.history
+    }
+
+    /// Stamps `now` (in milliseconds since the Unix epoch) on every log entry that has no time yet,
+    /// i.e. on the ones logged by the commands since the last call.
+    pub fn stamp_log(&mut self, now: u64) {
+        for logged in self.game.action_log.iter_mut().rev().take_while(|l| l.timestamp.is_none()) {
+            logged.timestamp = Some(now);
+        }
+    }
+
+    pub fn turn_index(&self) -> TurnIndex {
+        self.game.turn_index
+    }
+
+    /// How announcing `flavor` would turn out for `actor` right now, without announcing anything.
+    ///
+    /// This looks at everybody's hidden factions and items, so players only get to see it
+    /// for the announcement that ended the game, as part of their perspective.
+    pub fn evaluate_victory(&self, actor: SeatId, flavor: &VictoryFlavor) -> Result<VictoryEvaluation, CommandError> {
+        if !self.game.p.contains(actor) {
+            return Err(CommandError::InvalidTargetPlayer);
+        }
+        evaluate_victory(&self.game, actor, flavor)
+    }
+
+    fn apply_command_unchecked(&mut self, actor: SeatId, c: Command) -> Result<(), CommandError> {
         let s = &mut self.game;
         self.turn = match self.turn.clone() {
             TurnState::GameOver { .. } => return Err(CommandError::GameOver),
@@ -131,29 +490,28 @@ impl State {
                 match c {
                     Command::Pass => {
                         if !is_end_phase {
-                            s.action_log.push(ActionLogEntry::Pass { actor });
+                            s.log(actor, ActionLogEntry::Pass { actor });
                         }
                         // skip end phase (next_turn_player) for this because it would be redundant
                         TurnState::WaitingForQuickblink(s.p.next_player(p))
                     }
                     Command::UseClairvoyant => {
                         s.p.player_mut(p).use_job(Job::Clairvoyant)?;
-                        s.action_log.push(ActionLogEntry::UseClairvoyant { actor });
+                        s.log(actor, ActionLogEntry::UseClairvoyant { actor });
                         TurnState::DoingClairvoyant { clairvoyant: p, next: next_turn_player }
                     }
                     Command::UseDiplomat { target, item, return_item } => {
-                        if !s.p.players.contains_key(&target) || actor == target {
+                        if !s.p.contains(target) || actor == target {
                             return Err(CommandError::InvalidTargetPlayer);
                         }
 
                         s.p.player_mut(p).use_job(Job::Diplomat)?;
-                        let (actor_items, target_items) = s.p.player_pair_mut(actor, target);
-                        let _return_item_index = actor_items.items.iter().position(|&x| x == return_item).ok_or(CommandError::InvalidItemError(return_item))?;
-                        let stack_empty = s.item_stack.is_empty();
-                        let resolved_target_item = target_items.items.iter().copied().find(|&x| x == item || (stack_empty && (item == Item::Goblet && x == Item::BagGoblet) || (item == Item::Key && x == Item::BagKey)));
-                        drop((actor_items, target_items));
+                        if !s.p.player(actor).items.contains(&return_item) {
+                            return Err(CommandError::InvalidItemError(return_item));
+                        }
+                        let resolved_target_item = resolve_diplomat_item(&s.p.player(target).items, item, s.item_stack.is_empty());
 
-                        s.action_log.push(ActionLogEntry::UseDiplomat { actor, target, item, success: resolved_target_item.is_some() });
+                        s.log(actor, ActionLogEntry::UseDiplomat { actor, target, item, success: resolved_target_item.is_some() });
 
                         match resolved_target_item {
                             Some(target_item) => {
@@ -171,81 +529,33 @@ impl State {
                     // but it's needed to give you an opportunity for end-of-turn clairvoyant and diplomat
 
                     Command::AnnounceVictory { flavor } => {
-                        let actor_player = s.p.player(actor);
-                        s.action_log.push(ActionLogEntry::AnnounceVictory { actor });
-                        if actor_player.items.contains(&Item::BlackPearl) {
-                            return Err(CommandError::BlackPearl);
-                        }
-                        let faction = actor_player.effective_faction();
-                        let num_faction_members = s.p.players.values().filter(|x| x.borrow().effective_faction() == faction).count();
-                        let is_minority_faction = num_faction_members * 2 < s.p.players.len();
-                        let required_items: &[_] = match (&flavor, faction) {
-                            (VictoryFlavor::Normal { .. }, Faction::Order) => &[Item::Key, Item::BagKey],
-                            (VictoryFlavor::Normal { .. }, Faction::Brotherhood) => &[Item::Goblet, Item::BagGoblet],
-                            (VictoryFlavor::Loge, _) => &[Item::Key, Item::BagKey, Item::Goblet, Item::BagGoblet],
-                        };
-                        let mut required_items = required_items.to_vec();
-
-                        if !s.item_stack.is_empty() {
-                            required_items.retain(|&x| x != Item::BagKey && x != Item::BagGoblet);
-                        }
-
-                        let (loge, mut teammates) = match flavor {
-                            VictoryFlavor::Normal { teammates } => (false, teammates),
-                            VictoryFlavor::Loge => (true, Vec::new()),
-                        };
-                        teammates.push(actor);
-                        let mut victory = true;
-                        let mut total_victory_items = 0;
-                        for t in teammates {
-                            let ts = s.p.player(t);
-                            let victory_items = ts.items.iter().copied().filter(|i| required_items.contains(i)).count();
-                            if victory_items == 0 || ts.effective_faction() != faction {
-                                victory = false;
-                                break;
-                            }
-                            total_victory_items += victory_items;
-                        }
-                        let needed_victory_items = if is_minority_faction { 2 } else { 3 };
-                        victory &= total_victory_items >= needed_victory_items;
-
-                        let winner = if loge {
-                            if !victory || !s.p.player(actor).items.contains(&Item::CoatOfArmorOfTheLoge) {
-                                return Err(CommandError::InvalidLogeVictory);
-                            }
-                            WinningFaction::Traitor(actor)
-                        } else if victory {
-                            WinningFaction::Normal(faction)
-                        } else {
-                            WinningFaction::Normal(match faction {
-                                Faction::Order => Faction::Brotherhood,
-                                Faction::Brotherhood => Faction::Order,
-                            })
-                        };
+                        let winner = announcement_winner(s, actor, &flavor)?;
+                        s.victory = evaluate_victory(s, actor, &flavor).ok();
+                        s.log(actor, ActionLogEntry::AnnounceVictory { actor });
                         TurnState::GameOver { winner }
                     }
                     Command::OfferTrade { target, item } => {
-                        if actor == target {
+                        if !s.p.contains(target) || actor == target {
                             return Err(CommandError::InvalidTargetPlayer);
                         }
-                        if s.p.player(actor).items.iter().all(|&i| i != item) {
+                        if !s.p.player(actor).items.contains(&item) {
                             return Err(CommandError::InvalidItemError(item));
                         }
                         TurnState::TradePending { offerer: actor, target, item }
                     }
                     Command::InitiateAttack { player } => {
-                        if !s.p.players.contains_key(&player) || actor == player {
+                        if !s.p.contains(player) || actor == player {
                             return Err(CommandError::InvalidTargetPlayer);
                         }
 
-                        s.action_log.push(ActionLogEntry::Attack { attacker: actor, target: player });
+                        s.log(actor, ActionLogEntry::Attack { attacker: actor, target: player });
 
                         // if the priest card is already publicly visible, skip the priest phase entirely
-                        let priest_is_used = s.p.players.values().any(|p| p.borrow().job_is_visible && p.borrow().job == Job::Priest);
+                        let priest_is_used = s.p.states().any(|p| p.job_is_visible && p.job == Job::Priest);
                         // in addition, it is safe to auto-pass everyone who already has an open job
-                        let passed: HashSet<_> = s.p.players.iter().filter(|(_, v)| v.borrow().job_is_visible).map(|(&k, _)| k).collect();
+                        let passed: HashSet<_> = s.p.iter().filter(|(_, v)| v.job_is_visible).map(|(k, _)| k).collect();
 
-                        let state = if priest_is_used || passed.len() == s.p.players.len() {
+                        let state = if priest_is_used || passed.len() == s.p.len() {
                             AttackState::DeclaringSupport(HashMap::new())
                         } else {
                             AttackState::WaitingForPriest { passed }
@@ -263,10 +573,10 @@ impl State {
             TurnState::Attacking { attacker, defender, state } => match state {
                 AttackState::WaitingForPriest { mut passed } => {
                     match c {
+                        Command::UsePriest { .. } if passed.contains(&actor) => return Err(CommandError::YouHaveAlreadyPassed),
+                        // the attacker can't call off their own attack
+                        Command::UsePriest { priest: true } if actor == attacker => return Err(CommandError::InvalidCommandInThisContext),
                         Command::UsePriest { priest: true } => {
-                            if passed.contains(&actor) {
-                                return Err(CommandError::YouHaveAlreadyPassed);
-                            }
 
                             s.p.player_mut(actor).use_job(Job::Priest)?;
 
@@ -280,7 +590,7 @@ impl State {
                         },
                         Command::UsePriest { priest: false } => {
                             passed.insert(actor);
-                            let state = if passed.len() == s.p.players.len() {
+                            let state = if passed.len() == s.p.len() {
                                 AttackState::DeclaringSupport(HashMap::new())
                             } else {
                                 AttackState::WaitingForPriest { passed }
@@ -293,12 +603,13 @@ impl State {
                 AttackState::PayingPriest { priest } => {
                     match c {
                         Command::PayPriest { item } if actor == attacker => {
-                            let mut attacker_state = s.p.player_mut(attacker);
-                            let mut priest_state = s.p.player_mut(priest);
-                            let index = attacker_state.items.iter().position(|&i| i == item).ok_or(CommandError::InvalidItemError(item))?;
-                            attacker_state.items.remove(index);
+                            let limit = s.inventory_limit();
+                            let (attacker_state, priest_state) = s.p.player_pair_mut(attacker, priest);
+                            if !attacker_state.items.remove(item) {
+                                return Err(CommandError::InvalidItemError(item));
+                            }
                             priest_state.items.push(item);
-                            if priest_state.items.len() > inventory_limit(s.p.players.len()) {
+                            if priest_state.items.len() > limit {
                                 TurnState::DonatingItem { donor: priest, followup: FollowupState::end_phase(attacker) }
                             } else {
                                 TurnState::WaitingForEndTurn(attacker)
@@ -316,7 +627,7 @@ impl State {
                     match c {
                         Command::DeclareSupport { support } => {
                             votes.insert(actor, support);
-                            if votes.len() == s.p.players.len() - 2 {
+                            if votes.len() == s.p.len() - 2 {
                                 TurnState::Attacking { attacker, defender, state: AttackState::WaitingForHypnotizer(votes) }
                             } else {
                                 TurnState::Attacking { attacker, defender, state: AttackState::DeclaringSupport(votes) }
@@ -332,6 +643,9 @@ impl State {
                     match c {
                         Command::Hypnotize { target } => {
                             if let Some(target) = target {
+                                if !votes.contains_key(&target) {
+                                    return Err(CommandError::InvalidTargetPlayer);
+                                }
                                 s.p.player_mut(actor).use_job(Job::Hypnotist)?;
                                 votes.insert(target, AttackSupport::Abstain);
                             }
@@ -346,19 +660,19 @@ impl State {
                     }
 
                     match c {
-                        // TODO: We might wanna warn the player if he specifies a target for a buff that doesn't need a target
-                        Command::ItemOrJob { buff: None, target: _ } => {
+                        Command::ItemOrJob { buff, target: Some(_) } if buff.as_ref().and_then(BuffSource::rule).map(|r| r.target) != Some(BuffTarget::Fighter) => return Err(CommandError::InvalidTargetPlayer),
+                        Command::ItemOrJob { buff: None, target: None } => {
                             passed.insert(actor);
-                            let required_passes = s.p.players.len();
+                            let required_passes = s.p.len();
                             if passed.len() == required_passes {
-                                let score: BuffScore = buffs.iter().map(|b| b.raw_score)
-                                    .chain(votes.values().map(|v| v.vote_value())).sum();
-
-                                if score == 0 {
+                                if let Some(winner) = AttackTally::new(&votes, &buffs).winner() {
+                                    TurnState::Attacking { attacker, defender, state: AttackState::Resolving { winner } }
+                                } else {
                                     if let Some(drawn_item) = s.item_stack.pop() {
-                                        let mut player_state = s.p.player_mut(attacker);
+                                        let limit = s.inventory_limit();
+                                        let player_state = s.p.player_mut(attacker);
                                         player_state.items.push(drawn_item);
-                                        if player_state.items.len() > inventory_limit(s.p.players.len()) {
+                                        if player_state.items.len() > limit {
                                             TurnState::DonatingItem { donor: attacker, followup: FollowupState::end_phase(attacker) }
                                         } else {
                                             TurnState::WaitingForEndTurn(attacker)
@@ -366,13 +680,6 @@ impl State {
                                     } else {
                                         TurnState::WaitingForEndTurn(attacker)
                                     }
-                                } else {
-                                    let winner = if score > 0 {
-                                        AttackWinner::Attacker
-                                    } else {
-                                        AttackWinner::Defender
-                                    };
-                                    TurnState::Attacking { attacker, defender, state: AttackState::Resolving { winner } }
                                 }
                             } else {
                                 TurnState::Attacking { attacker, defender, state: AttackState::ItemsOrJobs { votes, passed, buffs } }
@@ -388,16 +695,20 @@ impl State {
                                 AttackRole::AttackSupport(votes[&actor])
                             };
 
-                            // additional poison mixer validation:
-                            if let BuffSource::Job(Job::PoisonMixer) = &buff {
-                                if let AttackRole::Attacker | AttackRole::Defender = role {
-                                    return Err(CommandError::CantPoisonMixYourself);
-                                }
-                                match target {
-                                    Some(x) if [attacker, defender].contains(&x) => (),
-                                    _ => return Err(CommandError::InvalidCommandInThisContext),
+                            let rule = buff.rule();
+                            let winner = match rule.map(|r| r.target) {
+                                Some(BuffTarget::Fighter) => {
+                                    if let AttackRole::Attacker | AttackRole::Defender = role {
+                                        return Err(CommandError::CantPoisonMixYourself);
+                                    }
+                                    match target {
+                                        Some(x) if x == attacker => Some(AttackWinner::Attacker),
+                                        Some(x) if x == defender => Some(AttackWinner::Defender),
+                                        _ => return Err(CommandError::InvalidCommandInThisContext),
+                                    }
                                 }
-                            }
+                                _ => None,
+                            };
 
                             // Check if using this buff is vaild for the player
                             match buff {
@@ -409,31 +720,23 @@ impl State {
                                 }
                             }
 
-                            // resolve triggers
-                            match buff {
-                                // triggers that end the fight:
-                                BuffSource::Job(Job::Doctor) => {
-                                    TurnState::WaitingForEndTurn(attacker)
-                                }
-                                BuffSource::Job(Job::PoisonMixer) => {
-                                    let winner = match target {
-                                        Some(x) if x == attacker => AttackWinner::Attacker,
-                                        Some(x) if x == defender => AttackWinner::Defender,
-                                        _ => unreachable!(),
-                                    };
+                            // Check if using this buff is vaild for the player's role
+                            let rule = rule.ok_or(CommandError::InvalidCommandInThisContext)?;
+                            let raw_score = rule.score(role).ok_or(CommandError::InvalidCommandInThisContext)?;
+
+                            match (rule.effect, winner) {
+                                (BuffEffect::EndFight, _) => TurnState::WaitingForEndTurn(attacker),
+                                (BuffEffect::DecideFight, Some(winner)) => {
                                     TurnState::Attacking { attacker, defender, state: AttackState::Resolving { winner } }
                                 }
-                                buff => {
-                                    // Check if using this buff is vaild for the player's role
-                                    let raw_score = buff.raw_score(role).ok_or(CommandError::InvalidCommandInThisContext)?;
-
+                                (BuffEffect::DecideFight, None) => unreachable!("deciding buffs always have a fighter as target"),
+                                (effect @ (BuffEffect::Score | BuffEffect::SendSupportersHome), _) => {
                                     // check if duplicate buff is being used
                                     if buffs.iter().any(|b| b.source == buff) {
                                         return Err(CommandError::DuplicateBuffUsage);
                                     }
 
-                                    // triggers that don't end the fight:
-                                    if let BuffSource::Job(Job::Duelist) = &buff {
+                                    if effect == BuffEffect::SendSupportersHome {
                                         for vote in votes.values_mut() {
                                             *vote = AttackSupport::Abstain;
                                         }
@@ -478,8 +781,8 @@ impl State {
                     match c {
                         Command::DoneLookingAtThings if !steal_items => TurnState::WaitingForEndTurn(attacker),
                         Command::StealItem { item, give_back } if steal_items => {
-                            let mut winner_state = s.p.player_mut(winner_player);
-                            let mut loser_state = s.p.player_mut(loser_player);
+                            let limit = s.inventory_limit();
+                            let (winner_state, loser_state) = s.p.player_pair_mut(winner_player, loser_player);
 
                             if give_back.is_some() != (loser_state.items.len() == 1) {
                                 // violating giveback/donate game rules
@@ -489,32 +792,31 @@ impl State {
                             // this whole structure is a bit more complex than it needs to be because we want
                             // to not modify the game state until we have determined that everything is in order
 
-                            let defender_del_idx = loser_state.items.iter().position(|x| *x == item).ok_or(CommandError::InvalidStealCommand)?;
-                            let attacker_del_idx = match give_back {
-                                None => None,
-                                // small complication here because we need to let you donate the item you are stealing
-                                Some(i) => Some(winner_state.items.iter().chain(iter::once(&item)).position(|x| *x == i).ok_or(CommandError::InvalidStealCommand)?),
-                            };
-
+                            if !loser_state.items.contains(&item) {
+                                return Err(CommandError::InvalidStealCommand);
+                            }
+                            // small complication here because we need to let you donate the item you are stealing
+                            if let Some(i) = give_back {
+                                if i != item && !winner_state.items.contains(&i) {
+                                    return Err(CommandError::InvalidStealCommand);
+                                }
+                            }
 
                             // only now that everything is verified and valid can we actually modify the game state
                             winner_state.items.push(item);
-                            if let Some(i) = attacker_del_idx {
-                                winner_state.items.remove(i);
-                            }
-
-                            loser_state.items.remove(defender_del_idx);
+                            loser_state.items.remove(item);
                             if let Some(i) = give_back {
+                                winner_state.items.remove(i);
                                 loser_state.items.push(i);
                             }
 
-                            if winner_state.items.len() > inventory_limit(s.p.players.len()) {
+                            if winner_state.items.len() > limit {
                                 TurnState::DonatingItem { donor: winner_player, followup: FollowupState::end_phase(attacker) }
                             } else {
                                 TurnState::WaitingForEndTurn(attacker)
                             }
                         }
-                        Command::ThreePlayerSelectFactionIndex { index } => {
+                        Command::ThreePlayerSelectFactionIndex { index } if !steal_items && index < 3 => {
                             if three_player_faction_index.is_some() || matches!(s.p.player(loser_player).faction, FactionKind::Normal(_)) {
                                 return Err(CommandError::InvalidCommandInThisContext);
                             }
@@ -527,14 +829,14 @@ impl State {
             }
             TurnState::TradePending { offerer, target, item } => {
                 let mut newstate = TurnState::WaitingForEndTurn(offerer);
-                s.action_log.push(ActionLogEntry::TradeOffer { offerer, target, accepted: matches!(c, Command::AcceptTrade { .. }) });
                 match c {
                     _ if actor != target => return Err(CommandError::NotYourTurn),
                     Command::AcceptTrade { item: item2 } => {
+                        s.log(actor, ActionLogEntry::TradeOffer { offerer, target, accepted: true });
                         newstate = perform_trade(s, offerer, item, target, item2, newstate)?;
                     }
                     Command::RejectTrade if [Item::BlackPearl, Item::BrokenMirror].contains(&item) => return Err(CommandError::MustAccept),
-                    Command::RejectTrade => (),
+                    Command::RejectTrade => s.log(actor, ActionLogEntry::TradeOffer { offerer, target, accepted: false }),
                     _ => return Err(CommandError::InvalidCommandInThisContext),
                 }
                 newstate
@@ -546,8 +848,8 @@ impl State {
 
                         match c {
                             Command::DoneLookingAtThings => Err(next_state),
-                            Command::ThreePlayerSelectFactionIndex { index } if
-                                matches!(trigger, TradeTriggerState::Monocle { three_player_faction_index: None })
+                            Command::ThreePlayerSelectFactionIndex { index } if index < 3
+                                && matches!(trigger, TradeTriggerState::Monocle { three_player_faction_index: None })
                                 && matches!(s.p.player(receiver).faction, FactionKind::ThreePlayer(_))
                                 => Ok(TurnState::ResolvingTradeTrigger { giver, receiver, trigger: TradeTriggerState::Monocle { three_player_faction_index: Some(index) }, next_state }),
                             _ => return Err(CommandError::InvalidCommandInThisContext),
@@ -560,8 +862,8 @@ impl State {
                             Command::PickNewJob { job } => {
                                 match s.job_stack.iter().position(|&j| j == job) {
                                     Some(i) => {
-                                        let mut p = s.p.player_mut(actor);
-                                        std::mem::swap(&mut s.job_stack[i], &mut p.job);
+                                        s.former_jobs.push((actor, s.p.player(actor).job));
+                                        std::mem::swap(&mut s.job_stack[i], &mut s.p.player_mut(actor).job);
                                         Err(next_state)
                                     }
                                     None => return Err(CommandError::InvalidJobError(job)),
@@ -585,26 +887,14 @@ impl State {
                                 }
                                 item_selections.insert(actor, item);
 
-                                if item_selections.len() == s.p.players.len() {
-                                    // everybody has made their choice!
-                                    fn eval_sextant<'a>(sels: &HashMap<Player, Item>, i: impl Iterator<Item=(&'a Player, &'a RefCell<PlayerState>)>) {
-                                        let mut i = i.peekable();
-                                        while let Some(((&px, sx), &(_, sy))) = i.next().and_then(|x| i.peek().map(|y| (x, y))) {
-                                            // move px's selection from px's inventory to py's inventory
-                                            let item = sels.get(&px).unwrap();
-                                            let mut xstate = sx.borrow_mut();
-                                            let xindex = xstate.items.iter().position(|i| i == item).unwrap();
-                                            xstate.items.remove(xindex);
-
-                                            sy.borrow_mut().items.push(*item);
-                                        }
-                                    }
-                                    let players_iter = s.p.players.iter();
-                                    let looped_iter = players_iter.clone().chain(players_iter.take(1));
-                                    if forward {
-                                        eval_sextant(&item_selections, looped_iter);
-                                    } else {
-                                        eval_sextant(&item_selections, looped_iter.rev());
+                                if item_selections.len() == s.p.len() {
+                                    // everybody has made their choice! every selection moves on to the next player
+                                    let ids: Vec<_> = s.p.ids().collect();
+                                    for (i, &px) in ids.iter().enumerate() {
+                                        let py = if forward { ids[(i + 1) % ids.len()] } else { ids[(i + ids.len() - 1) % ids.len()] };
+                                        let item = item_selections[&px];
+                                        s.p.player_mut(px).items.remove(item);
+                                        s.p.player_mut(py).items.push(item);
                                     }
 
                                     Err(next_state)
@@ -619,7 +909,7 @@ impl State {
 
                 match new_trigger {
                     Ok(x) => x,
-                    Err(next_state) => resolve_trade_followup(s, next_state),
+                    Err(next_state) => resolve_trade_followup(s, actor, next_state),
                 }
             }
             TurnState::DonatingItem { donor, followup } => {
@@ -628,17 +918,22 @@ impl State {
                 }
                 match c {
                     Command::DonateItem { target, item } => {
-                        {
-                            let mut donor_state = s.p.player_mut(donor);
-                            let mut target_state = s.p.player_mut(target);
-                            let index = donor_state.items.iter().position(|&i| i == item).ok_or(CommandError::InvalidItemError(item))?;
-                            donor_state.items.remove(index);
-                            target_state.items.push(item);
+                        if !s.p.contains(target) || target == donor {
+                            return Err(CommandError::InvalidTargetPlayer);
                         }
+                        // the donation must not push the receiver over the limit in turn
+                        if s.p.player(target).items.len() >= s.inventory_limit() {
+                            return Err(CommandError::InvalidTargetPlayer);
+                        }
+                        let (donor_state, target_state) = s.p.player_pair_mut(donor, target);
+                        if !donor_state.items.remove(item) {
+                            return Err(CommandError::InvalidItemError(item));
+                        }
+                        target_state.items.push(item);
 
-                        s.action_log.push(ActionLogEntry::DonateItem { giver: donor, receiver: target });
+                        s.log(actor, ActionLogEntry::DonateItem { giver: donor, receiver: target });
 
-                        resolve_trade_followup(s, followup)
+                        resolve_trade_followup(s, actor, followup)
                     }
                     _ => return Err(CommandError::InvalidCommandInThisContext),
                 }
@@ -660,7 +955,7 @@ impl State {
                         }
 
                         // shuffle the remaining items
-                        s.item_stack.shuffle(&mut rand::thread_rng());
+                        s.item_stack.shuffle(&mut s.rng);
 
                         // and push the selected ones back on top
                         for i in top_items.into_iter().rev() {
@@ -681,91 +976,111 @@ impl State {
                 self.turn = TurnState::WaitingForQuickblink(self.game.p.next_player(p));
             }
         }
+        if let TurnState::WaitingForQuickblink(p) = self.turn {
+            if !self.game.starts_round(p) || !self.end_round() {
+                self.game.start_turn(p);
+            }
+        }
+        self.record_reveal();
 
         Ok(())
     }
 
-    pub fn new(mut players: Vec<Player>, rng: &mut impl Rng) -> State {
+    /// Deals a new game. The same seed, players and config always deal the same game,
+    /// and all later random events are drawn from the same seeded generator.
+    ///
+    /// Panics if `config` does not pass `check_config` for these players.
+    pub fn new(mut players: Vec<Seat>, config: GameConfig, seed: u64) -> State {
         assert!(players.len() >= 3);
+        for (i, seat) in players.iter().enumerate() {
+            assert!(players[..i].iter().all(|s| s.id != seat.id), "{:?} is taken twice", seat.id);
+        }
+        if let Err(e) = check_config(&config, players.len()) {
+            panic!("{}", e);
+        }
+        let setup = GameSetup { players: players.clone(), config: config.clone(), seed };
+        let mut rng = GameRng::new(seed);
 
         // Das ist jetzt nicht mehr falsch
-        let mut start_items = [
-            Item::Key,
-            Item::Key,
-            Item::Key,
-            Item::Goblet,
-            Item::Goblet,
-            Item::Goblet,
-            Item::BlackPearl,
-            Item::Dagger,
-            Item::Gloves,
-            Item::PoisonRing,
-            Item::CastingKnives,
-            Item::Whip,
-            Item::Priviledge,
-            Item::Monocle
-        ];
-        let mut other_items = vec![
-            Item::BrokenMirror,
-            Item::Sextant,
-            Item::Coat,
-            Item::Tome,
-            Item::CoatOfArmorOfTheLoge
-        ];
-        let mut jobs = vec![
-            Job::Thug,
-            Job::GrandMaster,
-            Job::Bodyguard,
-            Job::Duelist,
-            Job::PoisonMixer,
-            Job::Doctor,
-            Job::Priest,
-            Job::Hypnotist,
-            Job::Diplomat,
-            Job::Clairvoyant,
-        ];
+        let mut start_items = vec![Item::Key; config.keys];
+        start_items.extend(vec![Item::Goblet; config.goblets]);
+        start_items.extend(&config.start_items);
+        let mut other_items = config.stack_items.clone();
+        let mut jobs = config.jobs.clone();
 
         let mut factions: Vec<_>;
         let factions: Box<dyn Iterator<Item=FactionKind>> = if players.len() == 3 {
             factions = iter::repeat(Faction::Order).take(5)
                 .chain(iter::repeat(Faction::Brotherhood).take(5)).collect();
-            factions.shuffle(rng);
+            factions.shuffle(&mut rng);
             Box::new(factions.chunks_exact(3).map(|chunk| FactionKind::ThreePlayer(chunk.try_into().unwrap())))
         } else {
             let instances_per_faction = (players.len() + 1) / 2;
             factions = iter::repeat(Faction::Order).take(instances_per_faction)
                 .chain(iter::repeat(Faction::Brotherhood).take(instances_per_faction)).collect();
-            let (factions, _) = factions.partial_shuffle(rng, players.len());
+            let (factions, _) = factions.partial_shuffle(&mut rng, players.len());
             Box::new(factions.iter().copied().map(FactionKind::Normal))
         };
 
-        let (start_items, other_start_items) = start_items.partial_shuffle(rng, players.len() - 2);
+        let (start_items, other_start_items) = start_items.partial_shuffle(&mut rng, players.len() - 2);
         let mut actual_start_items = vec![Item::BagGoblet, Item::BagKey];
         actual_start_items.extend(start_items.iter().copied());
-        actual_start_items.shuffle(rng);
-        players.shuffle(rng);
+        actual_start_items.shuffle(&mut rng);
+        players.shuffle(&mut rng);
         assert_eq!(players.len(), actual_start_items.len());
 
         other_items.extend(other_start_items.iter().copied());
-        other_items.shuffle(rng);
+        other_items.shuffle(&mut rng);
 
 
-        jobs.shuffle(rng);
+        jobs.shuffle(&mut rng);
         let (player_jobs, job_stack) = jobs.split_at_mut(players.len());
+        let last_round = config.round_limit.rounds;
 
         State {
             game: GameState {
                 item_stack: other_items,
                 job_stack: job_stack.iter().copied().collect(),
                 action_log: Vec::new(),
-                p: GameStatePlayers {
-                    players: players.iter().zip(actual_start_items).zip(player_jobs).zip(factions)
-                    .map(|(((&player, item), &mut job), faction)| (player, RefCell::new(PlayerState { faction, job, job_is_visible: false, items: vec![item] }))).collect()
-                }
+                config: Arc::new(config),
+                rng,
+                victory: None,
+                former_jobs: Vec::new(),
+                turn_index: TurnIndex { round: 1, turn: 1 },
+                turn_player: Some(players[0].id),
+                journal: Vec::new(),
+                sealed: Vec::new(),
+                resigned: Vec::new(),
+                last_round,
+                p: GameStatePlayers::new(
+                    players.iter().zip(actual_start_items).zip(player_jobs).zip(factions)
+                    .map(|(((seat, item), &mut job), faction)| (seat.id, PlayerState { faction, job, job_is_visible: false, items: Inventory::from([item]) })).collect(),
+                    players.clone(),
+                )
             },
-            turn: TurnState::WaitingForQuickblink(players[0]),
+            turn: TurnState::WaitingForQuickblink(players[0].id),
+            setup: Some(Arc::new(setup)),
+            history: Vec::new(),
+            last_command_undoable: false,
         }
     }
+    /// Everything that was hidden during the game, once it is over
+    pub fn summary(&self) -> Option<GameSummary> {
+        if !matches!(self.turn, TurnState::GameOver { .. }) {
+            return None;
+        }
+        let s = &self.game;
+        Some(GameSummary {
+            players: s.p.iter().map(|(player, state)| PlayerSummary {
+                player,
+                faction: state.faction,
+                jobs: s.former_jobs.iter().filter(|&&(p, _)| p == player).map(|&(_, j)| j).chain(iter::once(state.job)).collect(),
+                items: state.items.to_vec(),
+            }).collect(),
+            item_stack: s.item_stack.clone(),
+            job_stack: s.job_stack.clone(),
+        })
+    }
     pub fn spectate(&self) -> SpectatorPerspective {
         use PerspectiveTurnState::*;
         let turn = match &self.turn {
@@ -773,7 +1088,7 @@ impl State {
             &TurnState::WaitingForEndTurn(player) => TurnEndPhase { player },
             &TurnState::DoingClairvoyant { clairvoyant: c, .. } => DoingClairvoyant { player: c, item_stack: None },
             &TurnState::UnsuccessfulDiplomat { diplomat , target } => UnsuccessfulDiplomat { diplomat, target, inventory: None },
-            &TurnState::GameOver { winner } => GameOver { winner },
+            &TurnState::GameOver { winner } => GameOver { winner, evaluation: self.game.victory.clone(), standings: self.round_limit_standings(winner) },
             &TurnState::TradePending { offerer, target, .. } => TradePending { offerer, target, item: None },
             &TurnState::ResolvingTradeTrigger { giver, receiver, ref trigger, next_state: _ } => {
                 let trigger = match trigger {
@@ -790,36 +1105,34 @@ impl State {
             &TurnState::DonatingItem { donor, .. } => PerspectiveTurnState::DonatingItem { donor },
         };
         SpectatorPerspective {
-            players: self.game.p.players.iter().map(|(&k, v)| {
-                let v = v.borrow();
-                PerspectivePlayer {
-                    player: k,
-                    job: if v.job_is_visible { Some(v.job) } else { None },
-                    item_count: v.items.len(),
-                }
-            }).collect(),
+            players: self.game.public_players(),
             item_stack: self.game.item_stack.len(),
             turn,
+            turn_index: self.game.turn_index,
+            last_round: self.game.last_round,
             action_log: self.game.action_log.clone(),
+            pending_actors: self.visible_pending_actors(None),
+            summary: self.summary(),
+            config: (*self.game.config).clone(),
         }
     }
-    pub fn perspective(&self, p: Player) -> Perspective {
+    pub fn perspective(&self, p: SeatId) -> Perspective {
         use PerspectiveTurnState::*;
         let turn = match &self.turn {
             &TurnState::WaitingForQuickblink(player) => TurnStart { player },
             &TurnState::WaitingForEndTurn(player) => TurnEndPhase { player },
             &TurnState::DoingClairvoyant { clairvoyant: c, .. } if c == p => DoingClairvoyant { player: c, item_stack: Some(self.game.item_stack.clone()) },
             &TurnState::DoingClairvoyant { clairvoyant: c, .. } => DoingClairvoyant { player: c, item_stack: None },
-            &TurnState::UnsuccessfulDiplomat { diplomat , target } if diplomat == p => UnsuccessfulDiplomat { diplomat, target, inventory: Some(self.game.p.player(target).items.clone()) },
+            &TurnState::UnsuccessfulDiplomat { diplomat , target } if diplomat == p => UnsuccessfulDiplomat { diplomat, target, inventory: Some(self.game.p.player(target).items.to_vec()) },
             &TurnState::UnsuccessfulDiplomat { diplomat , target } => UnsuccessfulDiplomat { diplomat, target, inventory: None },
-            &TurnState::GameOver { winner } => GameOver { winner },
+            &TurnState::GameOver { winner } => GameOver { winner, evaluation: self.game.victory.clone(), standings: self.round_limit_standings(winner) },
             &TurnState::TradePending { offerer, target, item } if target == p => TradePending { offerer, target, item: Some(item) },
             &TurnState::TradePending { offerer, target, .. } => TradePending { offerer, target, item: None },
             &TurnState::ResolvingTradeTrigger { giver, receiver, ref trigger, next_state: _ } => {
                 let trigger = match trigger {
                     // only the relevant player is allowed to see the respective info
                     TradeTriggerState::Priviledge if giver == p =>
-                        PerspectiveTradeTriggerState::Priviledge { items: Some(self.game.p.player(receiver).items.clone()) },
+                        PerspectiveTradeTriggerState::Priviledge { items: Some(self.game.p.player(receiver).items.to_vec()) },
                     TradeTriggerState::Priviledge => PerspectiveTradeTriggerState::Priviledge { items: None },
                     &TradeTriggerState::Monocle { three_player_faction_index } if giver == p =>
                         PerspectiveTradeTriggerState::Monocle { faction: self.game.p.player(receiver).faction_by_index(three_player_faction_index), three_player_faction_index },
@@ -851,7 +1164,7 @@ impl State {
                         };
                         let victim = self.game.p.player(victim);
                         if steal_items {
-                            PerspectiveAttackState::FinishResolvingItems { target_items: victim.items.clone() }
+                            PerspectiveAttackState::FinishResolvingItems { target_items: victim.items.to_vec() }
                         } else {
                             victim
                                 .faction_by_index(three_player_faction_index)
@@ -868,38 +1181,168 @@ impl State {
             }
             &TurnState::DonatingItem { donor, .. } => PerspectiveTurnState::DonatingItem { donor },
         };
-        Perspective {
-            you: self.game.p.player(p).clone(),
-            your_player_index: self.game.p.players.get_index_of(&p).unwrap(),
-            players: self.game.p.players.iter().map(|(&k, v)| {
-                let v = v.borrow();
-                PerspectivePlayer {
-                    player: k,
-                    job: if v.job_is_visible { Some(v.job) } else { None },
-                    item_count: v.items.len(),
-                }
-            }).collect(),
+        let mut view = Perspective {
+            you: *self.game.p.player(p),
+            your_player_index: self.game.p.index_of(p).unwrap(),
+            players: self.game.public_players(),
             item_stack: self.game.item_stack.len(),
             turn,
+            turn_index: self.game.turn_index,
+            last_round: self.game.last_round,
             action_log: self.game.action_log.clone(),
+            pending_actors: self.visible_pending_actors(Some(p)),
+            summary: self.summary(),
+            attack_outlook: self.attack_outlook(p),
+            journal: self.journal(p),
+            notebook: Notebook::default(),
+            sealed_answer: self.sealed_answer(p).cloned(),
+            config: (*self.game.config).clone(),
+        };
+        view.notebook = deduce(&view);
+        view
+    }
+}
+
+/// Whether going from `before` to `after` via `c` showed anybody something that was hidden from them,
+/// i.e. whether taking the command back would let them act on knowledge they should not have.
+fn reveals_information(before: &State, after: &State, c: &Command) -> bool {
+    // items or jobs changing hands, being drawn or being put on the table
+    let (b, a) = (&before.game, &after.game);
+    // answers given in secret don't show anything until the last one is in
+    if a.sealed.len() > b.sealed.len() {
+        return false;
+    }
+    if b.item_stack != a.item_stack || b.job_stack != a.job_stack || b.p != a.p {
+        return true;
+    }
+    // buffs show the item or job they come from
+    if let Command::ItemOrJob { buff: Some(_), .. } = c {
+        return true;
+    }
+    // states in which somebody gets to look at hidden things
+    match &after.turn {
+        TurnState::GameOver { .. }
+        | TurnState::UnsuccessfulDiplomat { .. }
+        | TurnState::DoingClairvoyant { .. }
+        | TurnState::TradePending { .. }
+        | TurnState::Attacking { state: AttackState::FinishResolving { .. }, .. } => true,
+        TurnState::ResolvingTradeTrigger { trigger, .. } => !matches!(trigger, TradeTriggerState::Sextant { .. }),
+        _ => false,
+    }
+}
+
+/// Picks the item the diplomat actually receives when asking for `item`.
+/// Once the stack is empty, bags count as the item they contain.
+fn resolve_diplomat_item(target_items: &Inventory, item: Item, stack_empty: bool) -> Option<Item> {
+    let bag = match item {
+        Item::Goblet => Some(Item::BagGoblet),
+        Item::Key => Some(Item::BagKey),
+        _ => None,
+    };
+    // the item itself is handed over if the target has both
+    if target_items.contains(&item) {
+        Some(item)
+    } else {
+        bag.filter(|b| stack_empty && target_items.contains(b))
+    }
+}
+
+/// Bags can't be swapped for each other while the item stack still has cards.
+fn is_bag_swap(item_stack: &[Item], a: Item, b: Item) -> bool {
+    let items = [a, b];
+    !item_stack.is_empty() && items.contains(&Item::BagGoblet) && items.contains(&Item::BagKey)
+}
+
+fn announcement_winner(s: &GameState, actor: SeatId, flavor: &VictoryFlavor) -> Result<WinningFaction, CommandError> {
+    if s.p.player(actor).items.contains(&Item::BlackPearl) {
+        return Err(CommandError::BlackPearl);
+    }
+    evaluate_victory(s, actor, flavor)?.winner.ok_or(CommandError::InvalidLogeVictory)
+}
+
+/// Only fails for announcements that make no sense at all. Everything else,
+/// including the ones the rules forbid, gets an evaluation.
+fn evaluate_victory(s: &GameState, actor: SeatId, flavor: &VictoryFlavor) -> Result<VictoryEvaluation, CommandError> {
+    let actor_player = s.p.player(actor);
+    let faction = actor_player.effective_faction();
+    let num_faction_members = s.p.states().filter(|x| x.effective_faction() == faction).count();
+    let minority = num_faction_members * 2 < s.p.len();
+    let required_items: &[_] = match (flavor, faction) {
+        (VictoryFlavor::Normal { .. }, Faction::Order) => &[Item::Key, Item::BagKey],
+        (VictoryFlavor::Normal { .. }, Faction::Brotherhood) => &[Item::Goblet, Item::BagGoblet],
+        (VictoryFlavor::Loge, _) => &[Item::Key, Item::BagKey, Item::Goblet, Item::BagGoblet],
+    };
+    let mut required_items = required_items.to_vec();
+
+    if !s.item_stack.is_empty() {
+        required_items.retain(|&x| x != Item::BagKey && x != Item::BagGoblet);
+    }
+
+    let (loge, teammates) = match flavor {
+        VictoryFlavor::Normal { teammates } => (false, teammates.as_slice()),
+        VictoryFlavor::Loge => (true, &[][..]),
+    };
+    // every teammate has to be a different seated player (and not yourself)
+    for (i, &t) in teammates.iter().enumerate() {
+        if t == actor || !s.p.contains(t) || teammates[..i].contains(&t) {
+            return Err(CommandError::InvalidTargetPlayer);
         }
     }
+    if loge && !s.config.loge_victory {
+        return Err(CommandError::LogeVictoryDisabled);
+    }
+    let contributions = iter::once(actor).chain(teammates.iter().copied()).map(|t| {
+        let ts = s.p.player(t);
+        VictoryContribution {
+            player: t,
+            items: ts.items.iter().filter(|i| required_items.contains(i)).collect(),
+            same_faction: ts.effective_faction() == faction,
+        }
+    }).collect();
+
+    let mut evaluation = VictoryEvaluation {
+        announcer: actor,
+        flavor: flavor.clone(),
+        faction,
+        required_items,
+        needed_items: if minority { s.config.minority_victory_items } else { s.config.victory_items },
+        minority,
+        contributions,
+        black_pearl: actor_player.items.contains(&Item::BlackPearl),
+        loge_coat: if loge { Some(actor_player.items.contains(&Item::CoatOfArmorOfTheLoge)) } else { None },
+        winner: None,
+    };
+    let victory = evaluation.team_qualifies();
+    evaluation.winner = if evaluation.black_pearl {
+        None
+    } else if loge {
+        Some(WinningFaction::Traitor(actor)).filter(|_| victory && evaluation.loge_coat == Some(true))
+    } else if victory {
+        Some(WinningFaction::Normal(faction))
+    } else {
+        Some(WinningFaction::Normal(match faction {
+            Faction::Order => Faction::Brotherhood,
+            Faction::Brotherhood => Faction::Order,
+        }))
+    };
+    Ok(evaluation)
 }
 
 struct NeedDonation;
 fn try_resolve_trade_trigger(
     item: Item,
     item_stack: &mut Vec<Item>,
+    job_stack: &[Job],
     offerer_state: &mut PlayerState,
     target_state: &mut PlayerState,
-    num_players: usize,
+    inventory_limit: usize,
 ) -> (Option<Result<TradeTriggerState, NeedDonation>>, bool) {
     let mut public_information = true;
     let ret = match item {
         Item::BagKey | Item::BagGoblet => {
             if let Some(i) = item_stack.pop() {
                 offerer_state.items.push(i);
-                if offerer_state.items.len() > inventory_limit(num_players) {
+                if offerer_state.items.len() > inventory_limit {
                     return (Some(Err(NeedDonation)), true);
                 }
             } else {
@@ -911,6 +1354,8 @@ fn try_resolve_trade_trigger(
         Item::Priviledge => Some(Ok(TradeTriggerState::Priviledge)),
         Item::Monocle => Some(Ok(TradeTriggerState::Monocle { three_player_faction_index: None })),
         Item::Sextant => Some(Ok(TradeTriggerState::Sextant { item_selections: HashMap::new(), is_forward: None })),
+        // with every job dealt (10 players) there is nothing to exchange
+        Item::Coat if job_stack.is_empty() => None,
         Item::Coat => Some(Ok(TradeTriggerState::Coat)),
         Item::Tome => {
             std::mem::swap(&mut offerer_state.job, &mut target_state.job);
@@ -928,50 +1373,55 @@ fn try_resolve_trade_trigger(
 }
 fn resolve_trade_followup(
     s: &mut GameState,
+    actor: SeatId,
     followup: FollowupState,
 ) -> TurnState {
     match followup {
         FollowupState::State(s) => *s,
         FollowupState::TradeTriggers { giver, receiver, item, next_state } => {
-            let (mut offerer_state, mut target_state) = s.p.player_pair_mut(giver, receiver);
-            let (trigger, public) = try_resolve_trade_trigger(item, &mut s.item_stack, &mut *offerer_state, &mut *target_state, s.p.players.len());
+            let limit = s.inventory_limit();
+            let (offerer_state, target_state) = s.p.player_pair_mut(giver, receiver);
+            let (trigger, public) = try_resolve_trade_trigger(item, &mut s.item_stack, &s.job_stack, offerer_state, target_state, limit);
             if public {
                 // render both types of bags as BagGoblet to ensure we don't leak which one it is
                 let item = if item == Item::BagKey { Item::BagGoblet } else { item };
-                s.action_log.push(ActionLogEntry::TradeTrigger { giver, receiver, item });
+                s.log(actor, ActionLogEntry::TradeTrigger { giver, receiver, item });
             }
             match trigger {
                 None => *next_state,
                 Some(Ok(trigger)) => TurnState::ResolvingTradeTrigger { giver, receiver, next_state: FollowupState::State(next_state), trigger },
-                Some(Err(NeedDonation)) => TurnState::DonatingItem { donor: receiver, followup: FollowupState::State(next_state) },
+                // the giver of a bag is the one who draws, so they may have to donate
+                Some(Err(NeedDonation)) => TurnState::DonatingItem { donor: giver, followup: FollowupState::State(next_state) },
             }
         }
     }
 }
 fn perform_trade(
     s: &mut GameState,
-    offerer: Player,
+    offerer: SeatId,
     item: Item,
-    target: Player,
+    target: SeatId,
     item2: Item,
     next_state: TurnState,
 ) -> Result<TurnState, CommandError> {
     let items = [item, item2];
-    if !s.item_stack.is_empty() && items.contains(&Item::BagGoblet) && items.contains(&Item::BagKey) {
+    if is_bag_swap(&s.item_stack, item, item2) {
         // TODO: is item stack even relevant here?
         return Err(CommandError::InvalidItemError(item2)); // can't swap bag for bag
     }
 
-    let (mut offerer_state, mut target_state) = s.p.player_pair_mut(offerer, target);
+    let limit = s.inventory_limit();
+    let (offerer_state, target_state) = s.p.player_pair_mut(offerer, target);
 
-    let idx_offerer = offerer_state.items.iter().position(|&i| i == item)
-        .expect("We should not have allowed them to offer an item they don't even have.");
-    let idx_target = match target_state.items.iter().position(|&i| i == item2) {
-        Some(i) => i,
-        None => return Err(CommandError::InvalidItemError(item2)),
-    };
+    assert!(offerer_state.items.contains(&item), "We should not have allowed them to offer an item they don't even have.");
+    if !target_state.items.contains(&item2) {
+        return Err(CommandError::InvalidItemError(item2));
+    }
 
-    std::mem::swap(&mut offerer_state.items[idx_offerer], &mut target_state.items[idx_target]);
+    offerer_state.items.remove(item);
+    target_state.items.remove(item2);
+    offerer_state.items.push(item2);
+    target_state.items.push(item);
     // TODO: does this represent the game rules accurately?
 
     Ok(if items.contains(&Item::BrokenMirror) {
@@ -979,13 +1429,13 @@ fn perform_trade(
         next_state
     } else {
         // triggers for offered item
-        let np = s.p.players.len();
 
         let next_state = Box::new(next_state);
 
-        let (trigger, public) = try_resolve_trade_trigger(item, &mut s.item_stack, &mut offerer_state, &mut target_state, np);
+        let (trigger, public) = try_resolve_trade_trigger(item, &mut s.item_stack, &s.job_stack, offerer_state, target_state, limit);
         if public {
-            s.action_log.push(ActionLogEntry::TradeTrigger { giver: offerer, receiver: target, item });
+            // whoever completes a trade is always on the `target` side
+            s.log(target, ActionLogEntry::TradeTrigger { giver: offerer, receiver: target, item });
         }
         match trigger {
             Some(trigger) => {
@@ -996,9 +1446,10 @@ fn perform_trade(
                 }
             }
             None => {
-                let (trigger, public) = try_resolve_trade_trigger(item2, &mut s.item_stack, &mut target_state, &mut offerer_state, np);
+                let (offerer_state, target_state) = s.p.player_pair_mut(offerer, target);
+                let (trigger, public) = try_resolve_trade_trigger(item2, &mut s.item_stack, &s.job_stack, target_state, offerer_state, limit);
                 if public {
-                    s.action_log.push(ActionLogEntry::TradeTrigger { giver: target, receiver: offerer, item: item2 });
+                    s.log(target, ActionLogEntry::TradeTrigger { giver: target, receiver: offerer, item: item2 });
                 }
                 match trigger {
                     Some(trigger) => {
@@ -1017,7 +1468,22 @@ fn perform_trade(
 
 
 
+mod legal;
+mod journal;
+mod round_limit;
+mod sealed;
+mod resign;
+mod deduction;
+pub use deduction::deduce;
+mod invariants;
+pub use invariants::InvariantViolation;
+mod config;
+pub use config::{ConfigError, check_config};
+pub mod bot;
+
 #[cfg(test)]
 mod tests;
+#[cfg(test)]
+mod fuzz;
 
 // https://silo.tips/download/die-kutschfahrt-zur-teufelsburg-autoren-michael-palm-und-lukas-zach
\ No newline at end of file
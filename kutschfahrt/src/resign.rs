@@ -0,0 +1,69 @@
+//! Players who leave a game that is already going.
+//!
+//! Their seat stays at the table with everything they hold, but whatever the game waits for from them
+//! is answered right away by a fixed policy, so the others can play on. Once everybody still playing
+//! is on the same side, nobody is left to stop them and they win.
+
+use super::*;
+
+impl GameState {
+    /// `GameStatePlayers::public`, along with who resigned
+    pub(crate) fn public_players(&self) -> Vec<PerspectivePlayer> {
+        let mut players = self.p.public();
+        for p in &mut players {
+            p.resigned = self.resigned.contains(&p.player);
+        }
+        players
+    }
+}
+
+impl State {
+    pub fn has_resigned(&self, p: SeatId) -> bool {
+        self.game.resigned.contains(&p)
+    }
+
+    pub(crate) fn resign(&mut self, actor: SeatId) -> Result<(), CommandError> {
+        if let TurnState::GameOver { .. } = self.turn {
+            return Err(CommandError::GameOver);
+        }
+        if !self.game.p.contains(actor) {
+            return Err(CommandError::InvalidTargetPlayer);
+        }
+        self.game.resigned.push(actor);
+        self.game.log(actor, ActionLogEntry::Resign { actor });
+
+        let mut factions = self.game.p.iter().filter(|(p, _)| !self.game.resigned.contains(p)).map(|(_, state)| state.effective_faction());
+        let winner = match factions.next() {
+            Some(f) if factions.all(|g| g == f) => Some(f),
+            _ => None,
+        };
+        if let Some(winner) = winner {
+            self.turn = TurnState::GameOver { winner: WinningFaction::Normal(winner) };
+        }
+        Ok(())
+    }
+
+    /// What a resigned player answers: they pass, don't use the Priest, abstain, reject trades and don't hypnotize.
+    /// When they have to pick something, they pick the first thing they could.
+    fn default_answer(&self, p: SeatId) -> Option<Command> {
+        let cmds = self.legal_commands(p);
+        let passive = cmds.iter().find(|c| matches!(c,
+            Command::Pass
+            | Command::UsePriest { priest: false }
+            | Command::DeclareSupport { support: AttackSupport::Abstain }
+            | Command::ItemOrJob { buff: None, .. }
+            | Command::RejectTrade
+            | Command::Hypnotize { target: None }
+            | Command::DoneLookingAtThings
+        ));
+        passive.or_else(|| cmds.iter().find(|c| !matches!(c, Command::AnnounceVictory { .. }))).cloned()
+    }
+
+    /// Answers everything the game waits for from players who resigned
+    pub(crate) fn answer_for_resigned(&mut self) {
+        while let Some(p) = self.pending_actors().into_iter().find(|&p| self.has_resigned(p)) {
+            let c = self.default_answer(p).expect("pending players always have something to do");
+            self.apply_or_seal(p, c).expect("legal commands are always accepted");
+        }
+    }
+}
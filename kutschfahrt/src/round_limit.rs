@@ -0,0 +1,65 @@
+//! Ending games that nobody manages to win, see `RoundLimit`.
+//!
+//! The limit is only checked when play gets back to the first player, so everybody gets the same number
+//! of turns. Then either nobody wins, or with a tiebreak, the faction that was missing fewer of the
+//! victory items it would have needed to announce.
+
+use super::*;
+
+impl GameState {
+    /// How close each faction with any members came to victory, counting the way an announcement would
+    fn standings(&self) -> Vec<FactionStanding> {
+        let stack_empty = self.item_stack.is_empty();
+        [Faction::Order, Faction::Brotherhood].into_iter().filter_map(|faction| {
+            let members: Vec<_> = self.p.states().filter(|x| x.effective_faction() == faction).collect();
+            if members.is_empty() {
+                return None;
+            }
+            let counts = |i: Item| match (faction, i) {
+                (Faction::Order, Item::Key) | (Faction::Brotherhood, Item::Goblet) => true,
+                (Faction::Order, Item::BagKey) | (Faction::Brotherhood, Item::BagGoblet) => stack_empty,
+                _ => false,
+            };
+            let minority = members.len() * 2 < self.p.len();
+            Some(FactionStanding {
+                faction,
+                held_items: members.iter().map(|x| x.items.iter().filter(|&i| counts(i)).count()).sum(),
+                needed_items: if minority { self.config.minority_victory_items } else { self.config.victory_items },
+            })
+        }).collect()
+    }
+}
+
+impl State {
+    /// Called before the first turn of a new round. Ends the game and returns true if the round limit is reached.
+    pub(crate) fn end_round(&mut self) -> bool {
+        let round = self.game.turn_index.round;
+        if let Some(rounds) = self.game.config.round_limit.rounds_after_stack {
+            if self.game.item_stack.is_empty() {
+                let last = round + rounds;
+                self.game.last_round = Some(self.game.last_round.map_or(last, |l| l.min(last)));
+            }
+        }
+        if self.game.last_round.is_none_or(|l| round < l) {
+            return false;
+        }
+
+        let standings = self.game.standings();
+        let closest = standings.iter().map(FactionStanding::missing_items).min();
+        let mut leaders = standings.iter().filter(|x| Some(x.missing_items()) == closest);
+        let winner = match (leaders.next(), leaders.next()) {
+            (Some(leader), None) if self.game.config.round_limit.tiebreak => WinningFaction::Tiebreak(leader.faction),
+            _ => WinningFaction::Draw,
+        };
+        self.turn = TurnState::GameOver { winner };
+        true
+    }
+
+    /// What decided a game that `winner` says the round limit ended
+    pub(crate) fn round_limit_standings(&self, winner: WinningFaction) -> Vec<FactionStanding> {
+        match winner {
+            WinningFaction::Draw | WinningFaction::Tiebreak(_) => self.game.standings(),
+            WinningFaction::Normal(_) | WinningFaction::Traitor(_) => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Answers given in secret, for the phases that wait for several players at once.
+//!
+//! With `GameConfig::sealed_answers`, nobody can tell from the timing whether somebody holds the Priest
+//! or a buff: the answers are only collected, and nobody gets to see who already answered. Once the last
+//! one is in, what anybody did counts first, in seating order. Passing only counts if nobody did anything,
+//! since otherwise the others would have wanted to react to it and are asked again.
+
+use super::*;
+
+impl State {
+    /// The answer that does nothing in the current phase, if that phase is answered in secret
+    fn pass_answer(&self) -> Option<Command> {
+        if !self.game.config.sealed_answers {
+            return None;
+        }
+        match &self.turn {
+            TurnState::Attacking { state: AttackState::WaitingForPriest { .. }, .. } => Some(Command::UsePriest { priest: false }),
+            TurnState::Attacking { state: AttackState::ItemsOrJobs { .. }, .. } => Some(Command::ItemOrJob { buff: None, target: None }),
+            _ => None,
+        }
+    }
+
+    /// What `p` answered in secret, until everybody answered
+    pub fn sealed_answer(&self, p: SeatId) -> Option<&Command> {
+        self.game.sealed.iter().find(|&&(q, _)| q == p).map(|(_, c)| c)
+    }
+
+    /// `pending_actors` as `viewer` gets to see it, which is `None` for spectators.
+    /// Everybody else who answered in secret still seems to think about it.
+    pub(crate) fn visible_pending_actors(&self, viewer: Option<SeatId>) -> Vec<SeatId> {
+        let pending = self.pending_actors();
+        self.game.p.ids().filter(|&p| pending.contains(&p) || (Some(p) != viewer && self.sealed_answer(p).is_some())).collect()
+    }
+
+    /// Applies `c`, or keeps it to itself until everybody answered if the current phase is answered in secret.
+    pub(crate) fn apply_or_seal(&mut self, actor: SeatId, c: Command) -> Result<(), CommandError> {
+        let Some(pass) = self.pass_answer() else {
+            return self.apply_command_unchecked(actor, c);
+        };
+        if self.sealed_answer(actor).is_some() {
+            return Err(CommandError::YouHaveAlreadyPassed);
+        }
+        // only answers that would count right now may be given
+        self.clone().apply_command_unchecked(actor, c.clone())?;
+        self.game.sealed.push((actor, c));
+        if !self.pending_actors().is_empty() {
+            return Ok(());
+        }
+
+        let mut answers = std::mem::take(&mut self.game.sealed);
+        answers.sort_by_key(|&(p, _)| self.game.p.index_of(p));
+        let (passes, actions): (Vec<_>, Vec<_>) = answers.into_iter().partition(|(_, c)| *c == pass);
+        let mut acted = false;
+        for (p, c) in actions {
+            if self.pass_answer().as_ref() != Some(&pass) {
+                // somebody before them ended the phase
+                break;
+            }
+            // or used up the same buff
+            let mut next = self.clone();
+            if next.apply_command_unchecked(p, c).is_ok() {
+                *self = next;
+                acted = true;
+            }
+        }
+        if !acted {
+            for (p, c) in passes {
+                self.apply_command_unchecked(p, c).expect("passing is always allowed");
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,632 +1,881 @@
 use super::*;
+use bot::Agent;
+
+const MARIE: SeatId = SeatId(0);
+const GUNDLA: SeatId = SeatId(1);
+const SARAH: SeatId = SeatId(2);
+const ZACHARIAS: SeatId = SeatId(7);
+
+/// `n` players without a character
+pub(crate) fn seats(n: usize) -> Vec<Seat> {
+    (0..n as u32).map(|i| Seat { id: SeatId(i), name: format!("Player {}", i + 1), character: None }).collect()
+}
 
 fn teststate() -> State {
     State {
         game: GameState {
-            p: GameStatePlayers { players: [
-                (Player::Sarah, RefCell::new(PlayerState { faction: FactionKind::Normal(Faction::Order), job: Job::Duelist, job_is_visible: false, items: vec![Item::BagKey] })),
-                (Player::Gundla, RefCell::new(PlayerState { faction: FactionKind::Normal(Faction::Brotherhood), job: Job::Clairvoyant, job_is_visible: false, items: vec![Item::BagGoblet] })),
-                (Player::Marie, RefCell::new(PlayerState { faction: FactionKind::Normal(Faction::Order), job: Job::Thug, job_is_visible: false, items: vec![Item::PoisonRing] })),
-                (Player::Zacharias, RefCell::new(PlayerState { faction: FactionKind::Normal(Faction::Brotherhood), job: Job::Hypnotist, job_is_visible: false, items: vec![Item::Gloves] })),
-            ].into_iter().collect() },
+            p: GameStatePlayers::with_legacy_seats(vec![
+                (SARAH, PlayerState { faction: FactionKind::Normal(Faction::Order), job: Job::Duelist, job_is_visible: false, items: [Item::BagKey].into() }),
+                (GUNDLA, PlayerState { faction: FactionKind::Normal(Faction::Brotherhood), job: Job::Clairvoyant, job_is_visible: false, items: [Item::BagGoblet].into() }),
+                (MARIE, PlayerState { faction: FactionKind::Normal(Faction::Order), job: Job::Thug, job_is_visible: false, items: [Item::PoisonRing].into() }),
+                (ZACHARIAS, PlayerState { faction: FactionKind::Normal(Faction::Brotherhood), job: Job::Hypnotist, job_is_visible: false, items: [Item::Gloves].into() }),
+            ]),
             item_stack: vec![Item::BlackPearl, Item::Dagger],
             job_stack: vec![Job::Doctor],
             action_log: vec![],
+            config: Arc::default(),
+            rng: GameRng::new(0),
+            victory: None,
+            former_jobs: Vec::new(),
+            turn_index: TurnIndex { round: 1, turn: 1 },
+            turn_player: Some(SARAH),
+            journal: Vec::new(),
+            sealed: Vec::new(),
+            resigned: Vec::new(),
+            last_round: None,
         },
-        turn: TurnState::WaitingForQuickblink(Player::Sarah),
+        turn: TurnState::WaitingForQuickblink(SARAH),
+        setup: None,
+        history: vec![],
+        last_command_undoable: false,
     }
 }
 fn teststate_3p() -> State {
     State {
         game: GameState {
-            p: GameStatePlayers { players: [
-                (Player::Sarah, RefCell::new(PlayerState { faction: FactionKind::ThreePlayer([Faction::Order, Faction::Order, Faction::Brotherhood]), job: Job::Duelist, job_is_visible: false, items: vec![Item::BagKey] })),
-                (Player::Gundla, RefCell::new(PlayerState { faction: FactionKind::ThreePlayer([Faction::Brotherhood, Faction::Brotherhood, Faction::Order]), job: Job::Clairvoyant, job_is_visible: false, items: vec![Item::BagGoblet] })),
-                (Player::Marie, RefCell::new(PlayerState { faction: FactionKind::ThreePlayer([Faction::Order, Faction::Order, Faction::Brotherhood]), job: Job::Thug, job_is_visible: false, items: vec![Item::PoisonRing] })),
-            ].into_iter().collect() },
+            p: GameStatePlayers::with_legacy_seats(vec![
+                (SARAH, PlayerState { faction: FactionKind::ThreePlayer([Faction::Order, Faction::Order, Faction::Brotherhood]), job: Job::Duelist, job_is_visible: false, items: [Item::BagKey].into() }),
+                (GUNDLA, PlayerState { faction: FactionKind::ThreePlayer([Faction::Brotherhood, Faction::Brotherhood, Faction::Order]), job: Job::Clairvoyant, job_is_visible: false, items: [Item::BagGoblet].into() }),
+                (MARIE, PlayerState { faction: FactionKind::ThreePlayer([Faction::Order, Faction::Order, Faction::Brotherhood]), job: Job::Thug, job_is_visible: false, items: [Item::PoisonRing].into() }),
+            ]),
             item_stack: vec![Item::BlackPearl, Item::Dagger],
             job_stack: vec![Job::Doctor],
             action_log: vec![],
+            config: Arc::default(),
+            rng: GameRng::new(0),
+            victory: None,
+            former_jobs: Vec::new(),
+            turn_index: TurnIndex { round: 1, turn: 1 },
+            turn_player: Some(SARAH),
+            journal: Vec::new(),
+            sealed: Vec::new(),
+            resigned: Vec::new(),
+            last_round: None,
         },
-        turn: TurnState::WaitingForQuickblink(Player::Sarah),
+        turn: TurnState::WaitingForQuickblink(SARAH),
+        setup: None,
+        history: vec![],
+        last_command_undoable: false,
     }
 }
 
 #[test]
 fn pass() {
     let mut s = teststate();
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    s.apply_command(Player::Gundla, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Marie));
-    s.apply_command(Player::Marie, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Zacharias));
-    s.apply_command(Player::Zacharias, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Sarah));
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    s.apply_command(GUNDLA, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(MARIE));
+    s.apply_command(MARIE, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(ZACHARIAS));
+    s.apply_command(ZACHARIAS, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(SARAH));
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
 }
 
 #[test]
 fn wrong_player() {
     let mut s = teststate();
-    assert_eq!(s.apply_command(Player::Gundla, Command::Pass), Err(CommandError::NotYourTurn));
+    assert_eq!(s.apply_command(GUNDLA, Command::Pass), Err(CommandError::NotYourTurn));
 }
 
 #[test]
 fn trade_bad_item() {
     let mut s = teststate();
-    assert_eq!(s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Gundla, item: Item::Key }), Err(CommandError::InvalidItemError(Item::Key)));
+    assert_eq!(s.apply_command(SARAH, Command::OfferTrade { target: GUNDLA, item: Item::Key }), Err(CommandError::InvalidItemError(Item::Key)));
 }
 
 #[test]
 fn trade_bad_return_item() {
     let mut s = teststate();
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Gundla, item: Item::BagKey }).unwrap();
-    assert_eq!(s.apply_command(Player::Gundla, Command::AcceptTrade { item: Item::BagKey }), Err(CommandError::InvalidItemError(Item::BagKey)));
+    s.apply_command(SARAH, Command::OfferTrade { target: GUNDLA, item: Item::BagKey }).unwrap();
+    assert_eq!(s.apply_command(GUNDLA, Command::AcceptTrade { item: Item::BagKey }), Err(CommandError::InvalidItemError(Item::BagKey)));
 }
 
 #[test]
 fn trade_bag_invalid() {
     let mut s = teststate();
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Gundla, item: Item::BagKey }).unwrap();
-    assert_eq!(s.apply_command(Player::Gundla, Command::AcceptTrade { item: Item::BagGoblet }), Err(CommandError::InvalidItemError(Item::BagGoblet)));
+    s.apply_command(SARAH, Command::OfferTrade { target: GUNDLA, item: Item::BagKey }).unwrap();
+    assert_eq!(s.apply_command(GUNDLA, Command::AcceptTrade { item: Item::BagGoblet }), Err(CommandError::InvalidItemError(Item::BagGoblet)));
 }
 
 
 #[test]
 fn trade_bag_reject() {
     let mut s = teststate();
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Gundla, item: Item::BagKey }).unwrap();
-    s.apply_command(Player::Gundla, Command::RejectTrade).unwrap();
+    s.apply_command(SARAH, Command::OfferTrade { target: GUNDLA, item: Item::BagKey }).unwrap();
+    s.apply_command(GUNDLA, Command::RejectTrade).unwrap();
 }
 
 
 #[test]
 fn trade_bag_valid() {
     let mut s = teststate();
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Marie, item: Item::BagKey }).unwrap();
-    s.apply_command(Player::Marie, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    assert!(s.game.p.player(Player::Sarah).items.contains(&Item::PoisonRing));
-    assert!(!s.game.p.player(Player::Sarah).items.contains(&Item::BagKey));
-    assert!(s.game.p.player(Player::Sarah).items.contains(&Item::Dagger)); // test that the effect triggered
-
-    assert!(s.game.p.player(Player::Marie).items.contains(&Item::BagKey));
-    assert!(!s.game.p.player(Player::Marie).items.contains(&Item::PoisonRing));
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::BagKey }).unwrap();
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    assert!(s.game.p.player(SARAH).items.contains(&Item::PoisonRing));
+    assert!(!s.game.p.player(SARAH).items.contains(&Item::BagKey));
+    assert!(s.game.p.player(SARAH).items.contains(&Item::Dagger)); // test that the effect triggered
+
+    assert!(s.game.p.player(MARIE).items.contains(&Item::BagKey));
+    assert!(!s.game.p.player(MARIE).items.contains(&Item::PoisonRing));
 }
 
 #[test]
 fn trade_blackpearl_no_reject() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.push(Item::BlackPearl);
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Marie, item: Item::BlackPearl }).unwrap();
-    assert_eq!(s.apply_command(Player::Marie, Command::RejectTrade), Err(CommandError::MustAccept));
-    s.apply_command(Player::Marie, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    assert!(s.game.p.player(Player::Sarah).items.contains(&Item::PoisonRing));
-    assert!(!s.game.p.player(Player::Sarah).items.contains(&Item::BlackPearl));
-
-    assert!(s.game.p.player(Player::Marie).items.contains(&Item::BlackPearl));
-    assert!(!s.game.p.player(Player::Marie).items.contains(&Item::PoisonRing));
+    s.game.p.player_mut(SARAH).items.push(Item::BlackPearl);
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::BlackPearl }).unwrap();
+    assert_eq!(s.apply_command(MARIE, Command::RejectTrade), Err(CommandError::MustAccept));
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    assert!(s.game.p.player(SARAH).items.contains(&Item::PoisonRing));
+    assert!(!s.game.p.player(SARAH).items.contains(&Item::BlackPearl));
+
+    assert!(s.game.p.player(MARIE).items.contains(&Item::BlackPearl));
+    assert!(!s.game.p.player(MARIE).items.contains(&Item::PoisonRing));
 }
 
 #[test]
 fn trade_brokenmirror_no_reject() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.push(Item::BrokenMirror);
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Marie, item: Item::BrokenMirror }).unwrap();
-    assert_eq!(s.apply_command(Player::Marie, Command::RejectTrade), Err(CommandError::MustAccept));
-    s.apply_command(Player::Marie, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    assert!(s.game.p.player(Player::Sarah).items.contains(&Item::PoisonRing));
-    assert!(!s.game.p.player(Player::Sarah).items.contains(&Item::BrokenMirror));
-
-    assert!(s.game.p.player(Player::Marie).items.contains(&Item::BrokenMirror));
-    assert!(!s.game.p.player(Player::Marie).items.contains(&Item::PoisonRing));
+    s.game.p.player_mut(SARAH).items.push(Item::BrokenMirror);
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::BrokenMirror }).unwrap();
+    assert_eq!(s.apply_command(MARIE, Command::RejectTrade), Err(CommandError::MustAccept));
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    assert!(s.game.p.player(SARAH).items.contains(&Item::PoisonRing));
+    assert!(!s.game.p.player(SARAH).items.contains(&Item::BrokenMirror));
+
+    assert!(s.game.p.player(MARIE).items.contains(&Item::BrokenMirror));
+    assert!(!s.game.p.player(MARIE).items.contains(&Item::PoisonRing));
 }
 
 #[test]
 fn trade_monocle() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.push(Item::Monocle);
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Marie, item: Item::Monocle }).unwrap();
-    s.apply_command(Player::Marie, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Sarah, receiver: Player::Marie, trigger: PerspectiveTradeTriggerState::Monocle { faction: Some(Faction::Order), three_player_faction_index: None } });
-    assert_eq!(s.perspective(Player::Zacharias).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Sarah, receiver: Player::Marie, trigger: PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: None } });
-    s.apply_command(Player::Sarah, Command::DoneLookingAtThings).unwrap();
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
+    s.game.p.player_mut(SARAH).items.push(Item::Monocle);
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::Monocle }).unwrap();
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: SARAH, receiver: MARIE, trigger: PerspectiveTradeTriggerState::Monocle { faction: Some(Faction::Order), three_player_faction_index: None } });
+    assert_eq!(s.perspective(ZACHARIAS).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: SARAH, receiver: MARIE, trigger: PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: None } });
+    s.apply_command(SARAH, Command::DoneLookingAtThings).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
 }
 
 #[test]
 fn trade_monocle_backwards() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.push(Item::Monocle);
-    s.turn = TurnState::WaitingForQuickblink(Player::Marie);
-    s.apply_command(Player::Marie, Command::OfferTrade { target: Player::Sarah, item: Item::PoisonRing }).unwrap();
-    s.apply_command(Player::Sarah, Command::AcceptTrade { item: Item::Monocle }).unwrap();
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Sarah, receiver: Player::Marie, trigger: PerspectiveTradeTriggerState::Monocle { faction: Some(Faction::Order), three_player_faction_index: None } });
-    assert_eq!(s.perspective(Player::Zacharias).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Sarah, receiver: Player::Marie, trigger: PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: None } });
-    s.apply_command(Player::Sarah, Command::DoneLookingAtThings).unwrap();
-    s.apply_command(Player::Marie, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Zacharias));
+    s.game.p.player_mut(SARAH).items.push(Item::Monocle);
+    s.turn = TurnState::WaitingForQuickblink(MARIE);
+    s.apply_command(MARIE, Command::OfferTrade { target: SARAH, item: Item::PoisonRing }).unwrap();
+    s.apply_command(SARAH, Command::AcceptTrade { item: Item::Monocle }).unwrap();
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: SARAH, receiver: MARIE, trigger: PerspectiveTradeTriggerState::Monocle { faction: Some(Faction::Order), three_player_faction_index: None } });
+    assert_eq!(s.perspective(ZACHARIAS).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: SARAH, receiver: MARIE, trigger: PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: None } });
+    s.apply_command(SARAH, Command::DoneLookingAtThings).unwrap();
+    s.apply_command(MARIE, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(ZACHARIAS));
 }
 
 #[test]
 fn trade_monocle_priviledge() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.push(Item::Monocle);
-    s.game.p.player_mut(Player::Marie).items.push(Item::Priviledge);
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Marie, item: Item::Monocle }).unwrap();
-    s.apply_command(Player::Marie, Command::AcceptTrade { item: Item::Priviledge }).unwrap();
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Sarah, receiver: Player::Marie, trigger: PerspectiveTradeTriggerState::Monocle { faction: Some(Faction::Order), three_player_faction_index: None } });
-    assert_eq!(s.perspective(Player::Zacharias).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Sarah, receiver: Player::Marie, trigger: PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: None } });
-    s.apply_command(Player::Sarah, Command::DoneLookingAtThings).unwrap();
-    assert_eq!(s.perspective(Player::Marie).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Marie, receiver: Player::Sarah, trigger: PerspectiveTradeTriggerState::Priviledge { items: Some(vec![Item::BagKey, Item::Priviledge]) } });
-    assert_eq!(s.perspective(Player::Zacharias).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Marie, receiver: Player::Sarah, trigger: PerspectiveTradeTriggerState::Priviledge { items: None } });
-    s.apply_command(Player::Marie, Command::DoneLookingAtThings).unwrap();
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
+    s.game.p.player_mut(SARAH).items.push(Item::Monocle);
+    s.game.p.player_mut(MARIE).items.push(Item::Priviledge);
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::Monocle }).unwrap();
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::Priviledge }).unwrap();
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: SARAH, receiver: MARIE, trigger: PerspectiveTradeTriggerState::Monocle { faction: Some(Faction::Order), three_player_faction_index: None } });
+    assert_eq!(s.perspective(ZACHARIAS).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: SARAH, receiver: MARIE, trigger: PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: None } });
+    s.apply_command(SARAH, Command::DoneLookingAtThings).unwrap();
+    assert_eq!(s.perspective(MARIE).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: MARIE, receiver: SARAH, trigger: PerspectiveTradeTriggerState::Priviledge { items: Some(vec![Item::BagKey, Item::Priviledge]) } });
+    assert_eq!(s.perspective(ZACHARIAS).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: MARIE, receiver: SARAH, trigger: PerspectiveTradeTriggerState::Priviledge { items: None } });
+    s.apply_command(MARIE, Command::DoneLookingAtThings).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
 }
 
 #[test]
-fn trade_monocle_with_bag_causing_donation() {
+fn journal_keeps_what_only_one_player_saw() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Marie).items.push(Item::Monocle);
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::BagGoblet, Item::Key, Item::Key, Item::Key, Item::Goblet]);
+    s.game.p.player_mut(SARAH).items.push(Item::Monocle);
+    s.game.p.player_mut(MARIE).items.push(Item::Priviledge);
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::Monocle }).unwrap();
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::Priviledge }).unwrap();
+    s.apply_command(SARAH, Command::DoneLookingAtThings).unwrap();
+    s.apply_command(MARIE, Command::DoneLookingAtThings).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    s.apply_command(GUNDLA, Command::UseClairvoyant).unwrap();
+    s.apply_command(GUNDLA, Command::ClairvoyantSetItems { top_items: vec![Item::Dagger, Item::BlackPearl] }).unwrap();
+
+    let first = TurnIndex { round: 1, turn: 1 };
+    assert_eq!(s.perspective(SARAH).journal, [
+        JournalEntry { turn: first, log_position: 2, reveal: Reveal::Monocle { player: MARIE, faction: Faction::Order, three_player_faction_index: None } },
+    ]);
+    assert_eq!(s.perspective(MARIE).journal, [
+        JournalEntry { turn: first, log_position: 3, reveal: Reveal::Priviledge { player: SARAH, items: vec![Item::BagKey, Item::Priviledge] } },
+    ]);
+    assert_eq!(s.perspective(GUNDLA).journal, [
+        JournalEntry { turn: TurnIndex { round: 1, turn: 2 }, log_position: 4, reveal: Reveal::ItemStack { items: vec![Item::BlackPearl, Item::Dagger] } },
+    ]);
+    assert_eq!(s.perspective(ZACHARIAS).journal, []);
+}
+
+fn odds(view: &Perspective, p: SeatId) -> &PlayerOdds {
+    view.notebook.players.iter().find(|o| o.player == p).unwrap()
+}
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+}
+
+#[test]
+fn notebook_narrows_down_factions() {
+    let mut s = teststate();
+    let view = s.perspective(SARAH);
+    // one Order and two Brotherhood cards are left for the others
+    assert_eq!(view.notebook.faction_worlds, 3);
+    assert_eq!(odds(&view, SARAH).faction(), Some(Faction::Order));
+    for p in [GUNDLA, MARIE, ZACHARIAS] {
+        assert_close(odds(&view, p).order, 1.0 / 3.0);
+    }
+    let jobs_left = Job::all().count() - 1;
+    assert_eq!(view.notebook.job_worlds, (jobs_left * (jobs_left - 1) * (jobs_left - 2)) as u64);
+    assert_close(odds(&view, GUNDLA).job(Job::Clairvoyant), 1.0 / jobs_left as f64);
+    assert_eq!(odds(&view, GUNDLA).job(Job::Duelist), 0.0);
+
+    s.game.p.player_mut(SARAH).items.push(Item::Monocle);
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::Monocle }).unwrap();
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+    s.apply_command(SARAH, Command::DoneLookingAtThings).unwrap();
+    let view = s.perspective(SARAH);
+    assert_eq!(view.notebook.faction_worlds, 1);
+    assert_eq!(odds(&view, MARIE).faction(), Some(Faction::Order));
+    assert_eq!(odds(&view, GUNDLA).faction(), Some(Faction::Brotherhood));
+    assert_eq!(odds(&view, ZACHARIAS).faction(), Some(Faction::Brotherhood));
+    // everybody saw the monocle go to Marie
+    assert_eq!(odds(&s.perspective(ZACHARIAS), MARIE).item(Item::Monocle), 1.0);
+    assert_eq!(odds(&view, SARAH).items, [(Item::BagKey, 1.0), (Item::PoisonRing, 1.0)]);
+}
 
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Marie, item: Item::BagGoblet }).unwrap();
-    s.apply_command(Player::Marie, Command::AcceptTrade { item: Item::Monocle }).unwrap();
-    s.apply_command(Player::Sarah, Command::DonateItem { target: Player::Zacharias, item: Item::Key }).unwrap();
-    assert_eq!(s.perspective(Player::Marie).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Marie, receiver: Player::Sarah, trigger: PerspectiveTradeTriggerState::Monocle { faction: Some(Faction::Order), three_player_faction_index: None } });
-    assert_eq!(s.perspective(Player::Zacharias).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Marie, receiver: Player::Sarah, trigger: PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: None } });
-    s.apply_command(Player::Marie, Command::DoneLookingAtThings).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForEndTurn(Player::Sarah));
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
+#[test]
+fn notebook_counts_three_player_cards() {
+    let mut s = teststate_3p();
+    let view = s.perspective(SARAH);
+    // three Order and four Brotherhood cards, six of which were dealt to the others
+    assert_eq!(view.notebook.faction_worlds, 35);
+    for c in odds(&view, GUNDLA).order_cards.unwrap() {
+        assert_close(c, 3.0 / 7.0);
+    }
+
+    s.game.p.player_mut(SARAH).items.push(Item::Monocle);
+    s.apply_command(SARAH, Command::OfferTrade { target: GUNDLA, item: Item::Monocle }).unwrap();
+    s.apply_command(GUNDLA, Command::AcceptTrade { item: Item::BagGoblet }).unwrap();
+    s.apply_command(SARAH, Command::ThreePlayerSelectFactionIndex { index: 2 }).unwrap();
+    let view = s.perspective(SARAH);
+    let [first, second, third] = odds(&view, GUNDLA).order_cards.unwrap();
+    assert_eq!(third, 1.0);
+    assert_close(first, 1.0 / 3.0);
+    assert_close(second, 1.0 / 3.0);
+    // Gundla is on the side of the Order unless both other cards are Brotherhood cards
+    assert_close(odds(&view, GUNDLA).order, 1.0 - 4.0 / 6.0 * 3.0 / 5.0);
+    assert_eq!(s.perspective(MARIE).notebook.faction_worlds, 35);
+}
+
+#[test]
+fn trade_monocle_with_bag_causing_donation() {
+    let mut s = teststate();
+    s.game.p.player_mut(MARIE).items.push(Item::Monocle);
+    s.game.p.player_mut(SARAH).items.extend([Item::BagGoblet, Item::Key, Item::Key, Item::Key, Item::Goblet]);
+
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::BagGoblet }).unwrap();
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::Monocle }).unwrap();
+    s.apply_command(SARAH, Command::DonateItem { target: ZACHARIAS, item: Item::Key }).unwrap();
+    assert_eq!(s.perspective(MARIE).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: MARIE, receiver: SARAH, trigger: PerspectiveTradeTriggerState::Monocle { faction: Some(Faction::Order), three_player_faction_index: None } });
+    assert_eq!(s.perspective(ZACHARIAS).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: MARIE, receiver: SARAH, trigger: PerspectiveTradeTriggerState::Monocle { faction: None, three_player_faction_index: None } });
+    s.apply_command(MARIE, Command::DoneLookingAtThings).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForEndTurn(SARAH));
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
 }
 
 #[test]
 fn trade_priviledge() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.push(Item::Priviledge);
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Marie, item: Item::Priviledge }).unwrap();
-    s.apply_command(Player::Marie, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Sarah, receiver: Player::Marie, trigger: PerspectiveTradeTriggerState::Priviledge { items: Some(vec![Item::Priviledge]) } });
-    assert_eq!(s.perspective(Player::Zacharias).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Sarah, receiver: Player::Marie, trigger: PerspectiveTradeTriggerState::Priviledge { items: None } });
-    s.apply_command(Player::Sarah, Command::DoneLookingAtThings).unwrap();
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
+    s.game.p.player_mut(SARAH).items.push(Item::Priviledge);
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::Priviledge }).unwrap();
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: SARAH, receiver: MARIE, trigger: PerspectiveTradeTriggerState::Priviledge { items: Some(vec![Item::Priviledge]) } });
+    assert_eq!(s.perspective(ZACHARIAS).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: SARAH, receiver: MARIE, trigger: PerspectiveTradeTriggerState::Priviledge { items: None } });
+    s.apply_command(SARAH, Command::DoneLookingAtThings).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
 }
 
 #[test]
 fn trade_tome() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.push(Item::Tome);
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Marie, item: Item::Tome }).unwrap();
+    s.game.p.player_mut(SARAH).items.push(Item::Tome);
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::Tome }).unwrap();
 
-    assert_eq!(s.game.p.player(Player::Sarah).job, Job::Duelist);
-    assert_eq!(s.game.p.player(Player::Marie).job, Job::Thug);
+    assert_eq!(s.game.p.player(SARAH).job, Job::Duelist);
+    assert_eq!(s.game.p.player(MARIE).job, Job::Thug);
 
-    s.apply_command(Player::Marie, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
 
-    assert_eq!(s.game.p.player(Player::Sarah).job, Job::Thug);
-    assert_eq!(s.game.p.player(Player::Marie).job, Job::Duelist);
+    assert_eq!(s.game.p.player(SARAH).job, Job::Thug);
+    assert_eq!(s.game.p.player(MARIE).job, Job::Duelist);
 
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
 
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
 }
 
 #[test]
 fn trade_sextant() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.push(Item::Sextant);
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Marie, item: Item::Sextant }).unwrap();
-    s.apply_command(Player::Marie, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
-
-    s.apply_command(Player::Sarah, Command::SetSextantDirection { forward: true }).unwrap();
-
-    s.apply_command(Player::Zacharias, Command::SelectSextantItem { item: Item::Gloves }).unwrap();
-    s.apply_command(Player::Gundla, Command::SelectSextantItem { item: Item::BagGoblet }).unwrap();
-    s.apply_command(Player::Sarah, Command::SelectSextantItem { item: Item::BagKey }).unwrap();
-    s.apply_command(Player::Marie, Command::SelectSextantItem { item: Item::Sextant }).unwrap();
-
-    assert_eq!(s.game.p.player(Player::Zacharias).items, vec![Item::Sextant]);
-    assert_eq!(s.game.p.player(Player::Sarah).items, vec![Item::PoisonRing, Item::Gloves]);
-    assert_eq!(s.game.p.player(Player::Gundla).items, vec![Item::BagKey]);
-    assert_eq!(s.game.p.player(Player::Marie).items, vec![Item::BagGoblet]);
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
+    s.game.p.player_mut(SARAH).items.push(Item::Sextant);
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::Sextant }).unwrap();
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+
+    s.apply_command(SARAH, Command::SetSextantDirection { forward: true }).unwrap();
+
+    s.apply_command(ZACHARIAS, Command::SelectSextantItem { item: Item::Gloves }).unwrap();
+    s.apply_command(GUNDLA, Command::SelectSextantItem { item: Item::BagGoblet }).unwrap();
+    s.apply_command(SARAH, Command::SelectSextantItem { item: Item::BagKey }).unwrap();
+    s.apply_command(MARIE, Command::SelectSextantItem { item: Item::Sextant }).unwrap();
+
+    assert_eq!(s.game.p.player(ZACHARIAS).items, vec![Item::Sextant]);
+    assert_eq!(s.game.p.player(SARAH).items, vec![Item::PoisonRing, Item::Gloves]);
+    assert_eq!(s.game.p.player(GUNDLA).items, vec![Item::BagKey]);
+    assert_eq!(s.game.p.player(MARIE).items, vec![Item::BagGoblet]);
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
 }
 
 #[test]
 fn trade_coat() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.push(Item::Coat);
-    s.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Marie, item: Item::Coat }).unwrap();
-    s.apply_command(Player::Marie, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
-
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Sarah, receiver: Player::Marie, trigger: PerspectiveTradeTriggerState::Coat { available_jobs: Some(s.game.job_stack.clone()) } });
-    assert_eq!(s.perspective(Player::Zacharias).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: Player::Sarah, receiver: Player::Marie, trigger: PerspectiveTradeTriggerState::Coat { available_jobs: None } });
-
-    s.apply_command(Player::Sarah, Command::PickNewJob { job: Job::Doctor }).unwrap();
-    assert_eq!(s.game.p.player(Player::Sarah).job, Job::Doctor);
-    assert!(!s.game.p.player(Player::Sarah).job_is_visible);
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
+    s.game.p.player_mut(SARAH).items.push(Item::Coat);
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::Coat }).unwrap();
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: SARAH, receiver: MARIE, trigger: PerspectiveTradeTriggerState::Coat { available_jobs: Some(s.game.job_stack.clone()) } });
+    assert_eq!(s.perspective(ZACHARIAS).turn, PerspectiveTurnState::ResolvingTradeTrigger { giver: SARAH, receiver: MARIE, trigger: PerspectiveTradeTriggerState::Coat { available_jobs: None } });
+
+    s.apply_command(SARAH, Command::PickNewJob { job: Job::Doctor }).unwrap();
+    assert_eq!(s.game.p.player(SARAH).job, Job::Doctor);
+    assert!(!s.game.p.player(SARAH).job_is_visible);
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
 }
 
 #[test]
 fn victory_solo_bad() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key, Item::Key]);
-    s.apply_command(Player::Sarah, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: Vec::new() } }).unwrap();
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Key]);
+    s.apply_command(SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: Vec::new() } }).unwrap();
     assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Normal(Faction::Brotherhood) });
 }
 
 #[test]
 fn victory_solo_good() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key, Item::Key, Item::Key]);
-    s.apply_command(Player::Sarah, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: Vec::new() } }).unwrap();
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Key, Item::Key]);
+    s.apply_command(SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: Vec::new() } }).unwrap();
     assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Normal(Faction::Order) });
 }
 
 #[test]
 fn victory_team_bad() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key]);
-    s.game.p.player_mut(Player::Gundla).items.extend_from_slice(&[Item::Key, Item::Key]);
-    s.apply_command(Player::Sarah, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![Player::Gundla] } }).unwrap();
+    s.game.p.player_mut(SARAH).items.extend([Item::Key]);
+    s.game.p.player_mut(GUNDLA).items.extend([Item::Key, Item::Key]);
+    s.apply_command(SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![GUNDLA] } }).unwrap();
     assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Normal(Faction::Brotherhood) });
 }
 
 #[test]
 fn victory_team_bad2() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key]);
-    s.game.p.player_mut(Player::Marie).items.extend_from_slice(&[Item::Key]);
-    s.apply_command(Player::Sarah, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![Player::Marie] } }).unwrap();
+    s.game.p.player_mut(SARAH).items.extend([Item::Key]);
+    s.game.p.player_mut(MARIE).items.extend([Item::Key]);
+    s.apply_command(SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![MARIE] } }).unwrap();
     assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Normal(Faction::Brotherhood) });
 }
 
 #[test]
 fn victory_team_good() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key]);
-    s.game.p.player_mut(Player::Marie).items.extend_from_slice(&[Item::Key, Item::Key]);
-    s.apply_command(Player::Sarah, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![Player::Marie] } }).unwrap();
+    s.game.p.player_mut(SARAH).items.extend([Item::Key]);
+    s.game.p.player_mut(MARIE).items.extend([Item::Key, Item::Key]);
+    s.apply_command(SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![MARIE] } }).unwrap();
     assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Normal(Faction::Order) });
 }
 
+#[test]
+fn victory_evaluation() {
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.extend([Item::Key]);
+    s.game.p.player_mut(GUNDLA).items.extend([Item::Key, Item::Key]);
+    let flavor = VictoryFlavor::Normal { teammates: vec![GUNDLA] };
+    let evaluation = s.evaluate_victory(SARAH, &flavor).unwrap();
+    assert_eq!(evaluation, VictoryEvaluation {
+        announcer: SARAH,
+        flavor: flavor.clone(),
+        faction: Faction::Order,
+        required_items: vec![Item::Key],
+        needed_items: 3,
+        minority: false,
+        contributions: vec![
+            VictoryContribution { player: SARAH, items: vec![Item::Key], same_faction: true },
+            VictoryContribution { player: GUNDLA, items: vec![Item::Key, Item::Key], same_faction: false },
+        ],
+        black_pearl: false,
+        loge_coat: None,
+        winner: Some(WinningFaction::Normal(Faction::Brotherhood)),
+    });
+    assert_eq!(s.evaluate_victory(SARAH, &VictoryFlavor::Normal { teammates: vec![SARAH] }), Err(CommandError::InvalidTargetPlayer));
+
+    // dry runs don't change anything, and everybody gets to see the real thing at the end
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(SARAH));
+    s.apply_command(SARAH, Command::AnnounceVictory { flavor }).unwrap();
+    let turn = PerspectiveTurnState::GameOver { winner: WinningFaction::Normal(Faction::Brotherhood), evaluation: Some(evaluation), standings: Vec::new() };
+    assert_eq!(s.perspective(MARIE).turn, turn);
+    assert_eq!(s.spectate().turn, turn);
+}
+
+#[test]
+fn victory_evaluation_of_forbidden_announcements() {
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Goblet, Item::BlackPearl]);
+    s.game.item_stack.clear();
+    let evaluation = s.evaluate_victory(SARAH, &VictoryFlavor::Loge).unwrap();
+    assert_eq!(evaluation.required_items, [Item::Key, Item::BagKey, Item::Goblet, Item::BagGoblet]);
+    assert_eq!(evaluation.contributions[0].items, [Item::Key, Item::Goblet, Item::BagKey]);
+    assert!(evaluation.team_qualifies());
+    assert_eq!((evaluation.black_pearl, evaluation.loge_coat, evaluation.winner), (true, Some(false), None));
+}
+
+#[test]
+fn summary_reveals_everything_at_the_end() {
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.push(Item::Coat);
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::Coat }).unwrap();
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+    s.apply_command(SARAH, Command::PickNewJob { job: Job::Doctor }).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.perspective(GUNDLA).summary, None);
+
+    s.apply_command(GUNDLA, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: Vec::new() } }).unwrap();
+    let summary = s.summary().unwrap();
+    assert_eq!(summary.players[0], PlayerSummary {
+        player: SARAH,
+        faction: FactionKind::Normal(Faction::Order),
+        jobs: vec![Job::Duelist, Job::Doctor],
+        items: vec![Item::BagKey, Item::PoisonRing],
+    });
+    assert_eq!(summary.players.iter().map(|p| p.player).collect::<Vec<_>>(), [SARAH, GUNDLA, MARIE, ZACHARIAS]);
+    assert_eq!(summary.players[2].items, [Item::Coat]);
+    assert_eq!((summary.item_stack, summary.job_stack), (vec![Item::BlackPearl, Item::Dagger], vec![Job::Duelist]));
+    assert_eq!(s.perspective(ZACHARIAS).summary, s.summary());
+    assert_eq!(s.spectate().summary, s.summary());
+}
+
 #[test]
 fn victory_black_pearl() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key, Item::Key, Item::Key, Item::BlackPearl]);
-    assert_eq!(s.apply_command(Player::Sarah, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: Vec::new() } }), Err(CommandError::BlackPearl));
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Key, Item::Key, Item::BlackPearl]);
+    assert_eq!(s.apply_command(SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: Vec::new() } }), Err(CommandError::BlackPearl));
 }
 
 #[test]
 fn victory_loge_good() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key, Item::Goblet, Item::Key, Item::CoatOfArmorOfTheLoge]);
-    s.apply_command(Player::Sarah, Command::AnnounceVictory { flavor: VictoryFlavor::Loge }).unwrap();
-    assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Traitor(Player::Sarah) });
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Goblet, Item::Key, Item::CoatOfArmorOfTheLoge]);
+    s.apply_command(SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Loge }).unwrap();
+    assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Traitor(SARAH) });
 }
 
 #[test]
 fn victory_loge_good2() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key, Item::Goblet, Item::BagKey, Item::CoatOfArmorOfTheLoge]);
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Goblet, Item::BagKey, Item::CoatOfArmorOfTheLoge]);
     s.game.item_stack.clear();
-    s.apply_command(Player::Sarah, Command::AnnounceVictory { flavor: VictoryFlavor::Loge }).unwrap();
-    assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Traitor(Player::Sarah) });
+    s.apply_command(SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Loge }).unwrap();
+    assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Traitor(SARAH) });
 }
 
 #[test]
 fn victory_loge_bad() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key, Item::Goblet, Item::CoatOfArmorOfTheLoge]);
-    assert_eq!(s.apply_command(Player::Sarah, Command::AnnounceVictory { flavor: VictoryFlavor::Loge }), Err(CommandError::InvalidLogeVictory));
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Goblet, Item::CoatOfArmorOfTheLoge]);
+    assert_eq!(s.apply_command(SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Loge }), Err(CommandError::InvalidLogeVictory));
 }
 
 #[test]
 fn victory_loge_bad2() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key, Item::Goblet, Item::Key]);
-    assert_eq!(s.apply_command(Player::Sarah, Command::AnnounceVictory { flavor: VictoryFlavor::Loge }), Err(CommandError::InvalidLogeVictory));
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Goblet, Item::Key]);
+    assert_eq!(s.apply_command(SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Loge }), Err(CommandError::InvalidLogeVictory));
 }
 
 #[test]
 fn victory_loge_bad3() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key, Item::Goblet, Item::BagKey, Item::CoatOfArmorOfTheLoge]);
-    assert_eq!(s.apply_command(Player::Sarah, Command::AnnounceVictory { flavor: VictoryFlavor::Loge }), Err(CommandError::InvalidLogeVictory));
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Goblet, Item::BagKey, Item::CoatOfArmorOfTheLoge]);
+    assert_eq!(s.apply_command(SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Loge }), Err(CommandError::InvalidLogeVictory));
 }
 
 
 #[test]
 fn attack_tie() {
     let mut s = teststate();
-    s.apply_command(Player::Sarah, Command::InitiateAttack { player: Player::Zacharias }).unwrap();
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Gundla, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Marie, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Zacharias, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: false }).unwrap();
 
-    s.apply_command(Player::Gundla, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
-    s.apply_command(Player::Marie, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::Hypnotize { target: None }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Zacharias, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(GUNDLA, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(MARIE, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(ZACHARIAS, Command::ItemOrJob { buff: None, target: None }).unwrap();
 
-    assert_eq!(s.game.p.player(Player::Sarah).items, vec![Item::BagKey, Item::Dagger]);
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
+    assert_eq!(s.game.p.player(SARAH).items, vec![Item::BagKey, Item::Dagger]);
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
 }
 
 #[test]
 fn attack_win_creds() {
     let mut s = teststate();
-    s.apply_command(Player::Sarah, Command::InitiateAttack { player: Player::Zacharias }).unwrap();
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Gundla, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Marie, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Zacharias, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: false }).unwrap();
 
-    s.apply_command(Player::Gundla, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
-    s.apply_command(Player::Marie, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::Hypnotize { target: None }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Zacharias, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(GUNDLA, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(MARIE, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(ZACHARIAS, Command::ItemOrJob { buff: None, target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ClaimReward { steal_items: false }).unwrap();
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::FinishResolvingCredentials { target_faction: Faction::Brotherhood, target_job: Job::Hypnotist } });
-    assert_eq!(s.perspective(Player::Gundla).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: false, three_player_faction_index: None }) });
-    s.apply_command(Player::Sarah, Command::DoneLookingAtThings).unwrap();
+    s.apply_command(SARAH, Command::ClaimReward { steal_items: false }).unwrap();
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::FinishResolvingCredentials { target_faction: Faction::Brotherhood, target_job: Job::Hypnotist } });
+    assert_eq!(s.perspective(GUNDLA).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: false, three_player_faction_index: None }) });
+    s.apply_command(SARAH, Command::DoneLookingAtThings).unwrap();
 
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    assert_eq!(s.game.p.player(Player::Sarah).items, vec![Item::BagKey]);
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    assert_eq!(s.game.p.player(SARAH).items, vec![Item::BagKey]);
 }
 
 #[test]
 fn attack_win_creds_3p() {
     let mut s = teststate_3p();
-    s.apply_command(Player::Sarah, Command::InitiateAttack { player: Player::Marie }).unwrap();
+    s.apply_command(SARAH, Command::InitiateAttack { player: MARIE }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Gundla, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Marie, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
 
-    s.apply_command(Player::Gundla, Command::DeclareSupport { support: AttackSupport::Abstain }).unwrap();
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Abstain }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::Hypnotize { target: None }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(GUNDLA, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(MARIE, Command::ItemOrJob { buff: None, target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ClaimReward { steal_items: false }).unwrap();
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Marie, state: PerspectiveAttackState::FinishResolvingNeedFactionIndex });
-    assert_eq!(s.perspective(Player::Gundla).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Marie, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: false, three_player_faction_index: None }) });
-    s.apply_command(Player::Sarah, Command::ThreePlayerSelectFactionIndex { index: 0 }).unwrap();
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Marie, state: PerspectiveAttackState::FinishResolvingCredentials { target_faction: Faction::Order, target_job: Job::Thug } });
-    assert_eq!(s.perspective(Player::Gundla).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Marie, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: false, three_player_faction_index: Some(0) }) });
-    s.apply_command(Player::Sarah, Command::DoneLookingAtThings).unwrap();
+    s.apply_command(SARAH, Command::ClaimReward { steal_items: false }).unwrap();
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: MARIE, state: PerspectiveAttackState::FinishResolvingNeedFactionIndex });
+    assert_eq!(s.perspective(GUNDLA).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: MARIE, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: false, three_player_faction_index: None }) });
+    s.apply_command(SARAH, Command::ThreePlayerSelectFactionIndex { index: 0 }).unwrap();
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: MARIE, state: PerspectiveAttackState::FinishResolvingCredentials { target_faction: Faction::Order, target_job: Job::Thug } });
+    assert_eq!(s.perspective(GUNDLA).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: MARIE, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: false, three_player_faction_index: Some(0) }) });
+    s.apply_command(SARAH, Command::DoneLookingAtThings).unwrap();
 
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    assert_eq!(s.game.p.player(Player::Sarah).items, vec![Item::BagKey]);
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    assert_eq!(s.game.p.player(SARAH).items, vec![Item::BagKey]);
 }
 
 #[test]
 fn attack_win_steal_giveback() {
     let mut s = teststate();
-    s.apply_command(Player::Sarah, Command::InitiateAttack { player: Player::Zacharias }).unwrap();
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Gundla, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Marie, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Zacharias, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: false }).unwrap();
 
-    s.apply_command(Player::Gundla, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
-    s.apply_command(Player::Marie, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::Hypnotize { target: None }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Zacharias, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(GUNDLA, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(MARIE, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(ZACHARIAS, Command::ItemOrJob { buff: None, target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ClaimReward { steal_items: true }).unwrap();
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::FinishResolvingItems { target_items: vec![Item::Gloves] } });
-    assert_eq!(s.perspective(Player::Gundla).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: true, three_player_faction_index: None }) });
-    s.apply_command(Player::Sarah, Command::StealItem { item: Item::Gloves, give_back: Some(Item::BagKey) }).unwrap();
+    s.apply_command(SARAH, Command::ClaimReward { steal_items: true }).unwrap();
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::FinishResolvingItems { target_items: vec![Item::Gloves] } });
+    assert_eq!(s.perspective(GUNDLA).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: true, three_player_faction_index: None }) });
+    s.apply_command(SARAH, Command::StealItem { item: Item::Gloves, give_back: Some(Item::BagKey) }).unwrap();
 
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    assert_eq!(s.game.p.player(Player::Sarah).items, vec![Item::Gloves]);
-    assert_eq!(s.game.p.player(Player::Zacharias).items, vec![Item::BagKey]);
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    assert_eq!(s.game.p.player(SARAH).items, vec![Item::Gloves]);
+    assert_eq!(s.game.p.player(ZACHARIAS).items, vec![Item::BagKey]);
 }
 
 #[test]
 fn attack_win_steal() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Zacharias).items.push(Item::Coat);
+    s.game.p.player_mut(ZACHARIAS).items.push(Item::Coat);
 
-    s.apply_command(Player::Sarah, Command::InitiateAttack { player: Player::Zacharias }).unwrap();
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Gundla, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Marie, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Zacharias, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: false }).unwrap();
 
-    s.apply_command(Player::Gundla, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
-    s.apply_command(Player::Marie, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::Hypnotize { target: None }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Zacharias, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(GUNDLA, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(MARIE, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(ZACHARIAS, Command::ItemOrJob { buff: None, target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ClaimReward { steal_items: true }).unwrap();
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::FinishResolvingItems { target_items: vec![Item::Gloves, Item::Coat] } });
-    assert_eq!(s.perspective(Player::Gundla).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: true, three_player_faction_index: None }) });
-    s.apply_command(Player::Sarah, Command::StealItem { item: Item::Gloves, give_back: None }).unwrap();
+    s.apply_command(SARAH, Command::ClaimReward { steal_items: true }).unwrap();
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::FinishResolvingItems { target_items: vec![Item::Gloves, Item::Coat] } });
+    assert_eq!(s.perspective(GUNDLA).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: true, three_player_faction_index: None }) });
+    s.apply_command(SARAH, Command::StealItem { item: Item::Gloves, give_back: None }).unwrap();
 
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    assert_eq!(s.game.p.player(Player::Sarah).items, vec![Item::BagKey, Item::Gloves]);
-    assert_eq!(s.game.p.player(Player::Zacharias).items, vec![Item::Coat]);
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    assert_eq!(s.game.p.player(SARAH).items, vec![Item::BagKey, Item::Gloves]);
+    assert_eq!(s.game.p.player(ZACHARIAS).items, vec![Item::Coat]);
 }
 
 #[test]
 fn attack_lose_steal() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Zacharias).items.push(Item::Coat);
+    s.game.p.player_mut(ZACHARIAS).items.push(Item::Coat);
 
-    s.apply_command(Player::Sarah, Command::InitiateAttack { player: Player::Zacharias }).unwrap();
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Gundla, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Marie, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Zacharias, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: false }).unwrap();
 
-    s.apply_command(Player::Gundla, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
-    s.apply_command(Player::Marie, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::Hypnotize { target: None }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Zacharias, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(GUNDLA, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(MARIE, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(ZACHARIAS, Command::ItemOrJob { buff: None, target: None }).unwrap();
 
-    s.apply_command(Player::Zacharias, Command::ClaimReward { steal_items: true }).unwrap();
-    assert_eq!(s.perspective(Player::Zacharias).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::FinishResolvingItems { target_items: vec![Item::BagKey] } });
-    assert_eq!(s.perspective(Player::Gundla).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Defender, steal_items: true, three_player_faction_index: None }) });
+    s.apply_command(ZACHARIAS, Command::ClaimReward { steal_items: true }).unwrap();
+    assert_eq!(s.perspective(ZACHARIAS).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::FinishResolvingItems { target_items: vec![Item::BagKey] } });
+    assert_eq!(s.perspective(GUNDLA).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Defender, steal_items: true, three_player_faction_index: None }) });
 
-    assert_eq!(s.apply_command(Player::Zacharias, Command::StealItem { item: Item::BagKey, give_back: None }), Err(CommandError::InvalidStealCommand));
-    s.apply_command(Player::Zacharias, Command::StealItem { item: Item::BagKey, give_back: Some(Item::Gloves) }).unwrap();
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
+    assert_eq!(s.apply_command(ZACHARIAS, Command::StealItem { item: Item::BagKey, give_back: None }), Err(CommandError::InvalidStealCommand));
+    s.apply_command(ZACHARIAS, Command::StealItem { item: Item::BagKey, give_back: Some(Item::Gloves) }).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
 
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    assert_eq!(s.game.p.player(Player::Sarah).items, vec![Item::Gloves]);
-    assert_eq!(s.game.p.player(Player::Zacharias).items, vec![Item::Coat, Item::BagKey]);
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    assert_eq!(s.game.p.player(SARAH).items, vec![Item::Gloves]);
+    assert_eq!(s.game.p.player(ZACHARIAS).items, vec![Item::Coat, Item::BagKey]);
 }
 
 #[test]
 fn attack_win_steal_donate() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Zacharias).items.push(Item::Coat);
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key, Item::Key, Item::Key, Item::Goblet, Item::Goblet]);
+    s.game.p.player_mut(ZACHARIAS).items.push(Item::Coat);
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Key, Item::Key, Item::Goblet, Item::Goblet]);
 
-    s.apply_command(Player::Sarah, Command::InitiateAttack { player: Player::Zacharias }).unwrap();
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Gundla, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Marie, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Zacharias, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: false }).unwrap();
 
-    s.apply_command(Player::Gundla, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
-    s.apply_command(Player::Marie, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::Hypnotize { target: None }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Zacharias, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(GUNDLA, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(MARIE, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(ZACHARIAS, Command::ItemOrJob { buff: None, target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ClaimReward { steal_items: true }).unwrap();
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::FinishResolvingItems { target_items: vec![Item::Gloves, Item::Coat] } });
-    assert_eq!(s.perspective(Player::Gundla).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: true, three_player_faction_index: None }) });
-    s.apply_command(Player::Sarah, Command::StealItem { item: Item::Gloves, give_back: None }).unwrap();
+    s.apply_command(SARAH, Command::ClaimReward { steal_items: true }).unwrap();
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::FinishResolvingItems { target_items: vec![Item::Gloves, Item::Coat] } });
+    assert_eq!(s.perspective(GUNDLA).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: true, three_player_faction_index: None }) });
+    s.apply_command(SARAH, Command::StealItem { item: Item::Gloves, give_back: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::DonateItem { target: Player::Marie, item: Item::Key }).unwrap();
+    s.apply_command(SARAH, Command::DonateItem { target: MARIE, item: Item::Key }).unwrap();
 
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    assert_eq!(s.game.p.player(Player::Sarah).items, vec![Item::BagKey, Item::Key, Item::Key, Item::Goblet, Item::Goblet, Item::Gloves]);
-    assert_eq!(s.game.p.player(Player::Zacharias).items, vec![Item::Coat]);
-    assert_eq!(s.game.p.player(Player::Marie).items, vec![Item::PoisonRing, Item::Key]);
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    assert_eq!(s.game.p.player(SARAH).items, vec![Item::BagKey, Item::Key, Item::Key, Item::Goblet, Item::Goblet, Item::Gloves]);
+    assert_eq!(s.game.p.player(ZACHARIAS).items, vec![Item::Coat]);
+    assert_eq!(s.game.p.player(MARIE).items, vec![Item::PoisonRing, Item::Key]);
 }
 
 #[test]
 fn attack_win_steal_donate2() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Zacharias).items.push(Item::Coat);
-    s.game.p.player_mut(Player::Sarah).items.extend_from_slice(&[Item::Key, Item::Key, Item::Key, Item::Goblet, Item::Goblet]);
+    s.game.p.player_mut(ZACHARIAS).items.push(Item::Coat);
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Key, Item::Key, Item::Goblet, Item::Goblet]);
 
-    s.apply_command(Player::Sarah, Command::InitiateAttack { player: Player::Zacharias }).unwrap();
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Gundla, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Marie, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Zacharias, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: false }).unwrap();
 
-    s.apply_command(Player::Gundla, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
-    s.apply_command(Player::Marie, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::Hypnotize { target: None }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
-    s.apply_command(Player::Sarah, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    s.apply_command(Player::Zacharias, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(GUNDLA, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(MARIE, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(ZACHARIAS, Command::ItemOrJob { buff: None, target: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::ClaimReward { steal_items: true }).unwrap();
-    assert_eq!(s.perspective(Player::Sarah).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::FinishResolvingItems { target_items: vec![Item::Gloves, Item::Coat] } });
-    assert_eq!(s.perspective(Player::Gundla).turn, PerspectiveTurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: true, three_player_faction_index: None }) });
-    s.apply_command(Player::Sarah, Command::StealItem { item: Item::Gloves, give_back: None }).unwrap();
+    s.apply_command(SARAH, Command::ClaimReward { steal_items: true }).unwrap();
+    assert_eq!(s.perspective(SARAH).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::FinishResolvingItems { target_items: vec![Item::Gloves, Item::Coat] } });
+    assert_eq!(s.perspective(GUNDLA).turn, PerspectiveTurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: PerspectiveAttackState::Normal(AttackState::FinishResolving { winner: AttackWinner::Attacker, steal_items: true, three_player_faction_index: None }) });
+    s.apply_command(SARAH, Command::StealItem { item: Item::Gloves, give_back: None }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::DonateItem { target: Player::Marie, item: Item::Gloves }).unwrap();
+    s.apply_command(SARAH, Command::DonateItem { target: MARIE, item: Item::Gloves }).unwrap();
 
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    assert_eq!(s.game.p.player(Player::Sarah).items, vec![Item::BagKey, Item::Key, Item::Key, Item::Key, Item::Goblet, Item::Goblet]);
-    assert_eq!(s.game.p.player(Player::Zacharias).items, vec![Item::Coat]);
-    assert_eq!(s.game.p.player(Player::Marie).items, vec![Item::PoisonRing, Item::Gloves]);
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    assert_eq!(s.game.p.player(SARAH).items, vec![Item::BagKey, Item::Key, Item::Key, Item::Key, Item::Goblet, Item::Goblet]);
+    assert_eq!(s.game.p.player(ZACHARIAS).items, vec![Item::Coat]);
+    assert_eq!(s.game.p.player(MARIE).items, vec![Item::PoisonRing, Item::Gloves]);
 }
 
 #[test]
 fn attack_priest() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Sarah).items.push(Item::Coat);
-    s.game.p.player_mut(Player::Zacharias).job = Job::Priest;
+    s.game.p.player_mut(SARAH).items.push(Item::Coat);
+    s.game.p.player_mut(ZACHARIAS).job = Job::Priest;
+
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::InitiateAttack { player: Player::Zacharias }).unwrap();
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: true }).unwrap();
 
-    s.apply_command(Player::Sarah, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Gundla, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Marie, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Zacharias, Command::UsePriest { priest: true }).unwrap();
+    s.apply_command(SARAH, Command::PayPriest { item: Item::Coat }).unwrap();
+
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+    assert_eq!(s.game.p.player(SARAH).items, vec![Item::BagKey]);
+    assert_eq!(s.game.p.player(ZACHARIAS).items, vec![Item::Gloves, Item::Coat]);
+}
 
-    s.apply_command(Player::Sarah, Command::PayPriest { item: Item::Coat }).unwrap();
+#[test]
+fn turns_are_counted_and_logged() {
+    let mut s = teststate();
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    s.stamp_log(1000);
+    assert_eq!(s.turn_index(), TurnIndex { round: 1, turn: 2 });
+
+    // the clairvoyant in the main phase doesn't end the turn
+    s.apply_command(GUNDLA, Command::UseClairvoyant).unwrap();
+    s.apply_command(GUNDLA, Command::ClairvoyantSetItems { top_items: vec![Item::Dagger, Item::BlackPearl] }).unwrap();
+    assert_eq!(s.turn_index(), TurnIndex { round: 1, turn: 2 });
+    s.apply_command(GUNDLA, Command::OfferTrade { target: MARIE, item: Item::BagGoblet }).unwrap();
+    s.apply_command(MARIE, Command::RejectTrade).unwrap();
+    s.stamp_log(2000);
+    s.apply_command(MARIE, Command::Pass).unwrap();
+    s.apply_command(ZACHARIAS, Command::Pass).unwrap();
+    assert_eq!(s.turn_index(), TurnIndex { round: 2, turn: 5 });
+    assert_eq!(s.perspective(SARAH).turn_index, TurnIndex { round: 2, turn: 5 });
+
+    let log: Vec<_> = s.game.action_log.iter().map(|l| (l.seq, l.turn.turn, l.actor, l.timestamp)).collect();
+    assert_eq!(log, [
+        (0, 1, SARAH, Some(1000)),
+        (1, 2, GUNDLA, Some(2000)),
+        (2, 2, MARIE, Some(2000)),
+        (3, 3, MARIE, None),
+        (4, 4, ZACHARIAS, None),
+    ]);
+}
 
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
-    assert_eq!(s.game.p.player(Player::Sarah).items, vec![Item::BagKey]);
-    assert_eq!(s.game.p.player(Player::Zacharias).items, vec![Item::Gloves, Item::Coat]);
+#[test]
+fn pending_actors() {
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.push(Item::Sextant);
+    s.game.p.player_mut(ZACHARIAS).job = Job::Priest;
+    assert_eq!(s.pending_actors(), [SARAH]);
+
+    s.apply_command(SARAH, Command::OfferTrade { target: MARIE, item: Item::Sextant }).unwrap();
+    assert_eq!(s.pending_actors(), [MARIE]);
+    s.apply_command(MARIE, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+    s.apply_command(SARAH, Command::SetSextantDirection { forward: true }).unwrap();
+    s.apply_command(GUNDLA, Command::SelectSextantItem { item: Item::BagGoblet }).unwrap();
+    assert_eq!(s.pending_actors(), [SARAH, MARIE, ZACHARIAS]);
+    assert_eq!(s.spectate().pending_actors, [SARAH, MARIE, ZACHARIAS]);
+    assert_eq!(s.perspective(GUNDLA).pending_actors, [SARAH, MARIE, ZACHARIAS]);
+    s.apply_command(SARAH, Command::SelectSextantItem { item: Item::BagKey }).unwrap();
+    s.apply_command(MARIE, Command::SelectSextantItem { item: Item::Sextant }).unwrap();
+    s.apply_command(ZACHARIAS, Command::SelectSextantItem { item: Item::Gloves }).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
+
+    s.apply_command(GUNDLA, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    assert_eq!(s.pending_actors(), [GUNDLA, MARIE, ZACHARIAS]);
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: true }).unwrap();
+    assert_eq!(s.pending_actors(), [GUNDLA]);
 }
 
 /// Priest stops an attack where the attacker holds exactly one item.
@@ -637,51 +886,1093 @@ fn attack_priest() {
 fn attack_priest_single_item() {
     let mut s = teststate();
     // Sarah starts with exactly 1 item (BagKey) — do not add a second one
-    s.game.p.player_mut(Player::Zacharias).job = Job::Priest;
+    s.game.p.player_mut(ZACHARIAS).job = Job::Priest;
 
-    s.apply_command(Player::Sarah, Command::InitiateAttack { player: Player::Zacharias }).unwrap();
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
 
-    s.apply_command(Player::Sarah,     Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Gundla,    Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Marie,     Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Zacharias, Command::UsePriest { priest: true  }).unwrap();
+    s.apply_command(SARAH,     Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA,    Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE,     Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: true  }).unwrap();
 
     // Attack stopped, but no payment — turn goes straight to WaitingForEndTurn
-    assert_eq!(s.turn, TurnState::WaitingForEndTurn(Player::Sarah));
-    assert_eq!(s.game.p.player(Player::Sarah).items,     vec![Item::BagKey]);
-    assert_eq!(s.game.p.player(Player::Zacharias).items, vec![Item::Gloves]);
+    assert_eq!(s.turn, TurnState::WaitingForEndTurn(SARAH));
+    assert_eq!(s.game.p.player(SARAH).items,     vec![Item::BagKey]);
+    assert_eq!(s.game.p.player(ZACHARIAS).items, vec![Item::Gloves]);
 
-    s.apply_command(Player::Sarah, Command::Pass).unwrap();
-    assert_eq!(s.turn, TurnState::WaitingForQuickblink(Player::Gundla));
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
 }
 
 #[test]
 fn attack_poison_mixer() {
     let mut s = teststate();
-    s.game.p.player_mut(Player::Zacharias).job = Job::PoisonMixer;
-    s.game.p.player_mut(Player::Sarah).job = Job::PoisonMixer;
-    s.game.p.player_mut(Player::Marie).job = Job::PoisonMixer;
+    s.game.p.player_mut(ZACHARIAS).job = Job::PoisonMixer;
+    s.game.p.player_mut(SARAH).job = Job::PoisonMixer;
+    s.game.p.player_mut(MARIE).job = Job::PoisonMixer;
+
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
+
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: false }).unwrap();
+
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
+
+    assert_eq!(s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(SARAH) }), Err(CommandError::CantPoisonMixYourself));
+    assert_eq!(s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(ZACHARIAS) }), Err(CommandError::CantPoisonMixYourself));
+    assert_eq!(s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(MARIE) }), Err(CommandError::CantPoisonMixYourself));
+
+    assert_eq!(s.apply_command(ZACHARIAS, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(ZACHARIAS) }), Err(CommandError::CantPoisonMixYourself));
+
+    assert_eq!(s.apply_command(MARIE, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(MARIE) }), Err(CommandError::InvalidCommandInThisContext));
+    s.apply_command(MARIE, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(SARAH) }).unwrap();
+
+    assert_eq!(s.turn, TurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: AttackState::Resolving { winner: AttackWinner::Attacker } });
+}
+
+#[test]
+fn only_answers() {
+    let mut s = teststate();
+    s.game.p.player_mut(GUNDLA).job = Job::Priest;
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
+
+    // the attacker can't call off their own attack
+    assert_eq!(s.only_answer(SARAH), Some(Command::UsePriest { priest: false }));
+    assert_eq!(s.only_answer(GUNDLA), None);
+    let priest = AutoAnswers { priest: true, ..AutoAnswers::default() };
+    assert!(priest.allows(&Command::UsePriest { priest: false }));
+    assert!(!priest.allows(&Command::UsePriest { priest: true }));
+
+    for p in [SARAH, GUNDLA, MARIE, ZACHARIAS] {
+        s.apply_command(p, Command::UsePriest { priest: false }).unwrap();
+    }
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
+
+    // the Poison Ring only counts for the fighters
+    assert_eq!(s.only_answer(MARIE), Some(Command::ItemOrJob { buff: None, target: None }));
+    assert_eq!(s.only_answer(ZACHARIAS), None);
+    assert!(!priest.allows(&Command::ItemOrJob { buff: None, target: None }));
+    assert!(AutoAnswers { items_or_jobs: true, ..AutoAnswers::default() }.allows(&Command::ItemOrJob { buff: None, target: None }));
+}
+
+#[test]
+fn resigned_players_are_answered_for() {
+    let mut s = teststate();
+    s.apply_command(GUNDLA, Command::Resign).unwrap();
+    assert_eq!(s.apply_command(GUNDLA, Command::Pass), Err(CommandError::Resigned));
+    assert_eq!(s.apply_command(GUNDLA, Command::Resign), Err(CommandError::Resigned));
+    assert!(s.perspective(SARAH).players[1].resigned);
+    assert_eq!(s.game.action_log.last().unwrap().entry, ActionLogEntry::Resign { actor: GUNDLA });
+
+    // trades with them are rejected
+    s.apply_command(SARAH, Command::OfferTrade { target: GUNDLA, item: Item::BagKey }).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForEndTurn(SARAH));
+    // and their turn is passed right away
+    s.apply_command(SARAH, Command::Pass).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(MARIE));
+
+    // they don't help in fights either
+    s.apply_command(MARIE, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
+    for p in [MARIE, SARAH, ZACHARIAS] {
+        s.apply_command(p, Command::UsePriest { priest: false }).unwrap();
+    }
+    s.apply_command(SARAH, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    assert_eq!(s.turn, TurnState::Attacking { attacker: MARIE, defender: ZACHARIAS, state: AttackState::WaitingForHypnotizer(
+        [(SARAH, AttackSupport::Defend), (GUNDLA, AttackSupport::Abstain)].into_iter().collect()
+    ) });
+
+    // with only the Order left, nobody can stop them any more
+    s.apply_command(ZACHARIAS, Command::Resign).unwrap();
+    assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Normal(Faction::Order) });
+}
+
+#[test]
+fn resigning_cannot_be_undone() {
+    let mut s = State::new(seats(4), GameConfig::default(), 8);
+    let p = match s.turn {
+        TurnState::WaitingForQuickblink(p) => p,
+        _ => unreachable!(),
+    };
+    let other = s.game.p.next_player(p);
+    s.apply_command(other, Command::Resign).unwrap();
+    // resigning shows nothing, but it is for good
+    assert!(s.last_command_undoable);
+    assert_eq!(s.undo(other), Err(CommandError::CannotUndo));
+    assert!(s.has_resigned(other));
+}
+
+fn sealed_teststate() -> State {
+    let mut s = teststate();
+    s.game.config = Arc::new(GameConfig { sealed_answers: true, ..GameConfig::default() });
+    s
+}
+
+#[test]
+fn sealed_priest_answers() {
+    let mut s = sealed_teststate();
+    s.game.p.player_mut(GUNDLA).job = Job::Priest;
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
+
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: true }).unwrap();
+    assert_eq!(s.apply_command(GUNDLA, Command::UsePriest { priest: false }), Err(CommandError::YouHaveAlreadyPassed));
+    assert_eq!(s.legal_commands(GUNDLA), vec![]);
+    // nothing happened yet, and nobody can tell who answered
+    assert_eq!(s.turn, TurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: AttackState::WaitingForPriest { passed: HashSet::new() } });
+    assert_eq!(s.perspective(MARIE).pending_actors, vec![SARAH, GUNDLA, MARIE, ZACHARIAS]);
+    assert_eq!(s.perspective(MARIE).sealed_answer, None);
+    assert_eq!(s.perspective(GUNDLA).pending_actors, vec![SARAH, MARIE, ZACHARIAS]);
+    assert_eq!(s.perspective(GUNDLA).sealed_answer, Some(Command::UsePriest { priest: true }));
+
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: false }).unwrap();
+    // Sarah has nothing to pay the priest with
+    assert_eq!(s.turn, TurnState::WaitingForEndTurn(SARAH));
+    assert!(s.game.p.player(GUNDLA).job_is_visible);
+}
+
+#[test]
+fn sealed_items_and_jobs() {
+    let mut s = sealed_teststate();
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
+    for p in [SARAH, GUNDLA, MARIE, ZACHARIAS] {
+        s.apply_command(p, Command::UsePriest { priest: false }).unwrap();
+    }
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
+
+    s.apply_command(MARIE, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(ZACHARIAS, Command::ItemOrJob { buff: Some(BuffSource::Item(Item::Gloves)), target: None }).unwrap();
+    s.apply_command(GUNDLA, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
+
+    // both buffs count in seating order, and since somebody did something everybody gets to answer again
+    assert_eq!(s.turn, TurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: AttackState::ItemsOrJobs {
+        votes: [(GUNDLA, AttackSupport::Abstain), (MARIE, AttackSupport::Abstain)].into_iter().collect(),
+        passed: HashSet::new(),
+        buffs: vec![
+            Buff { user: SARAH, raw_score: 2, source: BuffSource::Job(Job::Duelist) },
+            Buff { user: ZACHARIAS, raw_score: -2, source: BuffSource::Item(Item::Gloves) },
+        ],
+    } });
+    assert_eq!(s.pending_actors(), vec![SARAH, GUNDLA, MARIE, ZACHARIAS]);
+
+    for p in [SARAH, GUNDLA, MARIE, ZACHARIAS] {
+        s.apply_command(p, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    }
+    // a tie, and Sarah used a job, so there is no end phase
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(GUNDLA));
+}
+
+#[test]
+fn buff_rules() {
+    let sources = Item::all().map(BuffSource::Item).chain(Job::all().map(BuffSource::Job));
+    for (source, rule) in sources.filter_map(|b| b.rule().map(|r| (b, r))) {
+        assert!(!rule.users.is_empty(), "{:?} can't be used by anybody", source);
+        for &(role, score) in rule.users {
+            match rule.effect {
+                // scores always help the user's side
+                BuffEffect::Score | BuffEffect::SendSupportersHome => match role {
+                    AttackRole::Attacker | AttackRole::AttackSupport(AttackSupport::Attack) => assert!(score > 0, "{:?}", source),
+                    AttackRole::Defender | AttackRole::AttackSupport(AttackSupport::Defend) => assert!(score < 0, "{:?}", source),
+                    AttackRole::AttackSupport(AttackSupport::Abstain) => panic!("{:?} can be used by abstaining supporters", source),
+                },
+                BuffEffect::EndFight | BuffEffect::DecideFight => assert_eq!(score, 0, "{:?}", source),
+            }
+        }
+        // only buffs that decide the fight need somebody to decide it for
+        assert_eq!(rule.target == BuffTarget::Fighter, rule.effect == BuffEffect::DecideFight, "{:?}", source);
+    }
+    assert_eq!(BuffSource::Item(Item::Key).rule(), None);
+    assert_eq!(BuffSource::Job(Job::Clairvoyant).rule(), None);
+
+    let mut s = teststate();
+    s.game.p.player_mut(GUNDLA).job = Job::PoisonMixer;
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
+    for p in [SARAH, GUNDLA, MARIE, ZACHARIAS] {
+        s.apply_command(p, Command::UsePriest { priest: false }).unwrap();
+    }
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Abstain }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
+
+    assert_eq!(s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: Some(ZACHARIAS) }), Err(CommandError::InvalidTargetPlayer));
+    assert_eq!(s.apply_command(GUNDLA, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: None }), Err(CommandError::InvalidCommandInThisContext));
+    assert_eq!(s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Item(Item::BagKey)), target: None }), Err(CommandError::InvalidCommandInThisContext));
+    // the ring counts for whichever fighter uses it, but not for supporters
+    assert_eq!(s.apply_command(MARIE, Command::ItemOrJob { buff: Some(BuffSource::Item(Item::PoisonRing)), target: None }), Err(CommandError::InvalidCommandInThisContext));
+    assert_eq!(s.legal_commands(GUNDLA), vec![
+        Command::ItemOrJob { buff: None, target: None },
+        Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(SARAH) },
+        Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(ZACHARIAS) },
+    ]);
+
+    // the Duelist sends everybody else home
+    s.apply_command(SARAH, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None }).unwrap();
+    let TurnState::Attacking { state: AttackState::ItemsOrJobs { votes, buffs, .. }, .. } = &s.turn else { panic!("{:?}", s.turn) };
+    assert!(votes.values().all(|&v| v == AttackSupport::Abstain));
+    assert_eq!(buffs, &vec![Buff { user: SARAH, raw_score: 2, source: BuffSource::Job(Job::Duelist) }]);
+}
+
+#[test]
+fn attack_outlook() {
+    let mut s = teststate();
+    s.game.p.player_mut(GUNDLA).job = Job::Doctor;
+    s.game.p.player_mut(MARIE).job = Job::PoisonMixer;
+
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
+    for p in [SARAH, GUNDLA, MARIE, ZACHARIAS] {
+        s.apply_command(p, Command::UsePriest { priest: false }).unwrap();
+    }
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    assert_eq!(s.attack_outlook(SARAH), None);
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
+    s.apply_command(ZACHARIAS, Command::ItemOrJob { buff: Some(BuffSource::Item(Item::Gloves)), target: None }).unwrap();
+
+    let tally = AttackTally { attack: 4, defense: 6 };
+    assert_eq!(tally.winner(), Some(AttackWinner::Defender));
+    // the duelist sends the supporters home, but the gloves stay
+    assert_eq!(s.attack_outlook(SARAH), Some(AttackOutlook { tally, options: vec![
+        BuffOption { buff: BuffSource::Job(Job::Duelist), target: None, outcome: BuffOutcome::Tally(AttackTally { attack: 4, defense: 4 }) },
+    ]}));
+    assert_eq!(s.attack_outlook(GUNDLA), Some(AttackOutlook { tally, options: vec![
+        BuffOption { buff: BuffSource::Job(Job::Doctor), target: None, outcome: BuffOutcome::Ended },
+    ]}));
+    assert_eq!(s.attack_outlook(MARIE), Some(AttackOutlook { tally, options: vec![
+        BuffOption { buff: BuffSource::Job(Job::PoisonMixer), target: Some(SARAH), outcome: BuffOutcome::Decided(AttackWinner::Attacker) },
+        BuffOption { buff: BuffSource::Job(Job::PoisonMixer), target: Some(ZACHARIAS), outcome: BuffOutcome::Decided(AttackWinner::Defender) },
+    ]}));
+    assert_eq!(s.attack_outlook(ZACHARIAS), Some(AttackOutlook { tally, options: Vec::new() }));
+    assert_eq!(s.perspective(MARIE).attack_outlook, s.attack_outlook(MARIE));
+
+    s.apply_command(SARAH, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    assert_eq!(s.attack_outlook(SARAH), None);
+}
+
+/// Every command shape, filled in with all seated players (plus one that isn't seated)
+/// and every item that is in play (plus one that isn't).
+fn command_pool(s: &State) -> Vec<Command> {
+    let seated: Vec<_> = s.game.p.ids().collect();
+    let mut players = seated.clone();
+    players.extend((0..).map(SeatId).find(|p| !seated.contains(p)));
+
+    let mut in_play: Vec<_> = s.game.item_stack.clone();
+    for p in s.game.p.states() {
+        in_play.extend(p.items.iter());
+    }
+    let mut items: Vec<_> = Item::all().filter(|i| in_play.contains(i)).collect();
+    items.extend(Item::all().find(|i| !in_play.contains(i)));
+
+    let mut pool = vec![
+        Command::Pass,
+        Command::UseClairvoyant,
+        Command::RejectTrade,
+        Command::DoneLookingAtThings,
+        Command::AnnounceVictory { flavor: VictoryFlavor::Loge },
+        Command::Hypnotize { target: None },
+        Command::ClairvoyantSetItems { top_items: Vec::new() },
+    ];
+    for mask in 0..(1usize << players.len()) {
+        let teammates = players.iter().enumerate().filter(|&(i, _)| mask & (1 << i) != 0).map(|(_, &t)| t).collect();
+        pool.push(Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates } });
+    }
+    for b in [false, true] {
+        pool.push(Command::SetSextantDirection { forward: b });
+        pool.push(Command::UsePriest { priest: b });
+        pool.push(Command::ClaimReward { steal_items: b });
+    }
+    for support in [AttackSupport::Attack, AttackSupport::Defend, AttackSupport::Abstain] {
+        pool.push(Command::DeclareSupport { support });
+    }
+    for index in 0..4 {
+        pool.push(Command::ThreePlayerSelectFactionIndex { index });
+    }
+    for job in Job::all() {
+        pool.push(Command::PickNewJob { job });
+    }
+    for &player in &players {
+        pool.push(Command::InitiateAttack { player });
+        pool.push(Command::Hypnotize { target: Some(player) });
+    }
+    let buffs = iter::once(None)
+        .chain(items.iter().map(|&i| Some(BuffSource::Item(i))))
+        .chain(Job::all().map(|j| Some(BuffSource::Job(j))));
+    for buff in buffs {
+        pool.push(Command::ItemOrJob { buff: buff.clone(), target: None });
+        for &target in &players {
+            pool.push(Command::ItemOrJob { buff: buff.clone(), target: Some(target) });
+        }
+    }
+    for &item in &items {
+        pool.push(Command::AcceptTrade { item });
+        pool.push(Command::SelectSextantItem { item });
+        pool.push(Command::PayPriest { item });
+        pool.push(Command::StealItem { item, give_back: None });
+        pool.push(Command::ClairvoyantSetItems { top_items: vec![item] });
+        for &item2 in &items {
+            pool.push(Command::StealItem { item, give_back: Some(item2) });
+            pool.push(Command::ClairvoyantSetItems { top_items: vec![item, item2] });
+            for &target in &players {
+                pool.push(Command::UseDiplomat { target, item, return_item: item2 });
+            }
+        }
+        for &target in &players {
+            pool.push(Command::OfferTrade { target, item });
+            pool.push(Command::DonateItem { target, item });
+        }
+    }
+    pool
+}
+
+/// Picks a random kind of command first, so that rare commands get their fair share.
+/// Victory announcements end the game, so they are left out.
+fn random_legal_move(s: &State, rng: &mut impl Rng) -> Option<(SeatId, Command)> {
+    let mut moves: Vec<Vec<(SeatId, Command)>> = Vec::new();
+    for p in s.game.p.ids() {
+        for c in s.legal_commands(p).into_iter().filter(|c| !matches!(c, Command::AnnounceVictory { .. })) {
+            match moves.iter_mut().find(|m| std::mem::discriminant(&m[0].1) == std::mem::discriminant(&c)) {
+                Some(m) => m.push((p, c)),
+                None => moves.push(vec![(p, c)]),
+            }
+        }
+    }
+    moves.choose(rng).and_then(|m| m.choose(rng)).cloned()
+}
 
-    s.apply_command(Player::Sarah, Command::InitiateAttack { player: Player::Zacharias }).unwrap();
+fn check_legal_commands(s: &State) {
+    let pool = command_pool(s);
+    for p in s.game.p.ids() {
+        let legal = s.legal_commands(p);
+        for c in &legal {
+            let mut s2 = s.clone();
+            assert_eq!(s2.apply_command(p, c.clone()), Ok(()), "{:?} should be able to {:?} in {:?}", p, c, s.turn);
+        }
+        // rejected commands leave the state alone, so one copy serves them all
+        let mut s2 = s.clone();
+        for c in pool.iter().filter(|c| !legal.contains(c)) {
+            assert!(s2.apply_command(p, c.clone()).is_err(), "{:?} should not be able to {:?} in {:?}", p, c, s.turn);
+        }
+    }
+}
+
+/// `teststate` with every trade trigger in play and a few job actions that need preparation
+fn teststate_loaded() -> State {
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.extend([Item::Monocle, Item::Sextant, Item::Key, Item::Key]);
+    s.game.p.player_mut(GUNDLA).items.extend([Item::Priviledge, Item::Coat, Item::Goblet]);
+    s.game.p.player_mut(MARIE).items.extend([Item::Tome, Item::BrokenMirror]);
+    s.game.p.player_mut(ZACHARIAS).items.extend([Item::Dagger, Item::Whip]);
+    s.game.p.player_mut(MARIE).job = Job::Diplomat;
+    s.game.p.player_mut(ZACHARIAS).job = Job::Priest;
+    s
+}
 
-    s.apply_command(Player::Sarah, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Gundla, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Marie, Command::UsePriest { priest: false }).unwrap();
-    s.apply_command(Player::Zacharias, Command::UsePriest { priest: false }).unwrap();
+#[test]
+fn legal_commands_match_apply_command() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let starts = [teststate(), teststate_3p(), teststate_loaded(), teststate_loaded()].into_iter()
+        .chain((3..=6).map(|n| State::new(seats(n), GameConfig::default(), n as u64)));
+    for mut s in starts {
+        for _ in 0..60 {
+            check_legal_commands(&s);
+            match random_legal_move(&s, &mut rng) {
+                Some((p, c)) => s.apply_command(p, c).unwrap(),
+                None => break,
+            }
+        }
+    }
+}
 
-    s.apply_command(Player::Gundla, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
-    s.apply_command(Player::Marie, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+#[test]
+fn legal_commands_attack() {
+    let mut s = teststate();
+    s.apply_command(SARAH, Command::InitiateAttack { player: ZACHARIAS }).unwrap();
+    assert_eq!(s.legal_commands(GUNDLA), vec![Command::UsePriest { priest: false }]);
+    assert_eq!(s.legal_commands(SARAH), vec![Command::UsePriest { priest: false }]);
+
+    s.apply_command(SARAH, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(GUNDLA, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(MARIE, Command::UsePriest { priest: false }).unwrap();
+    s.apply_command(ZACHARIAS, Command::UsePriest { priest: false }).unwrap();
+    assert_eq!(s.legal_commands(MARIE), vec![]);
+    assert_eq!(s.legal_commands(GUNDLA).len(), 3);
+
+    s.apply_command(GUNDLA, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    s.apply_command(MARIE, Command::DeclareSupport { support: AttackSupport::Defend }).unwrap();
+    s.apply_command(SARAH, Command::Hypnotize { target: None }).unwrap();
+
+    assert_eq!(s.legal_commands(SARAH), vec![
+        Command::ItemOrJob { buff: None, target: None },
+        Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Duelist)), target: None },
+    ]);
+    assert_eq!(s.legal_commands(ZACHARIAS), vec![
+        Command::ItemOrJob { buff: None, target: None },
+        Command::ItemOrJob { buff: Some(BuffSource::Item(Item::Gloves)), target: None },
+    ]);
+    assert_eq!(s.legal_commands(MARIE), vec![Command::ItemOrJob { buff: None, target: None }]);
+}
 
-    s.apply_command(Player::Sarah, Command::Hypnotize { target: None }).unwrap();
+#[test]
+fn legal_commands_trade_triggers() {
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.push(Item::Sextant);
+    let moves = [
+        (SARAH, Command::OfferTrade { target: MARIE, item: Item::Sextant }),
+        (MARIE, Command::AcceptTrade { item: Item::PoisonRing }),
+        (SARAH, Command::SetSextantDirection { forward: true }),
+        (ZACHARIAS, Command::SelectSextantItem { item: Item::Gloves }),
+    ];
+    for (p, c) in moves {
+        check_legal_commands(&s);
+        s.apply_command(p, c).unwrap();
+    }
+    check_legal_commands(&s);
 
-    assert_eq!(s.apply_command(Player::Sarah, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(Player::Sarah) }), Err(CommandError::CantPoisonMixYourself));
-    assert_eq!(s.apply_command(Player::Sarah, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(Player::Zacharias) }), Err(CommandError::CantPoisonMixYourself));
-    assert_eq!(s.apply_command(Player::Sarah, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(Player::Marie) }), Err(CommandError::CantPoisonMixYourself));
+    let mut s = teststate_3p();
+    s.game.p.player_mut(MARIE).items.push(Item::Monocle);
+    s.game.p.player_mut(SARAH).items.extend([Item::BagGoblet, Item::Key, Item::Key, Item::Key, Item::Goblet, Item::Goblet, Item::Goblet]);
+    let moves = [
+        (SARAH, Command::OfferTrade { target: MARIE, item: Item::BagGoblet }),
+        (MARIE, Command::AcceptTrade { item: Item::Monocle }),
+        (SARAH, Command::DonateItem { target: GUNDLA, item: Item::Key }),
+        (MARIE, Command::ThreePlayerSelectFactionIndex { index: 2 }),
+    ];
+    for (p, c) in moves {
+        check_legal_commands(&s);
+        s.apply_command(p, c).unwrap();
+    }
+    check_legal_commands(&s);
+}
 
-    assert_eq!(s.apply_command(Player::Zacharias, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(Player::Zacharias) }), Err(CommandError::CantPoisonMixYourself));
+#[test]
+fn same_seed_same_game() {
+    let deal = |seed| serde_json::to_string(&State::new(seats(5), GameConfig::default(), seed)).unwrap();
+    assert_eq!(deal(9), deal(9));
+    assert_ne!(deal(9), deal(10));
+}
 
-    assert_eq!(s.apply_command(Player::Marie, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(Player::Marie) }), Err(CommandError::InvalidCommandInThisContext));
-    s.apply_command(Player::Marie, Command::ItemOrJob { buff: Some(BuffSource::Job(Job::PoisonMixer)), target: Some(Player::Sarah) }).unwrap();
+#[test]
+fn clairvoyant_shuffle_is_seeded() {
+    let shuffled_stack = |seed, round_trip: bool| {
+        let mut s = teststate();
+        s.game.item_stack = vec![Item::BlackPearl, Item::Dagger, Item::Key, Item::Goblet, Item::Whip, Item::Tome, Item::Coat, Item::Sextant];
+        s.game.rng = GameRng::new(seed);
+        s.apply_command(SARAH, Command::Pass).unwrap();
+        s.apply_command(GUNDLA, Command::UseClairvoyant).unwrap();
+        if round_trip {
+            s = serde_json::from_str(&serde_json::to_string(&s).unwrap()).unwrap();
+        }
+        s.apply_command(GUNDLA, Command::ClairvoyantSetItems { top_items: vec![Item::Key, Item::Tome] }).unwrap();
+        assert_eq!(&s.game.item_stack[6..], &[Item::Tome, Item::Key]);
+        s.game.item_stack
+    };
+    assert_eq!(shuffled_stack(3, false), shuffled_stack(3, false));
+    assert_eq!(shuffled_stack(3, false), shuffled_stack(3, true));
+    assert_ne!(shuffled_stack(3, false), shuffled_stack(4, false));
+}
 
-    assert_eq!(s.turn, TurnState::Attacking { attacker: Player::Sarah, defender: Player::Zacharias, state: AttackState::Resolving { winner: AttackWinner::Attacker } });
+#[test]
+fn rng_position_is_stored_in_plain_numbers() {
+    let mut rng = GameRng::new(5);
+    rng.0.set_word_pos((7 << 64) | 3);
+    let json = serde_json::to_value(&rng).unwrap();
+    assert_eq!((&json["word_pos_low"], &json["word_pos_high"]), (&serde_json::json!(3), &serde_json::json!(7)));
+    assert_eq!(serde_json::from_value::<GameRng>(json).unwrap(), rng);
+}
+
+#[test]
+fn rejected_commands_change_nothing() {
+    fn assert_rejected(s: &mut State, actor: SeatId, c: Command, err: CommandError) {
+        let before = serde_json::to_string(&*s).unwrap();
+        assert_eq!(s.apply_command(actor, c), Err(err));
+        assert_eq!(serde_json::to_string(&*s).unwrap(), before);
+    }
+
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.push(Item::BlackPearl);
+    assert_rejected(&mut s, SARAH, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![MARIE] } }, CommandError::BlackPearl);
+
+    s.apply_command(SARAH, Command::OfferTrade { target: GUNDLA, item: Item::BagKey }).unwrap();
+    assert_rejected(&mut s, MARIE, Command::AcceptTrade { item: Item::PoisonRing }, CommandError::NotYourTurn);
+    assert_rejected(&mut s, GUNDLA, Command::AcceptTrade { item: Item::Dagger }, CommandError::InvalidItemError(Item::Dagger));
+    s.apply_command(GUNDLA, Command::RejectTrade).unwrap();
+    s.apply_command(SARAH, Command::Pass).unwrap();
+
+    s.apply_command(GUNDLA, Command::UseClairvoyant).unwrap();
+    assert_rejected(&mut s, GUNDLA, Command::ClairvoyantSetItems { top_items: vec![Item::Dagger, Item::Dagger] }, CommandError::InvalidItemError(Item::Dagger));
+}
+
+#[test]
+fn rejected_commands_change_nothing_fuzz() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let starts = [teststate(), teststate_3p(), teststate_loaded()].into_iter()
+        .chain((3..=6).map(|n| State::new(seats(n), GameConfig::default(), n as u64)));
+    for mut s in starts {
+        for _ in 0..60 {
+            let before = serde_json::to_string(&s).unwrap();
+            let pool = command_pool(&s);
+            let players: Vec<_> = s.game.p.ids().collect();
+            for _ in 0..200 {
+                let (&p, c) = (players.choose(&mut rng).unwrap(), pool.choose(&mut rng).unwrap());
+                let mut s2 = s.clone();
+                if s2.apply_command(p, c.clone()).is_err() {
+                    assert_eq!(serde_json::to_string(&s2).unwrap(), before, "rejected {:?} by {:?} changed the state", c, p);
+                }
+            }
+            match random_legal_move(&s, &mut rng) {
+                Some((p, c)) => s.apply_command(p, c).unwrap(),
+                None => break,
+            }
+        }
+    }
+}
+
+#[test]
+fn replay_rebuilds_every_position() {
+    let mut rng = StdRng::seed_from_u64(3);
+    for n in 3..=6 {
+        let mut s = State::new(seats(n), GameConfig::default(), 100 + n as u64);
+        let mut positions = vec![s.clone()];
+        for _ in 0..40 {
+            match random_legal_move(&s, &mut rng) {
+                Some((p, c)) => s.apply_command(p, c).unwrap(),
+                None => break,
+            }
+            positions.push(s.clone());
+        }
+
+        let setup = s.setup().unwrap();
+        assert_eq!(setup, &GameSetup { players: seats(n), config: GameConfig::default(), seed: 100 + n as u64 });
+        assert_eq!(s.history().len() + 1, positions.len());
+        for (i, position) in positions.iter().enumerate() {
+            let replayed = State::replay(setup, &s.history()[..i]).unwrap();
+            assert_eq!(&replayed, position);
+        }
+    }
+}
+
+#[test]
+fn replay_reports_rejected_command() {
+    let setup = GameSetup { players: seats(4), config: GameConfig::default(), seed: 5 };
+    let s = State::new(setup.players.clone(), setup.config.clone(), setup.seed);
+    let first = match s.turn {
+        TurnState::WaitingForQuickblink(p) => p,
+        _ => unreachable!(),
+    };
+    let second = s.game.p.next_player(first);
+    let commands = [(first, Command::Pass), (first, Command::Pass)];
+    assert_eq!(State::replay(&setup, &commands).unwrap_err(), ReplayError { index: 1, error: CommandError::NotYourTurn });
+    assert!(State::replay(&setup, &[(first, Command::Pass), (second, Command::Pass)]).is_ok());
+}
+
+#[test]
+fn trade_offers_cannot_be_undone() {
+    let mut s = State::new(seats(4), GameConfig::default(), 8);
+    let p = match s.turn {
+        TurnState::WaitingForQuickblink(p) => p,
+        _ => unreachable!(),
+    };
+    let target = s.game.p.next_player(p);
+    let item = s.game.p.player(p).items.iter().next().unwrap();
+    assert_eq!(s.undo(p), Err(CommandError::CannotUndo));
+
+    // the target already saw the offered item
+    s.apply_command(p, Command::OfferTrade { target, item }).unwrap();
+    assert!(matches!(s.perspective(target).turn, PerspectiveTurnState::TradePending { item: Some(i), .. } if i == item));
+    assert_eq!(s.undo(target), Err(CommandError::CannotUndo));
+    assert_eq!(s.undo(p), Err(CommandError::CannotUndo));
+}
+
+#[test]
+fn undo_only_what_revealed_nothing() {
+    let mut rng = StdRng::seed_from_u64(4);
+    let mut undone = 0;
+    for n in 3..=6 {
+        let mut s = State::new(seats(n), GameConfig::default(), 200 + n as u64);
+        for _ in 0..60 {
+            let before = s.clone();
+            let (p, c) = match random_legal_move(&s, &mut rng) {
+                Some(m) => m,
+                None => break,
+            };
+            s.apply_command(p, c.clone()).unwrap();
+            for other in s.game.p.ids().filter(|&o| o != p) {
+                assert_eq!(s.clone().undo(other), Err(CommandError::CannotUndo));
+            }
+
+            let reveals = match &c {
+                Command::UseClairvoyant | Command::UseDiplomat { .. } | Command::AcceptTrade { .. } | Command::ClaimReward { .. } | Command::StealItem { .. }
+                | Command::PickNewJob { .. } | Command::DonateItem { .. } | Command::PayPriest { .. } | Command::ItemOrJob { buff: Some(_), .. }
+                | Command::OfferTrade { .. } => true,
+                Command::UsePriest { priest } => *priest,
+                Command::Hypnotize { target } => target.is_some(),
+                _ => false,
+            };
+            let mut s2 = s.clone();
+            if reveals {
+                assert_eq!(s2.undo(p), Err(CommandError::CannotUndo), "{:?} by {:?} should not be undoable", c, p);
+            } else if s2.undo(p).is_ok() {
+                assert_eq!(s2, before);
+                undone += 1;
+            }
+        }
+    }
+    assert!(undone > 20);
+}
+
+#[test]
+fn invariants_hold_during_games() {
+    let mut rng = StdRng::seed_from_u64(5);
+    for n in 3..=GameConfig::default().jobs.len() {
+        let mut s = State::new(seats(n), GameConfig::default(), 300 + n as u64);
+        for _ in 0..60 {
+            assert_eq!(s.check_invariants(), Ok(()));
+            match random_legal_move(&s, &mut rng) {
+                Some((p, c)) => s.apply_command(p, c).unwrap(),
+                None => break,
+            }
+        }
+    }
+}
+
+#[test]
+fn invariant_violations() {
+    let s = State::new(seats(4), GameConfig::default(), 6);
+    assert_eq!(s.check_invariants(), Ok(()));
+    let first = s.game.p.ids().next().unwrap();
+
+    let mut broken = s.clone();
+    let item = broken.game.item_stack.pop().unwrap();
+    assert_eq!(broken.check_invariants(), Err(InvariantViolation::ItemCount { item, expected: 1, found: 0 }));
+    broken.game.p.player_mut(first).items.push(item);
+    broken.game.p.player_mut(first).items.push(Item::Key);
+    assert_eq!(broken.check_invariants(), Err(InvariantViolation::ItemCount { item: Item::Key, expected: 3, found: 4 }));
+
+    let mut broken = s.clone();
+    let job = broken.game.p.player(first).job;
+    broken.game.p.player_mut(first).job = if job == Job::Thug { Job::Doctor } else { Job::Thug };
+    assert!(matches!(broken.check_invariants(), Err(InvariantViolation::JobCount { .. })));
+
+    let mut broken = s.clone();
+    broken.game.p.player_mut(first).items = Inventory::new();
+    assert_eq!(broken.check_invariants(), Err(InvariantViolation::NoItems(first)));
+
+    let mut broken = s.clone();
+    broken.game.p.player_mut(first).items.extend(broken.game.item_stack.drain(..6));
+    assert_eq!(broken.check_invariants(), Err(InvariantViolation::InventoryLimit(first)));
+    broken.turn = TurnState::DonatingItem { donor: first, followup: FollowupState::end_phase(first) };
+    assert_eq!(broken.check_invariants(), Ok(()));
+
+    let mut broken = s.clone();
+    let missing = (0..).map(SeatId).find(|&p| !s.game.p.contains(p)).unwrap();
+    broken.turn = TurnState::TradePending { offerer: first, target: missing, item: Item::Key };
+    assert_eq!(broken.check_invariants(), Err(InvariantViolation::UnseatedPlayer(missing)));
+}
+
+#[test]
+fn donation_must_not_overfill_receiver() {
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Key, Item::Key, Item::Goblet, Item::Goblet, Item::Goblet]);
+    s.game.p.player_mut(GUNDLA).items.extend([Item::Whip, Item::Tome, Item::Coat, Item::Sextant, Item::Monocle]);
+    s.turn = TurnState::DonatingItem { donor: SARAH, followup: FollowupState::end_phase(SARAH) };
+    assert_eq!(s.apply_command(SARAH, Command::DonateItem { target: GUNDLA, item: Item::Key }), Err(CommandError::InvalidTargetPlayer));
+    s.apply_command(SARAH, Command::DonateItem { target: MARIE, item: Item::Key }).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForEndTurn(SARAH));
+}
+
+#[test]
+fn bag_drawn_after_other_trigger_is_donated_by_its_giver() {
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.push(Item::Coat);
+    s.game.p.player_mut(GUNDLA).items.extend([Item::Key, Item::Key, Item::Goblet, Item::Goblet, Item::Whip]);
+    s.apply_command(SARAH, Command::OfferTrade { target: GUNDLA, item: Item::Coat }).unwrap();
+    s.apply_command(GUNDLA, Command::AcceptTrade { item: Item::BagGoblet }).unwrap();
+    s.apply_command(SARAH, Command::PickNewJob { job: Job::Doctor }).unwrap();
+    assert_eq!(s.game.p.player(GUNDLA).items.len(), 7);
+    assert_eq!(s.turn, TurnState::DonatingItem { donor: GUNDLA, followup: FollowupState::end_phase(SARAH) });
+}
+
+#[test]
+fn coat_without_spare_jobs() {
+    let mut s = teststate();
+    s.game.job_stack.clear();
+    s.game.p.player_mut(SARAH).items.push(Item::Coat);
+    s.game.p.player_mut(GUNDLA).items.push(Item::Goblet);
+    s.apply_command(SARAH, Command::OfferTrade { target: GUNDLA, item: Item::Coat }).unwrap();
+    s.apply_command(GUNDLA, Command::AcceptTrade { item: Item::Goblet }).unwrap();
+    assert_eq!(s.turn, TurnState::WaitingForEndTurn(SARAH));
+}
+
+#[test]
+fn determinized_states_look_like_the_view() {
+    let mut rng = StdRng::seed_from_u64(8);
+    for n in 3..=GameConfig::default().jobs.len() {
+        let mut s = State::new(seats(n), GameConfig::default(), n as u64);
+        let mut agent = bot::RandomAgent::new(n as u64);
+        for _ in 0..200 {
+            for p in s.game.p.ids() {
+                let view = s.perspective(p);
+                let guess = bot::determinize(&view, &mut rng);
+                guess.check_invariants().unwrap();
+                assert_eq!(guess.perspective(p), view);
+                assert_eq!(bot::legal_commands(&view).is_empty(), s.legal_commands(p).is_empty());
+            }
+            let actor = match s.game.p.ids().find(|&p| !s.legal_commands(p).is_empty()) {
+                Some(p) => p,
+                None => break,
+            };
+            let c = agent.choose(&s.perspective(actor));
+            s.apply_command(actor, c).unwrap();
+        }
+    }
+}
+
+#[test]
+fn bots_finish_games() {
+    for n in 3..=GameConfig::default().jobs.len() {
+        for seed in 0..5 {
+            let mut s = State::new(seats(n), GameConfig::default(), seed);
+            let mut agents: HashMap<SeatId, Box<dyn Agent>> = s.game.p.ids().enumerate().map(|(i, p)| {
+                let agent: Box<dyn Agent> = if i % 3 == 2 { Box::new(bot::RandomAgent::new(seed + i as u64)) } else { Box::new(bot::HeuristicAgent::new(seed + i as u64)) };
+                (p, agent)
+            }).collect();
+            bot::play_bots(&mut s, &mut agents, 1000).unwrap();
+            s.check_invariants().unwrap();
+        }
+    }
+}
+
+#[test]
+fn heuristic_bot_gets_rid_of_black_pearl() {
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.push(Item::BlackPearl);
+    s.game.item_stack.retain(|&i| i != Item::BlackPearl);
+    let c = bot::HeuristicAgent::new(0).choose(&s.perspective(SARAH));
+    assert!(matches!(c, Command::OfferTrade { item: Item::BlackPearl, .. }), "{:?}", c);
+}
+
+#[test]
+fn heuristic_bot_announces_victory() {
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Key, Item::Key]);
+    let c = bot::HeuristicAgent::new(0).choose(&s.perspective(SARAH));
+    assert_eq!(c, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![] } });
+    s.apply_command(SARAH, c).unwrap();
+    assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Normal(Faction::Order) });
+}
+
+#[test]
+fn heuristic_bot_waits_for_the_configured_victory_items() {
+    let mut s = teststate();
+    s.game.config = Arc::new(GameConfig { victory_items: 4, ..GameConfig::default() });
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Key, Item::Key]);
+    let c = bot::HeuristicAgent::new(0).choose(&s.perspective(SARAH));
+    assert!(!matches!(c, Command::AnnounceVictory { .. }), "{:?}", c);
+
+    // the bag counts once the stack is empty
+    s.game.item_stack.clear();
+    let c = bot::HeuristicAgent::new(0).choose(&s.perspective(SARAH));
+    assert_eq!(c, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![] } });
+    s.apply_command(SARAH, c).unwrap();
+    assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Normal(Faction::Order) });
+}
+
+#[test]
+fn heuristic_bot_attacks_its_attacker() {
+    let mut s = teststate();
+    s.game.log(GUNDLA, ActionLogEntry::Attack { attacker: GUNDLA, target: SARAH });
+    let c = bot::HeuristicAgent::new(0).choose(&s.perspective(SARAH));
+    assert_eq!(c, Command::InitiateAttack { player: GUNDLA });
+}
+
+#[test]
+fn ismcts_bot_takes_a_sure_win() {
+    let mut s = teststate();
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Key, Item::Key]);
+    let c = bot::IsmctsAgent::new(0, 200).choose(&s.perspective(SARAH));
+    assert_eq!(c, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![] } });
+}
+
+#[test]
+fn ismcts_bot_plays_along() {
+    let mut s = State::new(seats(4), GameConfig::default(), 9);
+    let mut agents: HashMap<SeatId, Box<dyn Agent>> = s.game.p.ids().map(|p| {
+        let agent: Box<dyn Agent> = if p == SARAH { Box::new(bot::IsmctsAgent::new(1, 30)) } else { Box::new(bot::HeuristicAgent::new(2)) };
+        (p, agent)
+    }).collect();
+    bot::play_bots(&mut s, &mut agents, 60).unwrap();
+    s.check_invariants().unwrap();
+}
+
+fn small_config() -> GameConfig {
+    GameConfig {
+        jobs: vec![Job::Thug, Job::Bodyguard, Job::Priest, Job::Hypnotist, Job::Doctor],
+        start_items: vec![Item::BlackPearl, Item::Dagger, Item::Monocle],
+        stack_items: vec![Item::Coat, Item::Sextant],
+        keys: 2,
+        goblets: 2,
+        inventory_limits: [(4, 4)].into_iter().collect(),
+        victory_items: 2,
+        minority_victory_items: 1,
+        loge_victory: false,
+        sealed_answers: false,
+        round_limit: RoundLimit::default(),
+    }
+}
+
+#[test]
+fn config_decides_what_is_dealt() {
+    let s = State::new(seats(4), small_config(), 3);
+    s.check_invariants().unwrap();
+    assert!(s.game.p.states().all(|p| small_config().jobs.contains(&p.job)));
+    assert_eq!(s.game.job_stack.len(), 1);
+    assert_eq!(s.game.item_stack.len(), 2 + 2 + 3 + 2 + 2 - 4);
+    assert_eq!(s.perspective(MARIE).config, small_config());
+    assert_eq!(s.spectate().config, small_config());
+
+    let mut s = State::new(seats(4), small_config(), 4);
+    let mut agent = bot::RandomAgent::new(4);
+    for _ in 0..300 {
+        let actor = match s.game.p.ids().find(|&p| !s.legal_commands(p).is_empty()) {
+            Some(p) => p,
+            None => break,
+        };
+        let c = agent.choose(&s.perspective(actor));
+        s.apply_command(actor, c).unwrap();
+        s.check_invariants().unwrap();
+    }
+}
+
+#[test]
+fn config_victory_rules() {
+    let mut s = teststate();
+    s.game.config = Arc::new(small_config());
+    s.game.p.player_mut(SARAH).items.extend([Item::Key, Item::Goblet, Item::CoatOfArmorOfTheLoge]);
+    let loge = Command::AnnounceVictory { flavor: VictoryFlavor::Loge };
+    assert!(!s.legal_commands(SARAH).contains(&loge));
+    assert_eq!(s.apply_command(SARAH, loge), Err(CommandError::LogeVictoryDisabled));
+
+    // a single key is not enough, but with the bags out of the stack two items do
+    let solo = Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![] } };
+    let mut lost = s.clone();
+    lost.apply_command(SARAH, solo.clone()).unwrap();
+    assert_eq!(lost.turn, TurnState::GameOver { winner: WinningFaction::Normal(Faction::Brotherhood) });
+    s.game.item_stack.clear();
+    s.apply_command(SARAH, solo).unwrap();
+    assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Normal(Faction::Order) });
+}
+
+#[test]
+fn config_inventory_limit() {
+    let mut s = teststate();
+    s.game.config = Arc::new(small_config());
+    s.game.p.player_mut(GUNDLA).items.extend([Item::Key, Item::Key, Item::Goblet]);
+    s.turn = TurnState::DonatingItem { donor: SARAH, followup: FollowupState::end_phase(SARAH) };
+    assert_eq!(s.apply_command(SARAH, Command::DonateItem { target: GUNDLA, item: Item::BagKey }), Err(CommandError::InvalidTargetPlayer));
+}
+
+#[test]
+fn invalid_configs() {
+    let config = small_config();
+    assert_eq!(check_config(&config, 4), Ok(()));
+    assert_eq!(check_config(&config, 6), Err(ConfigError::NotEnoughJobs { players: 6, available: 5 }));
+    assert_eq!(check_config(&GameConfig { jobs: vec![Job::Thug, Job::Thug, Job::Priest, Job::Doctor], ..small_config() }, 4), Err(ConfigError::DuplicateJob(Job::Thug)));
+    assert_eq!(check_config(&GameConfig { stack_items: vec![Item::Key], ..small_config() }, 4), Err(ConfigError::InvalidItem(Item::Key)));
+    assert_eq!(check_config(&GameConfig { start_items: vec![Item::Dagger, Item::Dagger], ..small_config() }, 4), Err(ConfigError::InvalidItem(Item::Dagger)));
+    assert_eq!(check_config(&GameConfig { keys: 0, goblets: 1, start_items: vec![], ..small_config() }, 4), Err(ConfigError::NotEnoughStartItems { players: 4, needed: 2, available: 1 }));
+    assert_eq!(check_config(&GameConfig { inventory_limits: [(4, 2)].into_iter().collect(), ..small_config() }, 4), Err(ConfigError::InventoryLimitTooLow { players: 4, limit: 2 }));
+    assert_eq!(check_config(&GameConfig { goblets: 1000, ..small_config() }, 4), Err(ConfigError::TooManyCopies(Item::Goblet)));
+    let round_limit = RoundLimit { rounds: Some(0), ..RoundLimit::default() };
+    assert_eq!(check_config(&GameConfig { round_limit, ..small_config() }, 4), Err(ConfigError::NoRounds));
+    for n in 3..=GameConfig::default().jobs.len() {
+        assert_eq!(check_config(&GameConfig::default(), n), Ok(()));
+        assert_eq!(GameConfig::default().inventory_limit(n), inventory_limit(n));
+    }
+}
+
+#[test]
+fn games_stored_without_config_use_the_default() {
+    let mut s = State::new(seats(4), GameConfig::default(), 11);
+    let actor = s.game.p.ids().find(|&p| !s.legal_commands(p).is_empty()).unwrap();
+    s.apply_command(actor, Command::Pass).unwrap();
+    let mut json = serde_json::to_value(&s).unwrap();
+    json["game"].as_object_mut().unwrap().remove("config");
+    json["setup"].as_object_mut().unwrap().remove("config");
+    assert_eq!(serde_json::from_value::<State>(json).unwrap(), s);
+}
+
+#[test]
+fn games_stored_with_characters_get_seats() {
+    let stored = r#"{
+        "game": {
+            "p": { "players": [
+                ["Sarah", { "faction": { "Normal": "Order" }, "job": "Duelist", "job_is_visible": false, "items": ["BagKey"] }],
+                ["Gundla", { "faction": { "Normal": "Brotherhood" }, "job": "Clairvoyant", "job_is_visible": false, "items": ["BagGoblet"] }],
+                ["Marie", { "faction": { "Normal": "Order" }, "job": "Thug", "job_is_visible": false, "items": ["PoisonRing"] }],
+                ["Zacharias", { "faction": { "Normal": "Brotherhood" }, "job": "Hypnotist", "job_is_visible": false, "items": ["Gloves"] }]
+            ] },
+            "item_stack": ["BlackPearl", "Dagger"],
+            "job_stack": ["Doctor"],
+            "action_log": [{ "attack": { "attacker": "Sarah", "target": "Zacharias" } }]
+        },
+        "turn": { "Attacking": { "attacker": "Sarah", "defender": "Zacharias", "state": { "DeclaringSupport": { "Marie": "attack" } } } },
+        "setup": { "players": ["Marie", "Gundla", "Sarah", "Zacharias"], "seed": 5 },
+        "history": [["Sarah", { "action": "initiate_attack", "player": "Zacharias" }]]
+    }"#;
+    let s: State = serde_json::from_str(stored).unwrap();
+    let view = s.perspective(SARAH);
+    let names: Vec<_> = view.players.iter().map(|p| (p.player, p.name.as_str(), p.character)).collect();
+    assert_eq!(names, [
+        (SARAH, "Sarah Mac Mullin", Some(Character::Sarah)),
+        (GUNDLA, "Gundla von Hochberg", Some(Character::Gundla)),
+        (MARIE, "Marie Sauniére", Some(Character::Marie)),
+        (ZACHARIAS, "Bruder Zacharias", Some(Character::Zacharias)),
+    ]);
+    let entry = ActionLogEntry::Attack { attacker: SARAH, target: ZACHARIAS };
+    assert_eq!(view.action_log, [LoggedAction { seq: 0, turn: TurnIndex::default(), actor: SARAH, timestamp: None, entry }]);
+    assert_eq!(s.turn, TurnState::Attacking { attacker: SARAH, defender: ZACHARIAS, state: AttackState::DeclaringSupport([(MARIE, AttackSupport::Attack)].into_iter().collect()) });
+    assert_eq!(s.setup().unwrap().players[3], Seat { id: ZACHARIAS, name: "Bruder Zacharias".to_string(), character: Some(Character::Zacharias) });
+    assert_eq!(s.history(), [(SARAH, Command::InitiateAttack { player: ZACHARIAS })]);
+
+    // and from then on they're stored with seats
+    let restored: State = serde_json::from_str(&serde_json::to_string(&s).unwrap()).unwrap();
+    assert_eq!(restored, s);
+}
+
+#[test]
+fn players_are_named_freely() {
+    let players = vec![
+        Seat { id: SeatId(7), name: "Alice".to_string(), character: None },
+        Seat { id: SeatId(3), name: "Bob".to_string(), character: Some(Character::Juan) },
+        Seat { id: SeatId(12), name: "Carol".to_string(), character: None },
+    ];
+    let s = State::new(players.clone(), GameConfig::default(), 1);
+    let view = s.spectate();
+    for seat in players {
+        let p = view.players.iter().find(|p| p.player == seat.id).unwrap();
+        assert_eq!((&p.name, p.character), (&seat.name, seat.character));
+    }
+}
+
+#[test]
+fn inventories_only_count_items() {
+    let mut items = Inventory::from([Item::Key, Item::Dagger, Item::Key]);
+    assert_eq!(items, Inventory::from([Item::Dagger, Item::Key, Item::Key]));
+    assert_eq!(items, vec![Item::Key, Item::Key, Item::Dagger]);
+    assert_eq!((items.len(), items.count(Item::Key)), (3, 2));
+    assert!(!items.remove(Item::Gloves));
+    assert!(items.remove(Item::Key));
+    assert_eq!(items.to_vec(), [Item::Key, Item::Dagger]);
+
+    let stored: Inventory = serde_json::from_str(r#"["Dagger", "Key", "Dagger"]"#).unwrap();
+    assert_eq!(serde_json::to_string(&stored).unwrap(), r#"["Key","Dagger","Dagger"]"#);
+    assert_eq!(serde_json::from_str::<Inventory>(&serde_json::to_string(&stored).unwrap()).unwrap(), stored);
+    assert!(serde_json::from_str::<Inventory>(&serde_json::to_string(&vec![Item::Key; 256]).unwrap()).is_err());
+    assert!(Item::all().eq(Inventory::from_iter(Item::all()).iter()));
+}
+
+#[test]
+fn players_are_stored_in_seating_order() {
+    let s = teststate();
+    let json = serde_json::to_value(&s).unwrap();
+    let ids: Vec<_> = json["game"]["p"]["players"].as_array().unwrap().iter().map(|p| p[0].as_u64().unwrap()).collect();
+    assert_eq!(ids, [2, 1, 0, 7]);
+    assert_eq!(json["game"]["p"]["players"][0][1]["items"], serde_json::json!(["BagKey"]));
+    assert_eq!(serde_json::from_value::<State>(json).unwrap(), s);
+
+    let mut clone = s.clone();
+    let (sarah, gundla) = clone.game.p.player_pair_mut(SARAH, GUNDLA);
+    std::mem::swap(&mut sarah.items, &mut gundla.items);
+    assert_eq!(clone.game.p.player(SARAH).items, vec![Item::BagGoblet]);
+    assert_eq!(s.game.p.player(SARAH).items, vec![Item::BagKey]);
+}
+
+fn pass_round(s: &mut State) {
+    for p in [SARAH, GUNDLA, MARIE, ZACHARIAS] {
+        s.apply_command(p, Command::Pass).unwrap();
+    }
+}
+
+#[test]
+fn round_limit() {
+    let mut s = teststate();
+    let round_limit = RoundLimit { rounds: Some(1), rounds_after_stack: None, tiebreak: true };
+    s.game.config = Arc::new(GameConfig { round_limit, ..GameConfig::default() });
+    s.game.last_round = Some(1);
+    s.game.p.player_mut(MARIE).items.push(Item::Key);
+    assert_eq!(s.perspective(GUNDLA).last_round, Some(1));
+
+    // the bags don't count while there are items on the stack
+    let mut draw = s.clone();
+    pass_round(&mut s);
+    assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Tiebreak(Faction::Order) });
+    assert_eq!(s.game.turn_index, TurnIndex { round: 1, turn: 4 });
+    let standings = vec![
+        FactionStanding { faction: Faction::Order, held_items: 1, needed_items: 3 },
+        FactionStanding { faction: Faction::Brotherhood, held_items: 0, needed_items: 3 },
+    ];
+    let turn = PerspectiveTurnState::GameOver { winner: WinningFaction::Tiebreak(Faction::Order), evaluation: None, standings };
+    assert_eq!(s.perspective(GUNDLA).turn, turn);
+    assert_eq!(s.spectate().turn, turn);
+
+    draw.game.p.player_mut(ZACHARIAS).items.push(Item::Goblet);
+    pass_round(&mut draw);
+    assert_eq!(draw.turn, TurnState::GameOver { winner: WinningFaction::Draw });
+}
+
+#[test]
+fn round_limit_after_the_stack_ran_out() {
+    let mut s = teststate();
+    let round_limit = RoundLimit { rounds: None, rounds_after_stack: Some(1), tiebreak: false };
+    s.game.config = Arc::new(GameConfig { round_limit, ..GameConfig::default() });
+    s.game.item_stack.clear();
+    s.game.p.player_mut(MARIE).items.push(Item::Key);
+
+    // one more round after the one in which it ran out, and without a tiebreak it's a draw
+    pass_round(&mut s);
+    assert_eq!(s.turn, TurnState::WaitingForQuickblink(SARAH));
+    assert_eq!(s.perspective(SARAH).last_round, Some(2));
+    pass_round(&mut s);
+    assert_eq!(s.turn, TurnState::GameOver { winner: WinningFaction::Draw });
+    let standings = vec![
+        FactionStanding { faction: Faction::Order, held_items: 2, needed_items: 3 },
+        FactionStanding { faction: Faction::Brotherhood, held_items: 1, needed_items: 3 },
+    ];
+    assert_eq!(s.spectate().turn, PerspectiveTurnState::GameOver { winner: WinningFaction::Draw, evaluation: None, standings });
+}
+
+#[test]
+fn narration() {
+    let mut s = teststate();
+    assert_eq!(s.perspective(SARAH).narrate(Locale::English), "It is your turn to trade, attack, use your job or pass.");
+    assert_eq!(s.perspective(GUNDLA).narrate(Locale::German), "Sarah Mac Mullin ist am Zug; es wird auf Sarah Mac Mullin gewartet.");
+
+    s.apply_command(SARAH, Command::InitiateAttack { player: GUNDLA }).unwrap();
+    let p = s.perspective(MARIE);
+    let entry = &p.action_log.last().unwrap().entry;
+    assert_eq!(entry.narrate(Locale::English, &p.players), "Sarah Mac Mullin attacked Gundla von Hochberg.");
+    assert_eq!(entry.narrate(Locale::German, &p.players), "Sarah Mac Mullin hat Gundla von Hochberg angegriffen.");
+
+    let votes = [(MARIE, AttackSupport::Defend), (ZACHARIAS, AttackSupport::Attack)].into_iter().collect();
+    s.turn = TurnState::Attacking { attacker: SARAH, defender: GUNDLA, state: AttackState::ItemsOrJobs { votes, passed: HashSet::new(), buffs: Vec::new() } };
+    assert_eq!(s.perspective(MARIE).narrate(Locale::English), "Sarah Mac Mullin is attacking Gundla von Hochberg; you voted to support the defender; it is your turn to play an item or job.");
+    assert_eq!(s.perspective(GUNDLA).narrate(Locale::German), "Sarah Mac Mullin greift dich an; du bist an der Reihe, einen Gegenstand oder Beruf einzusetzen.");
+    s.apply_command(SARAH, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    assert_eq!(
+        s.perspective(SARAH).narrate(Locale::English),
+        "You are attacking Gundla von Hochberg; waiting for Gundla von Hochberg, Marie Sauniére and Bruder Zacharias.",
+    );
+    assert_eq!(
+        s.spectate().narrate(Locale::English),
+        "Sarah Mac Mullin is attacking Gundla von Hochberg; waiting for Gundla von Hochberg, Marie Sauniére and Bruder Zacharias.",
+    );
 }
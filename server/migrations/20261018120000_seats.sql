@@ -0,0 +1,46 @@
+-- Schema v2: players sit in numbered seats and pick their own names,
+-- the character is only decoration now.
+CREATE TABLE game_players_v2 (
+    gameid text NOT NULL,
+    steamid bigint NOT NULL,
+    seat integer NOT NULL,
+    name text NOT NULL,
+    player_character text,
+
+    PRIMARY KEY(gameid, steamid),
+    UNIQUE(gameid, seat),
+    UNIQUE(gameid, player_character)
+);
+
+-- the seat numbers have to match what the stored game states use (`Character::legacy_seat`)
+INSERT INTO game_players_v2(gameid, steamid, seat, name, player_character)
+SELECT gameid, steamid,
+    CASE player_character
+        WHEN 'Marie' THEN 0
+        WHEN 'Gundla' THEN 1
+        WHEN 'Sarah' THEN 2
+        WHEN 'Romana' THEN 3
+        WHEN 'Theodora' THEN 4
+        WHEN 'Basilius' THEN 5
+        WHEN 'Juan' THEN 6
+        WHEN 'Zacharias' THEN 7
+        WHEN 'Michel' THEN 8
+        WHEN 'Sinclair' THEN 9
+    END,
+    CASE player_character
+        WHEN 'Marie' THEN 'Marie Sauniére'
+        WHEN 'Gundla' THEN 'Gundla von Hochberg'
+        WHEN 'Sarah' THEN 'Sarah Mac Mullin'
+        WHEN 'Romana' THEN 'Romana Baranov'
+        WHEN 'Theodora' THEN 'Theodora Krayenborg'
+        WHEN 'Basilius' THEN 'Basilius Kartov'
+        WHEN 'Juan' THEN 'Juan Tirador'
+        WHEN 'Zacharias' THEN 'Bruder Zacharias'
+        WHEN 'Michel' THEN 'Michel de Molay'
+        WHEN 'Sinclair' THEN 'Sir Henry Sinclair'
+    END,
+    player_character
+FROM game_players;
+
+DROP TABLE game_players;
+ALTER TABLE game_players_v2 RENAME TO game_players;
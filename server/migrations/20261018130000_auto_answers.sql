@@ -0,0 +1,6 @@
+-- Schema v3: settings that belong to a player rather than to a game
+CREATE TABLE player_settings (
+    steamid bigint NOT NULL PRIMARY KEY,
+    -- `web_protocol::AutoAnswers` as JSON
+    auto_answers text NOT NULL
+);
@@ -0,0 +1,99 @@
+//! Answers prompts for players who have only one thing they could answer, if they asked for it.
+//!
+//! Answering those right away would tell everybody that the player had no choice,
+//! so every answer waits for a random, human-looking delay that never depends on the prompt.
+
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rand::Rng;
+use rocket::tokio::{self, sync::broadcast::{Sender, error::RecvError}};
+use sqlx::sqlite::SqlitePool;
+
+use web_protocol::{AutoAnswers, SeatId};
+
+use crate::{load_state, now, store_state};
+use crate::error::{Error, Result};
+
+/// How long an automatic answer waits, in milliseconds
+const DELAY: Range<u64> = 2000..7000;
+
+/// Seats that already have an answer on the way, by game
+type Scheduled = Arc<Mutex<HashSet<(String, SeatId)>>>;
+
+/// Looks for prompts to answer whenever a game changes, until the server shuts down.
+pub async fn run(db: SqlitePool, queue: Sender<String>) {
+    let mut rx = queue.subscribe();
+    let scheduled = Scheduled::default();
+    loop {
+        match rx.recv().await {
+            Ok(id) => {
+                if let Err(e) = schedule(&db, &queue, &scheduled, id).await {
+                    eprintln!("Could not schedule automatic answers: {:?}", e);
+                }
+            }
+            Err(RecvError::Lagged(_)) => (),
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+async fn schedule(db: &SqlitePool, queue: &Sender<String>, scheduled: &Scheduled, id: String) -> Result<()> {
+    let state = match sqlx::query_scalar!("SELECT state FROM game_state WHERE gameid = ?", id).fetch_optional(db).await? {
+        Some(s) => load_state(&s)?,
+        None => return Ok(()),
+    };
+    let rows = sqlx::query!("SELECT game_players.seat, player_settings.auto_answers FROM game_players JOIN player_settings ON player_settings.steamid = game_players.steamid WHERE game_players.gameid = ?", id).fetch_all(db).await?;
+    for row in rows {
+        let seat = SeatId(row.seat as u32);
+        let answers: AutoAnswers = serde_json::from_str(&row.auto_answers)?;
+        if !state.only_answer(seat).is_some_and(|c| answers.allows(&c)) {
+            continue;
+        }
+        if scheduled.lock().unwrap().insert((id.clone(), seat)) {
+            tokio::spawn(answer_later(db.clone(), queue.clone(), scheduled.clone(), id.clone(), seat, answers));
+        }
+    }
+    Ok(())
+}
+
+async fn answer_later(db: SqlitePool, queue: Sender<String>, scheduled: Scheduled, id: String, seat: SeatId, answers: AutoAnswers) {
+    let delay = rand::thread_rng().gen_range(DELAY);
+    tokio::time::sleep(Duration::from_millis(delay)).await;
+    scheduled.lock().unwrap().remove(&(id.clone(), seat));
+    match answer(&db, &id, seat, answers).await {
+        Ok(true) => {
+            let _ = queue.send(id);
+        }
+        Ok(false) => (),
+        Err(e) => eprintln!("Could not answer for {:?} in {}: {:?}", seat, id, e),
+    }
+}
+
+/// How often an answer is tried again when somebody else changed the game at the same time
+const ATTEMPTS: usize = 5;
+
+/// Gives the answer, unless the player answered themselves in the meantime or now has a choice after all
+async fn answer(db: &SqlitePool, id: &str, seat: SeatId, answers: AutoAnswers) -> Result<bool> {
+    for _ in 0..ATTEMPTS {
+        let s = match sqlx::query_scalar!("SELECT state FROM game_state WHERE gameid = ?", id).fetch_optional(db).await? {
+            Some(s) => s,
+            None => return Ok(false),
+        };
+        let mut state = load_state(&s)?;
+        let c = match state.only_answer(seat) {
+            Some(c) if answers.allows(&c) => c,
+            _ => return Ok(false),
+        };
+        state.apply_command(seat, c)?;
+        state.stamp_log(now());
+        // players answer the same prompts at the same time, and their commands must not be overwritten
+        match store_state(db, id, &s, &state).await {
+            Err(Error::GameChanged) => continue,
+            stored => return stored.map(|()| true),
+        }
+    }
+    Ok(false)
+}
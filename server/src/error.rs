@@ -1,5 +1,5 @@
 use std::io::Cursor;
-use kutschfahrt::CommandError;
+use kutschfahrt::{CommandError, ConfigError, InvariantViolation};
 use thiserror::Error;
 use rocket::{Request, Response};
 use rocket::response::{Responder, self};
@@ -15,8 +15,18 @@ pub enum Error {
     Json(#[from] serde_json::Error),
     #[error("Invalid game command: {0}")]
     Command(#[from] CommandError),
+    #[error("Stored game is corrupt: {0}")]
+    Invariant(#[from] InvariantViolation),
+    #[error("Invalid game config: {0}")]
+    Config(#[from] ConfigError),
+    #[error("You are not playing in this game")]
+    NotInGame,
+    #[error("Players need a name")]
+    EmptyName,
     #[error("Command does not match game state")]
     CommandDoesNotMatchGameState,
+    #[error("The game changed in the meantime, please try again")]
+    GameChanged,
 }
 impl<'r> Responder<'r, 'static> for Error {
     fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
@@ -8,7 +8,7 @@ use rocket::response::stream::{EventStream, Event};
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
 
 use kutschfahrt::State as KutschfahrtState;
-use web_protocol::{GameCommand, MyState, GameInfo};
+use web_protocol::{AutoAnswers, Command, GameCommand, MyState, GameInfo, Seat, SeatId};
 
 mod login;
 use login::LoggedIn;
@@ -16,63 +16,90 @@ use login::LoggedIn;
 mod error;
 use error::{Result, Error};
 
+mod auto_answer;
 
 
+/// Milliseconds since the Unix epoch
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+fn load_state(s: &str) -> Result<KutschfahrtState> {
+    let state: KutschfahrtState = serde_json::from_str(s)?;
+    state.check_invariants()?;
+    Ok(state)
+}
+
+/// Everybody who joined the game so far, by seat
+async fn load_seats(db: &SqlitePool, id: &str) -> Result<Vec<Seat>> {
+    let rows = sqlx::query!("SELECT seat, name, player_character FROM game_players WHERE gameid = ? ORDER BY seat", id).fetch_all(db).await?;
+    Ok(rows.into_iter().map(|r| Seat {
+        id: SeatId(r.seat as u32),
+        name: r.name,
+        character: r.player_character.and_then(|c| c.parse().ok()),
+    }).collect())
+}
+
+async fn your_seat(db: &SqlitePool, id: &str, steamid: i64) -> Result<Option<SeatId>> {
+    let seat = sqlx::query_scalar!("SELECT seat FROM game_players WHERE gameid = ? AND steamid = ?", id, steamid).fetch_optional(db).await?;
+    Ok(seat.map(|s| SeatId(s as u32)))
+}
+
 #[rocket::get("/me", rank = 1)]
 async fn me_loggedin(db: &State<SqlitePool>, l: LoggedIn) -> Result<Json<MyState>> {
     let my_games = sqlx::query_scalar!("SELECT gameid FROM game_players WHERE steamid = ?", l.steamid).fetch_all(&**db).await?;
-    Ok(Json(MyState::LoggedIn { my_games }))
+    let auto_answers = match sqlx::query_scalar!("SELECT auto_answers FROM player_settings WHERE steamid = ?", l.steamid).fetch_optional(&**db).await? {
+        Some(a) => serde_json::from_str(&a)?,
+        None => AutoAnswers::default(),
+    };
+    Ok(Json(MyState::LoggedIn { my_games, auto_answers }))
 }
 #[rocket::get("/me", rank = 2)]
 fn me_loggedout() -> Json<MyState> { Json(MyState::LoggedOut) }
 
+#[rocket::post("/me/auto_answers", data = "<answers>")]
+async fn set_auto_answers(answers: Json<AutoAnswers>, db: &State<SqlitePool>, l: LoggedIn) -> Result<()> {
+    let answers = serde_json::to_string(&answers.into_inner())?;
+    sqlx::query!("INSERT INTO player_settings(steamid, auto_answers) VALUES (?, ?) ON CONFLICT(steamid) DO UPDATE SET auto_answers = excluded.auto_answers", l.steamid, answers).execute(&**db).await?;
+    Ok(())
+}
+
 
-#[rocket::get("/game/<id>")]
-async fn game_get(db: &State<SqlitePool>, id: String, l: LoggedIn) -> Result<Json<GameInfo>> {
-    let state = sqlx::query_scalar!("SELECT state FROM game_state WHERE gameid = ?", id).fetch_optional(&**db).await?;
-    let you = sqlx::query_scalar!("SELECT player_character FROM game_players WHERE gameid = ? AND steamid = ?", id, l.steamid).fetch_optional(&**db).await?;
-    let you = you.and_then(|x| x.parse().ok());
-    Ok(Json(match (state, you) {
+/// The game as `steamid` gets to see it
+async fn game_info(db: &SqlitePool, id: &str, steamid: i64) -> Result<GameInfo> {
+    let state = sqlx::query_scalar!("SELECT state FROM game_state WHERE gameid = ?", id).fetch_optional(db).await?;
+    let you = your_seat(db, id, steamid).await?;
+    Ok(match (state, you) {
         (None, you) => {
-            let players = sqlx::query_scalar!("SELECT player_character FROM game_players WHERE gameid = ?", id).fetch_all(&**db).await?;
-            let players = players.into_iter().map(|x| x.parse().unwrap()).collect();
+            let players = load_seats(db, id).await?;
             GameInfo::WaitingForPlayers { players, you }
         }
         (Some(s), Some(you)) => {
-            let state: KutschfahrtState = serde_json::from_str(&s)?;
+            let state = load_state(&s)?;
             GameInfo::Game(state.perspective(you))
         }
         (Some(s), None) => {
-            let state: KutschfahrtState = serde_json::from_str(&s)?;
+            let state = load_state(&s)?;
             GameInfo::Spectating(state.spectate())
         }
-    }))
+    })
+}
+
+#[rocket::get("/game/<id>")]
+async fn game_get(db: &State<SqlitePool>, id: String, l: LoggedIn) -> Result<Json<GameInfo>> {
+    Ok(Json(game_info(db, &id, l.steamid).await?))
 }
 
 #[rocket::get("/game/<id>/events")]
-async fn game_events(db: &State<SqlitePool>, id: String, l: LoggedIn, queue: &State<Sender<String>>, mut end: Shutdown) -> EventStream![] {
+async fn game_events(db: &State<SqlitePool>, id: String, l: LoggedIn, queue: &State<Sender<String>>, mut end: Shutdown) -> Result<EventStream![]> {
     let mut rx = queue.subscribe();
     let db = (*db).clone();
-    EventStream! {
+    let first = game_info(&db, &id, l.steamid).await?;
+    Ok(EventStream! {
+        yield Event::json(&first);
+        // only send what changed, so nobody learns when others answered in secret
+        let mut last = first;
         'outer: loop {
-            let state = sqlx::query_scalar!("SELECT state FROM game_state WHERE gameid = ?", id).fetch_optional(&db).await.unwrap();
-            let you = sqlx::query_scalar!("SELECT player_character FROM game_players WHERE gameid = ? AND steamid = ?", id, l.steamid).fetch_optional(&db).await.unwrap();
-            let you = you.and_then(|x| x.parse().ok());
-            let msg = match (state, you) {
-                (None, you) => {
-                    let players = sqlx::query_scalar!("SELECT player_character FROM game_players WHERE gameid = ?", id).fetch_all(&db).await.unwrap();
-                    let players = players.into_iter().map(|x| x.parse().unwrap()).collect();
-                    GameInfo::WaitingForPlayers { players, you }
-                }
-                (Some(s), Some(you)) => {
-                    let state: KutschfahrtState = serde_json::from_str(&s).unwrap();
-                    GameInfo::Game(state.perspective(you))
-                }
-                (Some(_), None) => unimplemented!("spectator mode"),
-            };
-
-            yield Event::json(&msg);
-
             // wait for updates to this gameid
             loop {
                 select! {
@@ -80,42 +107,70 @@ async fn game_events(db: &State<SqlitePool>, id: String, l: LoggedIn, queue: &St
                         Ok(i) if i == id => break,
                         Ok(_) => (),
                         Err(RecvError::Closed) => break 'outer,
-                        Err(RecvError::Lagged(_)) => continue 'outer,
+                        Err(RecvError::Lagged(_)) => break,
                     },
                     _ = &mut end => break 'outer,
                 };
             }
+
+            let msg = match game_info(&db, &id, l.steamid).await {
+                Ok(msg) => msg,
+                Err(e) => {
+                    // the client reconnects and gets the error from there
+                    eprintln!("{:?}", e);
+                    break 'outer;
+                }
+            };
+            if msg != last {
+                yield Event::json(&msg);
+                last = msg;
+            }
         }
-    }
+    })
 }
 
 #[rocket::post("/game/<id>", data = "<cmd>")]
 async fn game_post(cmd: Json<GameCommand>, db: &State<SqlitePool>, id: String, l: LoggedIn, queue: &State<Sender<String>>) -> Result<()> {
     let state = sqlx::query_scalar!("SELECT state FROM game_state WHERE gameid = ?", id).fetch_optional(&**db).await?;
-    match (cmd.into_inner(), state) {
-        (GameCommand::JoinGame(player), None) => {
-            let player = format!("{player:?}");
-            sqlx::query!("INSERT INTO game_players(gameid, steamid, player_character) VALUES (?, ?, ?)", id, l.steamid, player).execute(&**db).await?;
+    let cmd = match (cmd.into_inner(), &state) {
+        // leaving a game that already started means resigning
+        (GameCommand::LeaveGame, Some(_)) => GameCommand::Command(Command::Resign),
+        (cmd, _) => cmd,
+    };
+    match (cmd, state) {
+        (GameCommand::JoinGame { name, character }, None) => {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(Error::EmptyName);
+            }
+            let character = character.map(|c| format!("{c:?}"));
+            // the seat is picked by the same statement, so players joining at the same time get different ones
+            sqlx::query!("INSERT INTO game_players(gameid, steamid, seat, name, player_character) SELECT ?, ?, COALESCE(MAX(seat) + 1, 0), ?, ? FROM game_players WHERE gameid = ?", id, l.steamid, name, character, id).execute(&**db).await?;
         }
         (GameCommand::LeaveGame, None) => {
             sqlx::query!("DELETE FROM game_players WHERE gameid = ? AND steamid = ?", id, l.steamid).execute(&**db).await?;
         }
-        (GameCommand::StartGame, None) => {
-            let players = sqlx::query_scalar!("SELECT player_character FROM game_players WHERE gameid = ?", id).fetch_all(&**db).await?;
-            let players = players.into_iter().map(|x| x.parse().unwrap()).collect();
+        (GameCommand::StartGame(config), None) => {
+            let players = load_seats(db, &id).await?;
+            kutschfahrt::check_config(&config, players.len())?;
 
-            let state = KutschfahrtState::new(players, &mut rand::thread_rng());
+            let state = KutschfahrtState::new(players, config, rand::random());
 
             let state = serde_json::to_string(&state)?;
             sqlx::query!("INSERT INTO game_state(gameid, state) VALUES (?, ?)", id, state).execute(&**db).await?;
         }
         (GameCommand::Command(c), Some(s)) => {
-            let you = sqlx::query_scalar!("SELECT player_character FROM game_players WHERE gameid = ? AND steamid = ?", id, l.steamid).fetch_one(&**db).await?;
-            let you = you.parse().unwrap();
-            let mut state: KutschfahrtState = serde_json::from_str(&s)?;
+            let you = your_seat(db, &id, l.steamid).await?.ok_or(Error::NotInGame)?;
+            let mut state = load_state(&s)?;
             state.apply_command(you, c)?;
-            let state = serde_json::to_string(&state)?;
-            sqlx::query!("UPDATE game_state SET state = ? WHERE gameid = ?", state, id).execute(&**db).await?;
+            state.stamp_log(now());
+            store_state(db, &id, &s, &state).await?;
+        }
+        (GameCommand::Undo, Some(s)) => {
+            let you = your_seat(db, &id, l.steamid).await?.ok_or(Error::NotInGame)?;
+            let mut state = load_state(&s)?;
+            state.undo(you)?;
+            store_state(db, &id, &s, &state).await?;
         }
         _ => return Err(Error::CommandDoesNotMatchGameState),
     }
@@ -123,6 +178,17 @@ async fn game_post(cmd: Json<GameCommand>, db: &State<SqlitePool>, id: String, l
     Ok(())
 }
 
+/// Replaces the stored game `old` with `state`. Fails if somebody else changed the game since
+/// `old` was read, like the server answering for a player, so that nothing gets overwritten.
+async fn store_state(db: &SqlitePool, id: &str, old: &str, state: &KutschfahrtState) -> Result<()> {
+    let state = serde_json::to_string(state)?;
+    let updated = sqlx::query!("UPDATE game_state SET state = ? WHERE gameid = ? AND state = ?", state, id, old).execute(db).await?;
+    if updated.rows_affected() == 0 {
+        return Err(Error::GameChanged);
+    }
+    Ok(())
+}
+
 #[rocket::get("/<_path..>", rank = 100)]
 async fn spa_fallback(_path: std::path::PathBuf) -> NamedFile {
     NamedFile::open("../client/dist/index.html").await.unwrap()
@@ -138,9 +204,12 @@ async fn create_db_pool() -> Result<SqlitePool> {
 
 #[rocket::launch]
 async fn rocket() -> _ {
+    let db = create_db_pool().await.unwrap();
+    let queue = channel::<String>(1024).0;
+    rocket::tokio::spawn(auto_answer::run(db.clone(), queue.clone()));
     rocket::build()
-        .manage(create_db_pool().await.unwrap())
-        .manage(channel::<String>(1024).0)
+        .manage(db)
+        .manage(queue)
         .mount("/", FileServer::from("../client/dist"))
         .mount("/", rocket::routes![spa_fallback])
         .mount("/api/", rocket::routes![
@@ -154,6 +223,7 @@ async fn rocket() -> _ {
             game_post,
             me_loggedin,
             me_loggedout,
+            set_auto_answers,
         ])
 }
 
@@ -1,7 +1,5 @@
 #![allow(dead_code)]
 
-use rand::prelude::*;
-
 use kutschfahrt::*;
 use web_protocol::*;
 
@@ -16,42 +14,42 @@ fn main() {
     println!("{}", serde_json::to_string(&Command::ItemOrJob { buff: Some(BuffSource::Item(Item::Gloves)) }).unwrap());
     println!("{}", serde_json::to_string(&Command::ItemOrJob { buff: Some(BuffSource::Job(Job::Bodyguard)) }).unwrap());
     */
-    let mut rng = StdRng::seed_from_u64(45);
-    let mut state = State::new(vec![Player::Gundla, Player::Marie, Player::Zacharias, Player::Sarah].into_iter().collect(), &mut rng);
+    let [gundla, marie, zacharias, sarah] = [Character::Gundla, Character::Marie, Character::Zacharias, Character::Sarah].map(Character::legacy_seat);
+    let mut state = State::new([gundla, marie, zacharias, sarah].into_iter().map(Seat::legacy).collect(), GameConfig::default(), 45);
     dbg!(&state);
 
-    //state.apply_command(Player::Marie, Command::Pass).unwrap();
-
-    state.apply_command(Player::Zacharias, Command::InitiateAttack { player: Player::Marie }).unwrap();
-    state.apply_command(Player::Marie, Command::UsePriest { priest: false }).unwrap();
-    state.apply_command(Player::Gundla, Command::UsePriest { priest: false }).unwrap();
-    state.apply_command(Player::Zacharias, Command::UsePriest { priest: false }).unwrap();
-    state.apply_command(Player::Sarah, Command::UsePriest { priest: false }).unwrap();
-    state.apply_command(Player::Sarah, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
-    state.apply_command(Player::Gundla, Command::DeclareSupport { support: AttackSupport::Abstain }).unwrap();
-    state.apply_command(Player::Zacharias, Command::Hypnotize { target: None }).unwrap();
-    state.apply_command(Player::Marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    state.apply_command(Player::Gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    state.apply_command(Player::Zacharias, Command::ItemOrJob { buff: Some(BuffSource::Item(Item::PoisonRing)), target: None }).unwrap();
-    state.apply_command(Player::Sarah, Command::ItemOrJob { buff: None, target: None }).unwrap();
-    state.apply_command(Player::Zacharias, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    //state.apply_command(marie, Command::Pass).unwrap();
+
+    state.apply_command(zacharias, Command::InitiateAttack { player: marie }).unwrap();
+    state.apply_command(marie, Command::UsePriest { priest: false }).unwrap();
+    state.apply_command(gundla, Command::UsePriest { priest: false }).unwrap();
+    state.apply_command(zacharias, Command::UsePriest { priest: false }).unwrap();
+    state.apply_command(sarah, Command::UsePriest { priest: false }).unwrap();
+    state.apply_command(sarah, Command::DeclareSupport { support: AttackSupport::Attack }).unwrap();
+    state.apply_command(gundla, Command::DeclareSupport { support: AttackSupport::Abstain }).unwrap();
+    state.apply_command(zacharias, Command::Hypnotize { target: None }).unwrap();
+    state.apply_command(marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    state.apply_command(gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    state.apply_command(zacharias, Command::ItemOrJob { buff: Some(BuffSource::Item(Item::PoisonRing)), target: None }).unwrap();
+    state.apply_command(sarah, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    state.apply_command(zacharias, Command::ItemOrJob { buff: None, target: None }).unwrap();
     println!("{:#?}", state);
-    state.apply_command(Player::Marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    state.apply_command(marie, Command::ItemOrJob { buff: None, target: None }).unwrap();
     println!("{:#?}", state);
-    state.apply_command(Player::Gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
+    state.apply_command(gundla, Command::ItemOrJob { buff: None, target: None }).unwrap();
 
-    state.apply_command(Player::Zacharias, Command::ClaimReward { steal_items: false }).unwrap();
-    state.apply_command(Player::Zacharias, Command::DoneLookingAtThings).unwrap();
+    state.apply_command(zacharias, Command::ClaimReward { steal_items: false }).unwrap();
+    state.apply_command(zacharias, Command::DoneLookingAtThings).unwrap();
 
-    state.apply_command(Player::Sarah, Command::OfferTrade { target: Player::Zacharias, item: Item::Goblet }).unwrap();
-    state.apply_command(Player::Zacharias, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+    state.apply_command(sarah, Command::OfferTrade { target: zacharias, item: Item::Goblet }).unwrap();
+    state.apply_command(zacharias, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
 
-    state.apply_command(Player::Gundla, Command::OfferTrade { target: Player::Sarah, item: Item::BagGoblet }).unwrap();
-    state.apply_command(Player::Sarah, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
+    state.apply_command(gundla, Command::OfferTrade { target: sarah, item: Item::BagGoblet }).unwrap();
+    state.apply_command(sarah, Command::AcceptTrade { item: Item::PoisonRing }).unwrap();
     println!("{:#?}", state);
-    //state.apply_command(Player::Sarah, Command::RejectTrade).unwrap();
+    //state.apply_command(sarah, Command::RejectTrade).unwrap();
 
-    state.apply_command(Player::Marie, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![] } }).unwrap();
+    state.apply_command(marie, Command::AnnounceVictory { flavor: VictoryFlavor::Normal { teammates: vec![] } }).unwrap();
 
     assert_eq!(state.turn, TurnState::GameOver { winner: WinningFaction::Normal(Faction::Order) });
 }
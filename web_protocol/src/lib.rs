@@ -1,33 +1,110 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
 use std::fmt::Display;
+use std::iter::FromIterator;
 use serde::{Serialize, Deserialize};
 
+mod narrate;
+pub use narrate::Locale;
+
 
 #[derive(Serialize, Deserialize)]
 pub enum MyState {
     LoggedIn {
         my_games: Vec<String>,
+        #[serde(default)]
+        auto_answers: AutoAnswers,
     },
     LoggedOut,
 }
+/// Prompts the server answers for you when there is only one thing you could answer.
+///
+/// It waits for a random delay first, the same for every prompt, so nobody can tell from the timing.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(default)]
+pub struct AutoAnswers {
+    /// not using the Priest
+    pub priest: bool,
+    /// passing when you have no item or job to use in a fight
+    pub items_or_jobs: bool,
+}
+impl AutoAnswers {
+    /// Whether `c` may be given for you, if it is your only legal command
+    pub fn allows(&self, c: &Command) -> bool {
+        match c {
+            Command::UsePriest { priest: false } => self.priest,
+            Command::ItemOrJob { buff: None, target: None } => self.items_or_jobs,
+            _ => false,
+        }
+    }
+}
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub enum GameInfo {
-    WaitingForPlayers { players: Vec<Player>, you: Option<Player> },
+    WaitingForPlayers { players: Vec<Seat>, you: Option<SeatId> },
     Game(Perspective),
     /// when a game has already started and you're not part of it
     Spectating(SpectatorPerspective),
 }
 #[derive(Serialize, Deserialize, Debug)]
 pub enum GameCommand {
-    JoinGame(Player),
+    JoinGame { name: String, character: Option<Character> },
     LeaveGame,
-    StartGame,
+    StartGame(GameConfig),
     Command(Command),
+    /// take back your last command
+    Undo,
 }
 
 
+/// Identifies a seat at the table. Everything during a game refers to players by their seat.
+#[derive(Debug, Serialize, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct SeatId(pub u32);
+
+/// Besides seat numbers, this also accepts the names of characters, which is what games
+/// stored before there were seats used instead (see `Character::legacy_seat`).
+impl<'de> Deserialize<'de> for SeatId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SeatIdVisitor;
+        impl<'de> serde::de::Visitor<'de> for SeatIdVisitor {
+            type Value = SeatId;
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "a seat number or the name of a character")
+            }
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<SeatId, E> {
+                u32::try_from(v).map(SeatId).map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+            }
+            // numbers end up as strings when they're used as keys of a map
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<SeatId, E> {
+                v.parse().map(SeatId)
+                    .or_else(|_| v.parse().map(Character::legacy_seat))
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+            }
+        }
+        deserializer.deserialize_any(SeatIdVisitor)
+    }
+}
+
+/// Who sits on a seat
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Seat {
+    pub id: SeatId,
+    pub name: String,
+    /// purely cosmetic, nobody has to pick one
+    pub character: Option<Character>,
+}
+impl Seat {
+    /// The seat a character had in games stored before there were seats
+    pub fn legacy(id: SeatId) -> Seat {
+        let character = Character::all().nth(id.0 as usize);
+        let name = character.map_or_else(|| format!("Seat {}", id.0 + 1), |c| c.to_string());
+        Seat { id, name, character }
+    }
+}
+
+/// The characters printed on the cards
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy, enum_utils::FromStr, enum_utils::IterVariants)]
-pub enum Player {
+pub enum Character {
     Marie,
     Gundla,
     Sarah,
@@ -39,24 +116,30 @@ pub enum Player {
     Michel,
     Sinclair,
 }
-impl Player {
-    pub fn all() -> impl Iterator<Item = Player> + Clone {
+impl Character {
+    pub fn all() -> impl Iterator<Item = Character> + Clone {
         Self::iter()
     }
+
+    /// Before there were seats, the characters themselves were the players.
+    /// Each of them is now seated by its position in `Character::all`.
+    pub fn legacy_seat(self) -> SeatId {
+        SeatId(Character::all().position(|c| c == self).unwrap() as u32)
+    }
 }
-impl std::fmt::Display for Player {
+impl std::fmt::Display for Character {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let name = match self {
-            Player::Marie => "Marie Sauniére",
-            Player::Gundla => "Gundla von Hochberg",
-            Player::Sarah => "Sarah Mac Mullin",
-            Player::Romana => "Romana Baranov",
-            Player::Theodora => "Theodora Krayenborg",
-            Player::Basilius => "Basilius Kartov",
-            Player::Juan => "Juan Tirador",
-            Player::Zacharias => "Bruder Zacharias",
-            Player::Michel => "Michel de Molay",
-            Player::Sinclair => "Sir Henry Sinclair",
+            Character::Marie => "Marie Sauniére",
+            Character::Gundla => "Gundla von Hochberg",
+            Character::Sarah => "Sarah Mac Mullin",
+            Character::Romana => "Romana Baranov",
+            Character::Theodora => "Theodora Krayenborg",
+            Character::Basilius => "Basilius Kartov",
+            Character::Juan => "Juan Tirador",
+            Character::Zacharias => "Bruder Zacharias",
+            Character::Michel => "Michel de Molay",
+            Character::Sinclair => "Sir Henry Sinclair",
         };
         write!(f, "{}", name)
     }
@@ -72,27 +155,93 @@ pub struct Perspective {
     pub players: Vec<PerspectivePlayer>,
 
     pub item_stack: usize,
-    pub action_log: Vec<ActionLogEntry>,
+    pub action_log: Vec<LoggedAction>,
     pub turn: PerspectiveTurnState,
+    /// which turn of the game this is
+    pub turn_index: TurnIndex,
+    /// the round after which the round limit ends the game, once that is known
+    pub last_round: Option<u32>,
+    /// everybody who can act right now, in seating order
+    pub pending_actors: Vec<SeatId>,
+    /// everything that was hidden, once the game is over
+    pub summary: Option<GameSummary>,
+    /// the standing of the fight and the buffs you could still use, while items and jobs are being played
+    pub attack_outlook: Option<AttackOutlook>,
+    /// everything you got to see that the others didn't, oldest first
+    pub journal: Vec<JournalEntry>,
+    /// what all of that tells you about the others
+    pub notebook: Notebook,
+    /// what you answered, while the others still answer in secret
+    pub sealed_answer: Option<Command>,
+    pub config: GameConfig,
+}
+impl Perspective {
+    /// The name `player` picked when they joined
+    pub fn name(&self, player: SeatId) -> &str {
+        self.players.iter().find(|p| p.player == player).map_or("?", |p| &p.name)
+    }
 }
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct PerspectivePlayer {
-    pub player: Player,
+    pub player: SeatId,
+    pub name: String,
+    pub character: Option<Character>,
     pub job: Option<Job>,
     pub item_count: usize,
+    /// whether they left the game, so that everything is answered for them
+    #[serde(default)]
+    pub resigned: bool,
 }
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub enum PerspectiveTurnState {
-    TurnStart { player: Player },
-    TurnEndPhase { player: Player },
-    DoingClairvoyant { player: Player, item_stack: Option<Vec<Item>> },
-    UnsuccessfulDiplomat { diplomat: Player, target: Player, inventory: Option<Vec<Item>> },
+    TurnStart { player: SeatId },
+    TurnEndPhase { player: SeatId },
+    DoingClairvoyant { player: SeatId, item_stack: Option<Vec<Item>> },
+    UnsuccessfulDiplomat { diplomat: SeatId, target: SeatId, inventory: Option<Vec<Item>> },
 
-    GameOver { winner: WinningFaction },
-    TradePending { offerer: Player, target: Player, item: Option<Item> },
-    ResolvingTradeTrigger { giver: Player, receiver: Player, trigger: PerspectiveTradeTriggerState }, // for sextant, item selections are cleared
-    Attacking { attacker: Player, defender: Player, state: PerspectiveAttackState }, // AttackState info ís always public
-    DonatingItem { donor: Player },
+    /// `evaluation` tells everyone why the game ended, as long as it ended with a victory announcement,
+    /// and `standings` how close each faction came, if the round limit ended it
+    GameOver {
+        winner: WinningFaction,
+        evaluation: Option<VictoryEvaluation>,
+        #[serde(default)]
+        standings: Vec<FactionStanding>,
+    },
+    TradePending { offerer: SeatId, target: SeatId, item: Option<Item> },
+    ResolvingTradeTrigger { giver: SeatId, receiver: SeatId, trigger: PerspectiveTradeTriggerState }, // for sextant, item selections are cleared
+    Attacking { attacker: SeatId, defender: SeatId, state: PerspectiveAttackState }, // AttackState info ís always public
+    DonatingItem { donor: SeatId },
+}
+/// How close a faction came to announcing victory when the round limit ended the game
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub struct FactionStanding {
+    pub faction: Faction,
+    /// the victory items its members held between them, bags only once the item stack was empty
+    pub held_items: usize,
+    /// how many they would have needed to announce
+    pub needed_items: usize,
+}
+impl FactionStanding {
+    pub fn missing_items(&self) -> usize {
+        self.needed_items.saturating_sub(self.held_items)
+    }
+}
+/// What everybody had at the end of a game.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct GameSummary {
+    /// in seating order
+    pub players: Vec<PlayerSummary>,
+    /// the next item to be drawn comes last
+    pub item_stack: Vec<Item>,
+    pub job_stack: Vec<Job>,
+}
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PlayerSummary {
+    pub player: SeatId,
+    pub faction: FactionKind,
+    /// every job they had, in order, so the last one is the one they ended with
+    pub jobs: Vec<Job>,
+    pub items: Vec<Item>,
 }
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub enum PerspectiveAttackState {
@@ -102,18 +251,18 @@ pub enum PerspectiveAttackState {
     FinishResolvingItems { target_items: Vec<Item> },
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
 pub enum FactionKind {
     Normal(Faction),
     ThreePlayer([Faction; 3]),
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
 pub struct PlayerState {
     pub faction: FactionKind,
     pub job: Job,
     pub job_is_visible: bool,
-    pub items: Vec<Item>,
+    pub items: Inventory,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -121,12 +270,22 @@ pub struct SpectatorPerspective {
     pub players: Vec<PerspectivePlayer>,
 
     pub item_stack: usize,
-    pub action_log: Vec<ActionLogEntry>,
+    pub action_log: Vec<LoggedAction>,
     pub turn: PerspectiveTurnState,
+    /// which turn of the game this is
+    pub turn_index: TurnIndex,
+    /// the round after which the round limit ends the game, once that is known
+    pub last_round: Option<u32>,
+    /// everybody who can act right now, in seating order
+    pub pending_actors: Vec<SeatId>,
+    /// everything that was hidden, once the game is over
+    pub summary: Option<GameSummary>,
+    pub config: GameConfig,
 }
 
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+/// New items go at the end, and the last one is `Inventory::LAST_KIND`, which sizes inventories.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, enum_utils::IterVariants)]
 pub enum Item {
     Key,
     Goblet,
@@ -146,6 +305,11 @@ pub enum Item {
     Tome, // trigger: trade occupation
     CoatOfArmorOfTheLoge
 }
+impl Item {
+    pub fn all() -> impl Iterator<Item = Item> + Clone {
+        Self::iter()
+    }
+}
 impl Display for Item {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
@@ -169,7 +333,111 @@ impl Display for Item {
         })
     }
 }
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+
+/// The items a player holds.
+///
+/// Nothing in the rules depends on the order items were picked up in, so an inventory only
+/// counts how many of each item it has. That keeps it small and `Copy`, which makes cloning
+/// game states cheap. It is (de)serialized as a plain list of items, like before it only counted
+/// them, but the list always comes out in the order of `Item::all` instead of the order the items
+/// came in. That change to the format is intended: lists in any order still load, and reading a
+/// stored inventory back gives the same items.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct Inventory {
+    counts: [u8; Inventory::KINDS],
+}
+// the last item sizes `Inventory::counts`, see `Item`
+const _: () = assert!(Item::CoatOfArmorOfTheLoge as usize == Inventory::LAST_KIND as usize);
+impl Inventory {
+    const KINDS: usize = Inventory::LAST_KIND as usize + 1;
+    const LAST_KIND: Item = Item::CoatOfArmorOfTheLoge;
+    /// No inventory can hold more copies of one item than this
+    pub const MAX_COPIES: usize = u8::MAX as usize;
+
+    pub const fn new() -> Inventory {
+        Inventory { counts: [0; Inventory::KINDS] }
+    }
+    pub fn len(&self) -> usize {
+        self.counts.iter().map(|&c| c as usize).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|&c| c == 0)
+    }
+    pub fn count(&self, item: Item) -> usize {
+        self.counts[item as usize] as usize
+    }
+    pub fn contains(&self, item: &Item) -> bool {
+        self.count(*item) > 0
+    }
+    pub fn push(&mut self, item: Item) {
+        let count = &mut self.counts[item as usize];
+        *count = count.checked_add(1).expect("more copies of an item than an inventory can hold");
+    }
+    /// Takes out one copy of `item`. Returns `false` if there was none.
+    pub fn remove(&mut self, item: Item) -> bool {
+        let count = &mut self.counts[item as usize];
+        if *count == 0 {
+            return false;
+        }
+        *count -= 1;
+        true
+    }
+    /// Every item, in the order of `Item::all`
+    pub fn iter(&self) -> impl Iterator<Item = Item> + Clone + '_ {
+        Item::all().zip(self.counts).flat_map(|(i, c)| std::iter::repeat_n(i, c as usize))
+    }
+    /// Every item, but only once no matter how many copies there are
+    pub fn kinds(&self) -> impl Iterator<Item = Item> + Clone + '_ {
+        Item::all().zip(self.counts).filter(|&(_, c)| c > 0).map(|(i, _)| i)
+    }
+    pub fn to_vec(&self) -> Vec<Item> {
+        self.iter().collect()
+    }
+}
+impl Extend<Item> for Inventory {
+    fn extend<T: IntoIterator<Item = Item>>(&mut self, items: T) {
+        for i in items {
+            self.push(i);
+        }
+    }
+}
+impl FromIterator<Item> for Inventory {
+    fn from_iter<T: IntoIterator<Item = Item>>(items: T) -> Inventory {
+        let mut inventory = Inventory::new();
+        inventory.extend(items);
+        inventory
+    }
+}
+impl<const N: usize> From<[Item; N]> for Inventory {
+    fn from(items: [Item; N]) -> Inventory {
+        items.iter().copied().collect()
+    }
+}
+/// Whether the inventory holds exactly these items, in whatever order
+impl PartialEq<Vec<Item>> for Inventory {
+    fn eq(&self, items: &Vec<Item>) -> bool {
+        items.len() == self.len() && Item::all().all(|i| items.iter().filter(|&&x| x == i).count() == self.count(i))
+    }
+}
+impl Serialize for Inventory {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+impl<'de> Deserialize<'de> for Inventory {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let items = Vec::<Item>::deserialize(deserializer)?;
+        let mut inventory = Inventory::new();
+        for i in items {
+            if inventory.count(i) == Inventory::MAX_COPIES {
+                return Err(serde::de::Error::custom(format!("more than {} copies of {:?}", Inventory::MAX_COPIES, i)));
+            }
+            inventory.push(i);
+        }
+        Ok(inventory)
+    }
+}
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, enum_utils::IterVariants)]
 pub enum Job {
     Thug,
     GrandMaster,
@@ -182,6 +450,11 @@ pub enum Job {
     Diplomat,
     Clairvoyant,
 }
+impl Job {
+    pub fn all() -> impl Iterator<Item = Job> + Clone {
+        Self::iter()
+    }
+}
 impl std::fmt::Display for Job {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = match self {
@@ -204,7 +477,11 @@ impl std::fmt::Display for Job {
 pub enum WinningFaction {
     Normal(Faction),
     /// Loge
-    Traitor(Player),
+    Traitor(SeatId),
+    /// the round limit ended the game and neither faction came closer to victory
+    Draw,
+    /// the round limit ended the game and this faction came closer to victory
+    Tiebreak(Faction),
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
@@ -214,59 +491,59 @@ pub enum Faction {
 }
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum TurnState {
-    WaitingForQuickblink(Player),
+    WaitingForQuickblink(SeatId),
     /// for end-of-turn clairvoyant and diplomat
-    WaitingForEndTurn(Player),
+    WaitingForEndTurn(SeatId),
 
     // job actions are currently only possible at turn start
-    DoingClairvoyant { clairvoyant: Player, next: Player },
-    UnsuccessfulDiplomat { diplomat: Player, target: Player },
+    DoingClairvoyant { clairvoyant: SeatId, next: SeatId },
+    UnsuccessfulDiplomat { diplomat: SeatId, target: SeatId },
 
     GameOver { winner: WinningFaction },
     TradePending {
-        offerer: Player,
-        target: Player,
+        offerer: SeatId,
+        target: SeatId,
         item: Item,
     },
     ResolvingTradeTrigger {
-        giver: Player,
-        receiver: Player,
+        giver: SeatId,
+        receiver: SeatId,
         trigger: TradeTriggerState,
         next_state: FollowupState,
     },
     Attacking {
-        attacker: Player,
-        defender: Player,
+        attacker: SeatId,
+        defender: SeatId,
         state: AttackState,
     },
-    DonatingItem { donor: Player, followup: FollowupState },
+    DonatingItem { donor: SeatId, followup: FollowupState },
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum FollowupState {
     State(Box<TurnState>),
     TradeTriggers {
-        giver: Player,
-        receiver: Player,
+        giver: SeatId,
+        receiver: SeatId,
         item: Item,
         next_state: Box<TurnState>,
     },
 }
 impl FollowupState {
-    pub fn end_phase(player: Player) -> Self {
+    pub fn end_phase(player: SeatId) -> Self {
         FollowupState::State(Box::new(TurnState::WaitingForEndTurn(player)))
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum AttackState {
-    WaitingForPriest { passed: HashSet<Player> },
-    PayingPriest { priest: Player },
-    DeclaringSupport(HashMap<Player, AttackSupport>),
-    WaitingForHypnotizer(HashMap<Player, AttackSupport>),
+    WaitingForPriest { passed: HashSet<SeatId> },
+    PayingPriest { priest: SeatId },
+    DeclaringSupport(HashMap<SeatId, AttackSupport>),
+    WaitingForHypnotizer(HashMap<SeatId, AttackSupport>),
     ItemsOrJobs {
-        votes: HashMap<Player, AttackSupport>,
-        passed: HashSet<Player>,
+        votes: HashMap<SeatId, AttackSupport>,
+        passed: HashSet<SeatId>,
         buffs: Vec<Buff>,
     },
     Resolving {
@@ -288,7 +565,7 @@ pub type BuffScore = i8;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct Buff {
-    pub user: Player,
+    pub user: SeatId,
     pub source: BuffSource,
     pub raw_score: BuffScore, // Raw score is twice actual strength and 1 if it breaks ties
 }
@@ -315,12 +592,57 @@ impl AttackSupport {
     }
 }
 
+/// Both sides' strength in a fight, in raw scores like `Buff::raw_score`.
+/// The attacker and the defender count for themselves.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub struct AttackTally {
+    pub attack: BuffScore,
+    pub defense: BuffScore,
+}
+impl AttackTally {
+    pub fn new(votes: &HashMap<SeatId, AttackSupport>, buffs: &[Buff]) -> AttackTally {
+        let scores = buffs.iter().map(|b| b.raw_score).chain(votes.values().map(|v| v.vote_value()));
+        let (attack, defense) = scores.fold((2, 2), |(a, d), x| if x > 0 { (a + x, d) } else { (a, d - x) });
+        AttackTally { attack, defense }
+    }
+    /// Who wins if everybody passes now. Nobody does on a tie, the attacker draws an item instead.
+    pub fn winner(&self) -> Option<AttackWinner> {
+        match self.attack.cmp(&self.defense) {
+            std::cmp::Ordering::Greater => Some(AttackWinner::Attacker),
+            std::cmp::Ordering::Less => Some(AttackWinner::Defender),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+}
+/// What happens to a fight when a buff is used
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub enum BuffOutcome {
+    /// the fight goes on with this tally (the Duelist sends everybody's supporters home)
+    Tally(AttackTally),
+    /// the Doctor ends the fight without a winner
+    Ended,
+    /// the Poison Mixer decides the fight
+    Decided(AttackWinner),
+}
+/// A buff a player may still use in the current fight, and what it would do
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct BuffOption {
+    pub buff: BuffSource,
+    pub target: Option<SeatId>,
+    pub outcome: BuffOutcome,
+}
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct AttackOutlook {
+    pub tally: AttackTally,
+    pub options: Vec<BuffOption>,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum PerspectiveTradeTriggerState {
     Priviledge { items: Option<Vec<Item>> },
     Monocle { faction: Option<Faction>, three_player_faction_index: Option<usize> },
     Coat { available_jobs: Option<Vec<Job>> },
-    Sextant { item_selections: HashMap<Player, Item>, is_forward: Option<bool> },
+    Sextant { item_selections: HashMap<SeatId, Item>, is_forward: Option<bool> },
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -328,7 +650,7 @@ pub enum TradeTriggerState {
     Priviledge,
     Monocle { three_player_faction_index: Option<usize> },
     Coat,
-    Sextant { item_selections: HashMap<Player, Item>, is_forward: Option<bool> },
+    Sextant { item_selections: HashMap<SeatId, Item>, is_forward: Option<bool> },
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -337,45 +659,88 @@ pub enum Command {
     Pass,
     AnnounceVictory { flavor: VictoryFlavor },
 
-    UseDiplomat { target: Player, item: Item, return_item: Item },
+    UseDiplomat { target: SeatId, item: Item, return_item: Item },
     UseClairvoyant,
     ClairvoyantSetItems {
         /// always exactly two items unless the stack has fewer than two items in total
         top_items: Vec<Item>
     },
 
-    OfferTrade { target: Player, item: Item },
+    OfferTrade { target: SeatId, item: Item },
     RejectTrade,
     AcceptTrade { item: Item },
     PickNewJob { job: Job },
     SelectSextantItem { item: Item },
     SetSextantDirection { forward: bool },
 
-    InitiateAttack { player: Player },
+    InitiateAttack { player: SeatId },
     UsePriest { priest: bool },
     PayPriest { item: Item },
     DeclareSupport { support: AttackSupport },
-    Hypnotize { target: Option<Player> },
+    Hypnotize { target: Option<SeatId> },
     ItemOrJob {
         buff: Option<BuffSource>, // None is passing
-        target: Option<Player> // Only for poison mixer atm, will have additional uses in expansion
+        target: Option<SeatId> // Only for poison mixer atm, will have additional uses in expansion
     },
     ClaimReward { steal_items: bool },
     StealItem { item: Item, give_back: Option<Item> },
 
-    DonateItem { target: Player, item: Item },
+    DonateItem { target: SeatId, item: Item },
     DoneLookingAtThings,
 
     ThreePlayerSelectFactionIndex { index: usize },
+
+    /// leave the game for good, at any time
+    Resign,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum VictoryFlavor {
-    Normal { teammates: Vec<Player> },
+    Normal { teammates: Vec<SeatId> },
     Loge,
 }
 
+/// Everything that decides how a victory announcement turns out.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct VictoryEvaluation {
+    pub announcer: SeatId,
+    pub flavor: VictoryFlavor,
+    /// the announcer's faction, which is who the announcement is for
+    pub faction: Faction,
+    /// the items that count, bags only once the item stack is empty
+    pub required_items: Vec<Item>,
+    /// how many of them the team needs between them
+    pub needed_items: usize,
+    /// whether the announcer's faction has fewer members than the other one, which lowers `needed_items`
+    pub minority: bool,
+    /// the announcer first, then the teammates in the order they were named
+    pub contributions: Vec<VictoryContribution>,
+    /// nobody holding the Black Pearl may announce
+    pub black_pearl: bool,
+    /// for Loge announcements, whether the announcer holds the Coat of Armor of the Loge
+    pub loge_coat: Option<bool>,
+    /// `None` if the announcement isn't allowed at all
+    pub winner: Option<WinningFaction>,
+}
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct VictoryContribution {
+    pub player: SeatId,
+    /// the required items they hold
+    pub items: Vec<Item>,
+    pub same_faction: bool,
+}
+impl VictoryEvaluation {
+    pub fn total_items(&self) -> usize {
+        self.contributions.iter().map(|c| c.items.len()).sum()
+    }
+    /// Every member of the team is on the announcer's faction and brings at least one of the
+    /// required items, and together they have enough of them.
+    pub fn team_qualifies(&self) -> bool {
+        self.contributions.iter().all(|c| c.same_faction && !c.items.is_empty()) && self.total_items() >= self.needed_items
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum AttackRole {
@@ -384,21 +749,74 @@ pub enum AttackRole {
     AttackSupport(AttackSupport)
 }
 
+/// Who may be picked as the `target` of a buff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuffTarget {
+    /// the buff doesn't take a target
+    Nobody,
+    /// the attacker or the defender, which the fighters themselves can't do
+    Fighter,
+}
+
+/// What happens when a buff is used in a fight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuffEffect {
+    /// adds its score to the fight, once per fight
+    Score,
+    /// like `Score`, but also sends the supporters home, dropping their votes and buffs
+    SendSupportersHome,
+    /// ends the fight without a winner
+    EndFight,
+    /// the target wins the fight
+    DecideFight,
+}
+
+/// How an item or job works in a fight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuffRule {
+    pub effect: BuffEffect,
+    pub target: BuffTarget,
+    /// who may use it, and the raw score it adds for them
+    pub users: &'static [(AttackRole, BuffScore)],
+}
+
+impl BuffRule {
+    pub fn score(&self, user_type: AttackRole) -> Option<BuffScore> {
+        self.users.iter().find(|&&(role, _)| role == user_type).map(|&(_, score)| score)
+    }
+}
+
+const SUPPORTERS: &[(AttackRole, BuffScore)] = &[
+    (AttackRole::AttackSupport(AttackSupport::Attack), 0),
+    (AttackRole::AttackSupport(AttackSupport::Defend), 0),
+    (AttackRole::AttackSupport(AttackSupport::Abstain), 0),
+];
+const ANYONE: &[(AttackRole, BuffScore)] = &[
+    (AttackRole::Attacker, 0),
+    (AttackRole::Defender, 0),
+    (AttackRole::AttackSupport(AttackSupport::Attack), 0),
+    (AttackRole::AttackSupport(AttackSupport::Defend), 0),
+    (AttackRole::AttackSupport(AttackSupport::Abstain), 0),
+];
+
 impl BuffSource {
-    pub fn raw_score(&self, user_type: AttackRole) -> Option<BuffScore> {
-        match (self, user_type) {
-            (BuffSource::Item(Item::Dagger), AttackRole::Attacker) => Some(2),
-            (BuffSource::Job(Job::Thug), AttackRole::Attacker) => Some(2),
-            (BuffSource::Item(Item::Gloves), AttackRole::Defender) => Some(-2),
-            (BuffSource::Job(Job::GrandMaster), AttackRole::Defender) => Some(-2),
-            (BuffSource::Item(Item::PoisonRing), AttackRole::Defender) => Some(-1),
-            (BuffSource::Item(Item::PoisonRing), AttackRole::Attacker) => Some(1),
-            (BuffSource::Job(Job::Duelist), AttackRole::Attacker) => Some(2),
-            (BuffSource::Job(Job::Duelist), AttackRole::Defender) => Some(-2),
-            (BuffSource::Item(Item::CastingKnives), AttackRole::AttackSupport(AttackSupport::Attack)) => Some(2),
-            (BuffSource::Item(Item::Whip), AttackRole::AttackSupport(AttackSupport::Defend)) => Some(-2),
-            (BuffSource::Job(Job::Bodyguard), AttackRole::AttackSupport(AttackSupport::Attack)) => Some(2),
-            (BuffSource::Job(Job::Bodyguard), AttackRole::AttackSupport(AttackSupport::Defend)) => Some(-2),
+    /// How this works in a fight, `None` if it can't be used in one.
+    /// Items and jobs that only need one of the existing effects are added here and nowhere else.
+    pub fn rule(&self) -> Option<BuffRule> {
+        let rule = |effect, users: &'static [(AttackRole, BuffScore)]| Some(BuffRule { effect, target: BuffTarget::Nobody, users });
+        match self {
+            BuffSource::Item(Item::Dagger) | BuffSource::Job(Job::Thug) => rule(BuffEffect::Score, &[(AttackRole::Attacker, 2)]),
+            BuffSource::Item(Item::Gloves) | BuffSource::Job(Job::GrandMaster) => rule(BuffEffect::Score, &[(AttackRole::Defender, -2)]),
+            BuffSource::Item(Item::PoisonRing) => rule(BuffEffect::Score, &[(AttackRole::Attacker, 1), (AttackRole::Defender, -1)]),
+            BuffSource::Item(Item::CastingKnives) => rule(BuffEffect::Score, &[(AttackRole::AttackSupport(AttackSupport::Attack), 2)]),
+            BuffSource::Item(Item::Whip) => rule(BuffEffect::Score, &[(AttackRole::AttackSupport(AttackSupport::Defend), -2)]),
+            BuffSource::Job(Job::Bodyguard) => rule(BuffEffect::Score, &[
+                (AttackRole::AttackSupport(AttackSupport::Attack), 2),
+                (AttackRole::AttackSupport(AttackSupport::Defend), -2),
+            ]),
+            BuffSource::Job(Job::Duelist) => rule(BuffEffect::SendSupportersHome, &[(AttackRole::Attacker, 2), (AttackRole::Defender, -2)]),
+            BuffSource::Job(Job::Doctor) => rule(BuffEffect::EndFight, ANYONE),
+            BuffSource::Job(Job::PoisonMixer) => Some(BuffRule { effect: BuffEffect::DecideFight, target: BuffTarget::Fighter, users: SUPPORTERS }),
             _ => None
         }
     }
@@ -428,8 +846,12 @@ impl PlayerState {
         }
     }
 
+    pub fn can_use_job(&self, job: Job) -> bool {
+        self.job == job && !(self.job_is_visible && job.once())
+    }
+
     pub fn use_job(&mut self, job: Job) -> Result<(), JobUseError> {
-        if self.job == job && !(self.job_is_visible && job.once()) {
+        if self.can_use_job(job) {
             self.job_is_visible = true;
             Ok(())
         } else {
@@ -459,18 +881,206 @@ pub fn inventory_limit(players: usize) -> usize {
     }
 }
 
+/// The rules a game is played with. The default is the game as printed.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct GameConfig {
+    /// jobs that are dealt, all others are left out of the game
+    pub jobs: Vec<Job>,
+    /// items besides Keys and Goblets that may be dealt at the start (the bags always are)
+    pub start_items: Vec<Item>,
+    /// items that only ever come from the stack
+    pub stack_items: Vec<Item>,
+    pub keys: usize,
+    pub goblets: usize,
+    /// how many items a player may keep, by number of players
+    pub inventory_limits: BTreeMap<usize, usize>,
+    /// victory items needed to announce victory
+    pub victory_items: usize,
+    /// victory items needed by a faction with fewer members than the other one
+    pub minority_victory_items: usize,
+    pub loge_victory: bool,
+    /// the priest and the items and jobs of a fight are answered in secret, and only count once everybody answered
+    #[serde(default)]
+    pub sealed_answers: bool,
+    #[serde(default)]
+    pub round_limit: RoundLimit,
+}
+/// Ends games that nobody manages to win. The game ends after the earliest round that either limit allows,
+/// and a round always ends when play gets back to the first player.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+pub struct RoundLimit {
+    /// how many rounds are played at most
+    pub rounds: Option<u32>,
+    /// how many rounds are played after the one in which the item stack ran out
+    pub rounds_after_stack: Option<u32>,
+    /// whether the faction missing fewer victory items wins, instead of a draw
+    pub tiebreak: bool,
+}
+impl Default for GameConfig {
+    fn default() -> GameConfig {
+        GameConfig {
+            jobs: Job::all().collect(),
+            start_items: vec![
+                Item::BlackPearl,
+                Item::Dagger,
+                Item::Gloves,
+                Item::PoisonRing,
+                Item::CastingKnives,
+                Item::Whip,
+                Item::Priviledge,
+                Item::Monocle,
+            ],
+            stack_items: vec![
+                Item::BrokenMirror,
+                Item::Sextant,
+                Item::Coat,
+                Item::Tome,
+                Item::CoatOfArmorOfTheLoge,
+            ],
+            keys: 3,
+            goblets: 3,
+            inventory_limits: (3..=Character::all().count()).map(|n| (n, inventory_limit(n))).collect(),
+            victory_items: 3,
+            minority_victory_items: 2,
+            loge_victory: true,
+            sealed_answers: false,
+            round_limit: RoundLimit::default(),
+        }
+    }
+}
+impl GameConfig {
+    pub fn inventory_limit(&self, players: usize) -> usize {
+        self.inventory_limits.get(&players).copied().unwrap_or_else(|| inventory_limit(players))
+    }
+
+    /// How many copies of `item` are in the game
+    pub fn copies(&self, item: Item) -> usize {
+        match item {
+            Item::Key => self.keys,
+            Item::Goblet => self.goblets,
+            Item::BagKey | Item::BagGoblet => 1,
+            item => self.start_items.iter().chain(&self.stack_items).filter(|&&i| i == item).count(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum ActionLogEntry {
-    Pass { actor: Player },
-    AnnounceVictory { actor: Player },
+    Pass { actor: SeatId },
+    AnnounceVictory { actor: SeatId },
 
-    UseDiplomat { actor: Player, target: Player, item: Item, success: bool },
-    UseClairvoyant { actor: Player },
+    UseDiplomat { actor: SeatId, target: SeatId, item: Item, success: bool },
+    UseClairvoyant { actor: SeatId },
 
-    TradeOffer { offerer: Player, target: Player, accepted: bool },
-    Attack { attacker: Player, target: Player },
+    TradeOffer { offerer: SeatId, target: SeatId, accepted: bool },
+    Attack { attacker: SeatId, target: SeatId },
 
-    TradeTrigger { giver: Player, receiver: Player, item: Item },
-    DonateItem { giver: Player, receiver: Player },
+    TradeTrigger { giver: SeatId, receiver: SeatId, item: Item },
+    DonateItem { giver: SeatId, receiver: SeatId },
+    Resign { actor: SeatId },
+}
+impl ActionLogEntry {
+    /// Whoever did the logged thing
+    pub fn actor(&self) -> SeatId {
+        match *self {
+            ActionLogEntry::Pass { actor }
+            | ActionLogEntry::AnnounceVictory { actor }
+            | ActionLogEntry::UseDiplomat { actor, .. }
+            | ActionLogEntry::UseClairvoyant { actor }
+            | ActionLogEntry::Resign { actor } => actor,
+            ActionLogEntry::TradeOffer { offerer, .. } => offerer,
+            ActionLogEntry::Attack { attacker, .. } => attacker,
+            ActionLogEntry::TradeTrigger { giver, .. } | ActionLogEntry::DonateItem { giver, .. } => giver,
+        }
+    }
+}
+
+/// Counts the turns of a game. The first turn of the first round is `{ round: 1, turn: 1 }`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub struct TurnIndex {
+    /// a new round starts whenever play gets back around the table
+    pub round: u32,
+    /// counted over the whole game
+    pub turn: u32,
+}
+
+/// An action log entry, along with when it happened
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct LoggedAction {
+    /// counts the entries of the log, starting at 0
+    pub seq: u64,
+    pub turn: TurnIndex,
+    /// whoever issued the command that led to this entry
+    pub actor: SeatId,
+    /// when the server accepted that command, in milliseconds since the Unix epoch
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+    pub entry: ActionLogEntry,
+}
+
+/// Something only one player got to see
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Reveal {
+    /// the receiver's inventory, when passing them the Priviledge
+    Priviledge { player: SeatId, items: Vec<Item> },
+    /// the receiver's faction (card), when passing them the Monocle
+    Monocle { player: SeatId, faction: Faction, three_player_faction_index: Option<usize> },
+    /// the target's inventory, when they didn't have what the diplomat asked for
+    Diplomat { player: SeatId, items: Vec<Item> },
+    /// the faction (card) and job of an opponent you defeated
+    Credentials { player: SeatId, faction: Faction, three_player_faction_index: Option<usize>, job: Job },
+    /// the inventory of an opponent you defeated, before stealing from it
+    AttackItems { player: SeatId, items: Vec<Item> },
+    /// the item stack as the clairvoyant saw it, top item last
+    ItemStack { items: Vec<Item> },
+}
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct JournalEntry {
+    pub turn: TurnIndex,
+    /// how many action log entries were written before this, to tell what happened afterwards
+    pub log_position: u64,
+    pub reveal: Reveal,
+}
+
+/// What a player can tell about the hidden parts of the game from everything they got to see
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct Notebook {
+    /// how many ways of dealing the factions to the others are still possible
+    pub faction_worlds: u64,
+    /// how many ways of handing out jobs to the others are still possible
+    pub job_worlds: u64,
+    /// everybody, in seating order
+    pub players: Vec<PlayerOdds>,
+}
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PlayerOdds {
+    pub player: SeatId,
+    /// the chance that the player is on the side of the Order (the rest is the Brotherhood)
+    pub order: f64,
+    /// in three player games, the chance of each faction card being an Order card
+    pub order_cards: Option<[f64; 3]>,
+    /// the chance of having each job that is still possible
+    pub jobs: Vec<(Job, f64)>,
+    /// the chance of holding at least one of each item that is still possible
+    pub items: Vec<(Item, f64)>,
+}
+impl PlayerOdds {
+    /// The player's faction, if there is no doubt about it
+    pub fn faction(&self) -> Option<Faction> {
+        if self.order == 1.0 {
+            Some(Faction::Order)
+        } else if self.order == 0.0 {
+            Some(Faction::Brotherhood)
+        } else {
+            None
+        }
+    }
+    pub fn job(&self, job: Job) -> f64 {
+        self.jobs.iter().find(|&&(j, _)| j == job).map_or(0.0, |&(_, p)| p)
+    }
+    pub fn item(&self, item: Item) -> f64 {
+        self.items.iter().find(|&&(i, _)| i == item).map_or(0.0, |&(_, p)| p)
+    }
 }
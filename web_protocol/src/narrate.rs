@@ -0,0 +1,434 @@
+//! The game told in full sentences, for screen readers, text-only clients and chat messages.
+//!
+//! A perspective is narrated as one sentence of clauses: what is going on, what you did about it so far,
+//! and what you have to do now or who everybody is waiting for.
+
+use super::*;
+
+/// The languages the game can be narrated in
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+}
+impl Locale {
+    fn item(self, item: Item) -> String {
+        match self {
+            Locale::English => item.to_string(),
+            Locale::German => match item {
+                Item::Key => "Schlüssel",
+                Item::Goblet => "Kelch",
+                Item::BagKey => "Beutel (Schlüssel)",
+                Item::BagGoblet => "Beutel (Kelch)",
+                Item::BlackPearl => "Schwarze Perle",
+                Item::Dagger => "Dolch",
+                Item::Gloves => "Handschuhe",
+                Item::PoisonRing => "Giftring",
+                Item::CastingKnives => "Wurfmesser",
+                Item::Whip => "Peitsche",
+                Item::Priviledge => "Privileg",
+                Item::Monocle => "Monokel",
+                Item::BrokenMirror => "Zerbrochener Spiegel",
+                Item::Sextant => "Sextant",
+                Item::Coat => "Mantel",
+                Item::Tome => "Foliant",
+                Item::CoatOfArmorOfTheLoge => "Wappen der Loge",
+            }.to_owned(),
+        }
+    }
+    fn job(self, job: Job) -> String {
+        match self {
+            Locale::English => job.to_string(),
+            Locale::German => match job {
+                Job::Thug => "Schläger",
+                Job::GrandMaster => "Großmeister",
+                Job::Bodyguard => "Leibwächter",
+                Job::Duelist => "Duellant",
+                Job::PoisonMixer => "Giftmischer",
+                Job::Doctor => "Arzt",
+                Job::Priest => "Priester",
+                Job::Hypnotist => "Hypnotiseur",
+                Job::Diplomat => "Diplomat",
+                Job::Clairvoyant => "Hellseher",
+            }.to_owned(),
+        }
+    }
+    fn buff(self, buff: &BuffSource) -> String {
+        match (self, buff) {
+            (Locale::English, &BuffSource::Item(i)) => format!("the {}", self.item(i)),
+            (Locale::English, &BuffSource::Job(j)) => format!("the {}", self.job(j)),
+            (Locale::German, &BuffSource::Item(i)) => format!("den Gegenstand „{}“", self.item(i)),
+            (Locale::German, &BuffSource::Job(j)) => format!("den Beruf „{}“", self.job(j)),
+        }
+    }
+    fn faction(self, faction: Faction) -> &'static str {
+        match (self, faction) {
+            (Locale::English, Faction::Order) => "the Order",
+            (Locale::English, Faction::Brotherhood) => "the Brotherhood",
+            (Locale::German, Faction::Order) => "der Orden",
+            (Locale::German, Faction::Brotherhood) => "die Bruderschaft",
+        }
+    }
+    /// What `faction` is preceded by "belongs to" with
+    fn member_of(self, faction: Faction) -> &'static str {
+        match (self, faction) {
+            (Locale::English, f) => self.faction(f),
+            (Locale::German, Faction::Order) => "zum Orden",
+            (Locale::German, Faction::Brotherhood) => "zur Bruderschaft",
+        }
+    }
+    fn list(self, mut xs: Vec<String>) -> String {
+        let Some(last) = xs.pop() else {
+            return self.pick("nothing", "nichts").to_owned();
+        };
+        if xs.is_empty() {
+            return last;
+        }
+        format!("{} {} {}", xs.join(", "), self.pick("and", "und"), last)
+    }
+    fn items(self, items: &[Item]) -> String {
+        self.list(items.iter().map(|&i| self.item(i)).collect())
+    }
+    fn pick<T>(self, english: T, german: T) -> T {
+        match self {
+            Locale::English => english,
+            Locale::German => german,
+        }
+    }
+}
+
+/// Names the players, and talks to whoever is watching
+struct Narrator<'a> {
+    locale: Locale,
+    players: &'a [PerspectivePlayer],
+    /// `None` for spectators
+    you: Option<SeatId>,
+}
+impl Narrator<'_> {
+    fn name(&self, p: SeatId) -> String {
+        self.players.iter().find(|x| x.player == p).map_or("?", |x| &x.name).to_owned()
+    }
+    fn is_you(&self, p: SeatId) -> bool {
+        self.you == Some(p)
+    }
+    fn subject(&self, p: SeatId) -> String {
+        if self.is_you(p) { self.locale.pick("you", "du").to_owned() } else { self.name(p) }
+    }
+    fn object(&self, p: SeatId) -> String {
+        if self.is_you(p) { self.locale.pick("you", "dich").to_owned() } else { self.name(p) }
+    }
+    fn dative(&self, p: SeatId) -> String {
+        if self.is_you(p) { self.locale.pick("you", "dir").to_owned() } else { self.name(p) }
+    }
+    /// The verb that goes with `p`, which is `you_form` if that's who is watching
+    fn verb(&self, p: SeatId, you_form: &'static str, other: &'static str) -> &'static str {
+        if self.is_you(p) { you_form } else { other }
+    }
+    fn is(&self, p: SeatId) -> &'static str {
+        self.locale.pick(self.verb(p, "are", "is"), self.verb(p, "bist", "ist"))
+    }
+    fn has(&self, p: SeatId) -> &'static str {
+        self.locale.pick(self.verb(p, "have", "has"), self.verb(p, "hast", "hat"))
+    }
+
+    /// What is going on, as far as the viewer can tell
+    fn situation(&self, turn: &PerspectiveTurnState) -> Vec<String> {
+        let l = self.locale;
+        let mut clauses = Vec::new();
+        match *turn {
+            PerspectiveTurnState::TurnStart { player } => clauses.push(match l {
+                Locale::English if self.is_you(player) => "it is your turn".to_owned(),
+                Locale::English => format!("it is {}'s turn", self.name(player)),
+                Locale::German => format!("{} {} am Zug", self.subject(player), self.is(player)),
+            }),
+            PerspectiveTurnState::TurnEndPhase { player } => clauses.push(l.pick(
+                format!("{} {} ending {} turn", self.subject(player), self.is(player), self.verb(player, "your", "their")),
+                format!("{} {} dabei, den Zug zu beenden", self.subject(player), self.is(player)),
+            )),
+            PerspectiveTurnState::DoingClairvoyant { player, .. } => clauses.push(l.pick(
+                format!("{} {} rearranging the item stack with the Clairvoyant", self.subject(player), self.is(player)),
+                format!("{} {} mit dem Hellseher den Gegenstandsstapel neu", self.subject(player), self.verb(player, "ordnest", "ordnet")),
+            )),
+            PerspectiveTurnState::UnsuccessfulDiplomat { diplomat, target, ref inventory } => {
+                clauses.push(l.pick(
+                    format!("{} asked {} for an item {} did not have", self.subject(diplomat), self.object(target), self.verb(target, "you", "they")),
+                    format!("{} {} {} nach einem Gegenstand gefragt, den {} nicht {}", self.subject(diplomat), self.has(diplomat), self.object(target), self.subject(target), self.has(target)),
+                ));
+                if let Some(items) = inventory {
+                    clauses.push(self.holds(target, items));
+                }
+            }
+            PerspectiveTurnState::GameOver { winner, .. } => clauses.push(match winner {
+                WinningFaction::Normal(f) => l.pick(
+                    format!("the game is over and {} won", l.faction(f)),
+                    format!("das Spiel ist vorbei und {} hat gewonnen", l.faction(f)),
+                ),
+                WinningFaction::Traitor(p) => l.pick(
+                    format!("the game is over and {} won alone", self.subject(p)),
+                    format!("das Spiel ist vorbei und {} {} allein gewonnen", self.subject(p), self.has(p)),
+                ),
+                WinningFaction::Draw => l.pick("the game is over and ended in a draw", "das Spiel ist unentschieden ausgegangen").to_owned(),
+                WinningFaction::Tiebreak(f) => l.pick(
+                    format!("the round limit is reached and {} came closer to victory", l.faction(f)),
+                    format!("die Rundenbegrenzung ist erreicht und {} war näher am Sieg", l.faction(f)),
+                ),
+            }),
+            PerspectiveTurnState::TradePending { offerer, target, item } => {
+                clauses.push(l.pick(
+                    format!("{} {} offering {} a trade", self.subject(offerer), self.is(offerer), self.dative(target)),
+                    format!("{} {} {} einen Tausch an", self.subject(offerer), self.verb(offerer, "bietest", "bietet"), self.dative(target)),
+                ));
+                if let Some(item) = item {
+                    clauses.push(l.pick(
+                        format!("the item offered is the {}", l.item(item)),
+                        format!("angeboten wird der Gegenstand „{}“", l.item(item)),
+                    ));
+                }
+            }
+            PerspectiveTurnState::ResolvingTradeTrigger { giver, receiver, ref trigger } => {
+                let item = match trigger {
+                    PerspectiveTradeTriggerState::Priviledge { .. } => Item::Priviledge,
+                    PerspectiveTradeTriggerState::Monocle { .. } => Item::Monocle,
+                    PerspectiveTradeTriggerState::Coat { .. } => Item::Coat,
+                    PerspectiveTradeTriggerState::Sextant { .. } => Item::Sextant,
+                };
+                clauses.push(l.pick(
+                    format!("{} gave {} the {}", self.subject(giver), self.dative(receiver), l.item(item)),
+                    format!("{} {} {} den Gegenstand „{}“ gegeben", self.subject(giver), self.has(giver), self.dative(receiver), l.item(item)),
+                ));
+                match trigger {
+                    PerspectiveTradeTriggerState::Priviledge { items: Some(items) } => clauses.push(self.holds(receiver, items)),
+                    &PerspectiveTradeTriggerState::Monocle { faction: Some(f), .. } => clauses.push(self.belongs(receiver, f)),
+                    PerspectiveTradeTriggerState::Coat { available_jobs: Some(jobs) } => {
+                        let jobs = l.list(jobs.iter().map(|&j| l.job(j)).collect());
+                        clauses.push(l.pick(format!("the jobs to pick from are {}", jobs), format!("zur Wahl stehen {}", jobs)));
+                    }
+                    _ => (),
+                }
+            }
+            PerspectiveTurnState::Attacking { attacker, defender, ref state } => {
+                clauses.push(l.pick(
+                    format!("{} {} attacking {}", self.subject(attacker), self.is(attacker), self.object(defender)),
+                    format!("{} {} {} an", self.subject(attacker), self.verb(attacker, "greifst", "greift"), self.object(defender)),
+                ));
+                // only the winner gets to see the special states, so they are always about the viewer
+                let loser = if self.is_you(attacker) { defender } else { attacker };
+                let won = |winner: AttackWinner| match winner {
+                    AttackWinner::Attacker => attacker,
+                    AttackWinner::Defender => defender,
+                };
+                match state {
+                    PerspectiveAttackState::Normal(AttackState::WaitingForPriest { .. }) => clauses.push(
+                        l.pick("the others may stop the fight with the Priest", "die anderen können den Kampf mit dem Priester beenden").to_owned(),
+                    ),
+                    &PerspectiveAttackState::Normal(AttackState::PayingPriest { priest }) => clauses.push(l.pick(
+                        format!("{} stopped the fight with the Priest", self.subject(priest)),
+                        format!("{} {} den Kampf mit dem Priester beendet", self.subject(priest), self.has(priest)),
+                    )),
+                    PerspectiveAttackState::Normal(AttackState::DeclaringSupport(votes)) => {
+                        clauses.push(l.pick("the others are choosing sides", "die anderen wählen eine Seite").to_owned());
+                        clauses.extend(self.your_vote(votes));
+                    }
+                    PerspectiveAttackState::Normal(AttackState::WaitingForHypnotizer(votes)) => {
+                        clauses.push(l.pick("the attacker may still hypnotize somebody", "der Angreifer kann noch jemanden hypnotisieren").to_owned());
+                        clauses.extend(self.your_vote(votes));
+                    }
+                    PerspectiveAttackState::Normal(AttackState::ItemsOrJobs { votes, buffs, .. }) => {
+                        clauses.extend(self.your_vote(votes));
+                        for b in buffs {
+                            clauses.push(l.pick(
+                                format!("{} used {}", self.subject(b.user), l.buff(&b.source)),
+                                format!("{} {} {} eingesetzt", self.subject(b.user), self.has(b.user), l.buff(&b.source)),
+                            ));
+                        }
+                    }
+                    &PerspectiveAttackState::Normal(AttackState::Resolving { winner }) => clauses.push(self.won_fight(won(winner))),
+                    &PerspectiveAttackState::Normal(AttackState::FinishResolving { winner, .. }) => {
+                        let winner = won(winner);
+                        clauses.push(l.pick(
+                            format!("{} won the fight and {} taking the reward", self.subject(winner), self.is(winner)),
+                            format!("{} {} den Kampf gewonnen und {} sich die Belohnung", self.subject(winner), self.has(winner), self.verb(winner, "nimmst", "nimmt")),
+                        ));
+                    }
+                    PerspectiveAttackState::FinishResolvingNeedFactionIndex => clauses.push(self.won_fight(self.you.unwrap_or(attacker))),
+                    &PerspectiveAttackState::FinishResolvingCredentials { target_faction, target_job } => {
+                        clauses.push(self.won_fight(self.you.unwrap_or(attacker)));
+                        clauses.push(self.belongs(loser, target_faction));
+                        clauses.push(l.pick(
+                            format!("{} {} the {}", self.subject(loser), self.is(loser), l.job(target_job)),
+                            format!("{} {} den Beruf „{}“", self.subject(loser), self.has(loser), l.job(target_job)),
+                        ));
+                    }
+                    PerspectiveAttackState::FinishResolvingItems { target_items } => {
+                        clauses.push(self.won_fight(self.you.unwrap_or(attacker)));
+                        clauses.push(self.holds(loser, target_items));
+                    }
+                }
+            }
+            PerspectiveTurnState::DonatingItem { donor } => clauses.push(l.pick(
+                format!("{} {} too many items and {} to give one away", self.subject(donor), self.has(donor), self.has(donor)),
+                format!("{} {} zu viele Gegenstände und {} einen abgeben", self.subject(donor), self.has(donor), self.verb(donor, "musst", "muss")),
+            )),
+        }
+        clauses
+    }
+    fn holds(&self, p: SeatId, items: &[Item]) -> String {
+        let items = self.locale.items(items);
+        self.locale.pick(
+            format!("{} {} {}", self.subject(p), self.verb(p, "hold", "holds"), items),
+            format!("{} {} {}", self.subject(p), self.has(p), items),
+        )
+    }
+    fn belongs(&self, p: SeatId, faction: Faction) -> String {
+        self.locale.pick(
+            format!("{} {} to {}", self.subject(p), self.verb(p, "belong", "belongs"), self.locale.member_of(faction)),
+            format!("{} {} {}", self.subject(p), self.verb(p, "gehörst", "gehört"), self.locale.member_of(faction)),
+        )
+    }
+    fn won_fight(&self, p: SeatId) -> String {
+        self.locale.pick(
+            format!("{} won the fight", self.subject(p)),
+            format!("{} {} den Kampf gewonnen", self.subject(p), self.has(p)),
+        )
+    }
+    fn your_vote(&self, votes: &HashMap<SeatId, AttackSupport>) -> Option<String> {
+        let vote = votes.get(&self.you?)?;
+        Some(match vote {
+            AttackSupport::Attack => self.locale.pick("you voted to support the attacker", "du hast für den Angreifer gestimmt"),
+            AttackSupport::Defend => self.locale.pick("you voted to support the defender", "du hast für den Verteidiger gestimmt"),
+            AttackSupport::Abstain => self.locale.pick("you abstained", "du hast dich enthalten"),
+        }.to_owned())
+    }
+
+    /// What the viewer has to do, if anything
+    fn task(&self, turn: &PerspectiveTurnState) -> Option<&'static str> {
+        let l = self.locale;
+        let looking = l.pick("go on once you are done looking", "weiterzumachen, wenn du genug gesehen hast");
+        Some(match turn {
+            PerspectiveTurnState::TurnStart { .. } => l.pick("trade, attack, use your job or pass", "zu tauschen, anzugreifen, deinen Beruf einzusetzen oder zu passen"),
+            PerspectiveTurnState::TurnEndPhase { .. } => l.pick("use your job or end your turn", "deinen Beruf einzusetzen oder den Zug zu beenden"),
+            PerspectiveTurnState::DoingClairvoyant { .. } => l.pick("put two items back on top of the stack", "zwei Gegenstände oben auf den Stapel zu legen"),
+            PerspectiveTurnState::UnsuccessfulDiplomat { .. } => looking,
+            PerspectiveTurnState::GameOver { .. } => return None,
+            PerspectiveTurnState::TradePending { .. } => l.pick("accept or decline the trade", "den Tausch anzunehmen oder abzulehnen"),
+            PerspectiveTurnState::ResolvingTradeTrigger { trigger, .. } => match trigger {
+                PerspectiveTradeTriggerState::Sextant { is_forward: None, .. } => l.pick("choose which way the items are passed", "zu wählen, in welche Richtung die Gegenstände weitergegeben werden"),
+                PerspectiveTradeTriggerState::Sextant { .. } => l.pick("pick an item to pass on", "einen Gegenstand zum Weitergeben zu wählen"),
+                PerspectiveTradeTriggerState::Coat { .. } => l.pick("pick a new job", "einen neuen Beruf zu wählen"),
+                PerspectiveTradeTriggerState::Priviledge { .. } | PerspectiveTradeTriggerState::Monocle { .. } => looking,
+            },
+            PerspectiveTurnState::Attacking { state, .. } => match state {
+                PerspectiveAttackState::Normal(AttackState::WaitingForPriest { .. }) => l.pick("decide whether to use the Priest", "zu entscheiden, ob du den Priester einsetzt"),
+                PerspectiveAttackState::Normal(AttackState::PayingPriest { .. }) => l.pick("pay the Priest an item", "dem Priester einen Gegenstand zu geben"),
+                PerspectiveAttackState::Normal(AttackState::DeclaringSupport(_)) => l.pick("choose a side", "eine Seite zu wählen"),
+                PerspectiveAttackState::Normal(AttackState::WaitingForHypnotizer(_)) => l.pick("decide whether to hypnotize somebody", "zu entscheiden, ob du jemanden hypnotisierst"),
+                PerspectiveAttackState::Normal(AttackState::ItemsOrJobs { .. }) => l.pick("play an item or job", "einen Gegenstand oder Beruf einzusetzen"),
+                PerspectiveAttackState::Normal(AttackState::Resolving { .. } | AttackState::FinishResolving { .. }) => l.pick("claim your reward", "deine Belohnung zu wählen"),
+                PerspectiveAttackState::FinishResolvingNeedFactionIndex => l.pick("pick which of their faction cards to look at", "zu wählen, welche Gesinnungskarte du ansiehst"),
+                PerspectiveAttackState::FinishResolvingItems { .. } => l.pick("steal an item", "einen Gegenstand zu stehlen"),
+                PerspectiveAttackState::FinishResolvingCredentials { .. } => looking,
+            },
+            PerspectiveTurnState::DonatingItem { .. } => l.pick("give an item to somebody", "jemandem einen Gegenstand zu geben"),
+        })
+    }
+    fn waiting_for(&self, pending: &[SeatId]) -> Option<String> {
+        if pending.is_empty() {
+            return None;
+        }
+        let names = self.locale.list(pending.iter().map(|&p| self.name(p)).collect());
+        Some(self.locale.pick(format!("waiting for {}", names), format!("es wird auf {} gewartet", names)))
+    }
+
+    fn narrate(&self, turn: &PerspectiveTurnState, pending: &[SeatId], sealed: bool) -> String {
+        let l = self.locale;
+        let mut clauses = self.situation(turn);
+        if sealed {
+            clauses.push(l.pick("you answered in secret", "du hast geheim geantwortet").to_owned());
+        }
+        match self.you.filter(|p| pending.contains(p)).and_then(|_| self.task(turn)) {
+            Some(task) => {
+                if let PerspectiveTurnState::TurnStart { .. } = turn {
+                    // that it is your turn goes without saying
+                    clauses.clear();
+                }
+                clauses.push(l.pick(format!("it is your turn to {}", task), format!("du bist an der Reihe, {}", task)));
+            }
+            None => clauses.extend(self.waiting_for(pending)),
+        }
+        sentence(&clauses)
+    }
+}
+
+/// The clauses joined into one sentence
+fn sentence(clauses: &[String]) -> String {
+    let text = clauses.join("; ");
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}.", first.to_uppercase(), chars.as_str()),
+        None => text,
+    }
+}
+
+impl Perspective {
+    /// What is going on and what you have to do, in one sentence
+    pub fn narrate(&self, locale: Locale) -> String {
+        let you = self.players[self.your_player_index].player;
+        let narrator = Narrator { locale, players: &self.players, you: Some(you) };
+        narrator.narrate(&self.turn, &self.pending_actors, self.sealed_answer.is_some())
+    }
+}
+impl SpectatorPerspective {
+    /// What is going on and who everybody is waiting for, in one sentence
+    pub fn narrate(&self, locale: Locale) -> String {
+        let narrator = Narrator { locale, players: &self.players, you: None };
+        narrator.narrate(&self.turn, &self.pending_actors, false)
+    }
+}
+
+impl ActionLogEntry {
+    /// The entry as a sentence, naming everybody by the names in `players`
+    pub fn narrate(&self, locale: Locale, players: &[PerspectivePlayer]) -> String {
+        let n = Narrator { locale, players, you: None };
+        let name = |p| n.name(p);
+        let l = locale;
+        match *self {
+            ActionLogEntry::Pass { actor } => l.pick(format!("{} passed.", name(actor)), format!("{} hat gepasst.", name(actor))),
+            ActionLogEntry::AnnounceVictory { actor } => l.pick(format!("{} announced victory.", name(actor)), format!("{} hat den Sieg verkündet.", name(actor))),
+            ActionLogEntry::UseDiplomat { actor, target, item, success: true } => l.pick(
+                format!("{} asked {} for a {}. They exchanged items.", name(actor), name(target), l.item(item)),
+                format!("{} hat {} nach dem Gegenstand „{}“ gefragt. Sie haben Gegenstände getauscht.", name(actor), name(target), l.item(item)),
+            ),
+            ActionLogEntry::UseDiplomat { actor, target, item, success: false } => l.pick(
+                format!("{} asked {1} for a {2}, but {1} did not have one.", name(actor), name(target), l.item(item)),
+                format!("{} hat {1} nach dem Gegenstand „{2}“ gefragt, aber {1} hatte keinen.", name(actor), name(target), l.item(item)),
+            ),
+            ActionLogEntry::UseClairvoyant { actor } => l.pick(
+                format!("{} reordered the item stack.", name(actor)),
+                format!("{} hat den Gegenstandsstapel neu geordnet.", name(actor)),
+            ),
+            ActionLogEntry::TradeOffer { offerer, target, accepted } => l.pick(
+                format!("{} offered a trade to {}. The trade was {}.", name(offerer), name(target), if accepted { "accepted" } else { "declined" }),
+                format!("{} hat {} einen Tausch angeboten. Der Tausch wurde {}.", name(offerer), name(target), if accepted { "angenommen" } else { "abgelehnt" }),
+            ),
+            ActionLogEntry::Attack { attacker, target } => l.pick(
+                format!("{} attacked {}.", name(attacker), name(target)),
+                format!("{} hat {} angegriffen.", name(attacker), name(target)),
+            ),
+            // both bags are logged as the Goblet one, so nobody learns which it was
+            ActionLogEntry::TradeTrigger { giver, receiver, item: Item::BagGoblet } => l.pick(
+                format!("{} passed a Bag to {}.", name(giver), name(receiver)),
+                format!("{} hat {} einen Beutel weitergegeben.", name(giver), name(receiver)),
+            ),
+            ActionLogEntry::TradeTrigger { giver, receiver, item } => l.pick(
+                format!("{} passed a {} to {}.", name(giver), l.item(item), name(receiver)),
+                format!("{} hat {} den Gegenstand „{}“ weitergegeben.", name(giver), name(receiver), l.item(item)),
+            ),
+            ActionLogEntry::DonateItem { giver, receiver } => l.pick(
+                format!("{} donates an item to {}.", name(giver), name(receiver)),
+                format!("{} gibt {} einen Gegenstand ab.", name(giver), name(receiver)),
+            ),
+            ActionLogEntry::Resign { actor } => l.pick(format!("{} left the game.", name(actor)), format!("{} hat das Spiel verlassen.", name(actor))),
+        }
+    }
+}